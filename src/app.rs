@@ -1,16 +1,125 @@
 use anyhow::Result;
 use gtk4 as gtk;
 use gtk::prelude::*;
+use gtk::{gio, glib};
+use crate::state::AyahRef;
+use crate::{cli, data, uri};
 
 const APP_ID: &str = "org.hyprquran.app";
 
-pub fn run(init: Option<crate::state::AyahRef>) -> Result<()> {
-    let app = gtk::Application::new(Some(APP_ID), Default::default());
-    app.connect_activate(move |a| {
-        if let Err(e) = crate::ui::build_ui_with_init(a, init.clone()) {
-            eprintln!("{}", e);
+// The first instance parses the command line of every later launch too, so
+// `hyprquran 2:255` navigates the open window instead of starting another.
+// `quran:` links arrive as arguments from the desktop file or through `open`.
+// All reader options are defined here; main.rs only handles the commands and
+// options that print or write something instead.
+
+fn add_options(app: &gtk::Application) {
+    let option = |name: &str, short: u8, arg: glib::OptionArg, description: &str, arg_description: Option<&str>| {
+        app.add_main_option(name, glib::Char::from(short), glib::OptionFlags::NONE, arg, description, arg_description);
+    };
+    option("surah", b's', glib::OptionArg::Int, "Surah to open", Some("N"));
+    option("ayah", b'a', glib::OptionArg::Int, "Ayah to open within the surah", Some("N"));
+    option("ref", b'r', glib::OptionArg::String, "Reference to open, such as 2:255 or Yasin 12", Some("REFERENCE"));
+    option("today", 0, glib::OptionArg::None, "Open the verse of the day", None);
+    option("lang", b'l', glib::OptionArg::String, "Translation to show, as a language code or part of an edition name", Some("NAME"));
+    option("tafsir", 0, glib::OptionArg::Int64, "Tafsir source to open beside the text", Some("ID"));
+    option(glib::OPTION_REMAINING, 0, glib::OptionArg::StringArray, "", Some("[REFERENCE]"));
+}
+
+/// What a launch asks the open window to show.
+#[derive(Debug, Default)]
+struct Target {
+    at: Option<AyahRef>,
+    /// A language code or part of an edition name.
+    translation: Option<String>,
+    tafsir: Option<i64>,
+}
+
+fn target(options: &glib::VariantDict) -> Result<Target> {
+    let number = |key: &str| options.lookup::<i32>(key).ok().flatten().and_then(|n| u16::try_from(n).ok());
+    let reference = options
+        .lookup::<String>("ref")
+        .ok()
+        .flatten()
+        .or_else(|| options.lookup::<Vec<String>>(glib::OPTION_REMAINING).ok().flatten().map(|words| words.join(" ")));
+    let today = options.lookup::<bool>("today").ok().flatten().unwrap_or(false);
+    let at = cli::navigation_target(reference.as_deref(), number("surah"), number("ayah"), today)?;
+    let translation = options
+        .lookup::<String>("lang")
+        .ok()
+        .flatten()
+        .or_else(|| reference.filter(|r| uri::is_quran_uri(r)).and_then(|r| uri::parse(&r).ok()?.translation));
+    let tafsir = options.lookup::<i64>("tafsir").ok().flatten();
+    Ok(Target { at, translation, tafsir })
+}
+
+/// Shows `target` in the open window, or in a new one.
+fn navigate(app: &gtk::Application, target: Target) -> Result<()> {
+    match app.active_window() {
+        Some(window) => {
+            if let Some(at) = target.at {
+                let id = at.surah_id as i64 * 1000 + at.ayah_index as i64;
+                app.activate_action("show-ayah", Some(&id.to_variant()));
+            }
+            window.present();
+        }
+        None => crate::ui::build_ui_with_init(app, target.at)?,
+    }
+    if let Some(wanted) = target.translation {
+        match cli::resolve_translation(&wanted, &data::get_available_translations().unwrap_or_default()) {
+            Some(lang) => app.activate_action("show-translation", Some(&lang.to_variant())),
+            None => tracing::warn!("no translation matches {}", wanted),
+        }
+    }
+    if let Some(id) = target.tafsir {
+        app.activate_action("show-tafsir", Some(&id.to_variant()));
+    }
+    Ok(())
+}
+
+/// Runs the reader with `args` as its command line.
+pub fn run(args: Vec<String>) -> Result<()> {
+    let app = gtk::Application::new(Some(APP_ID), gio::ApplicationFlags::HANDLES_COMMAND_LINE | gio::ApplicationFlags::HANDLES_OPEN);
+    add_options(&app);
+    // Bad references are reported by the process that was given them.
+    app.connect_handle_local_options(|_, options| match target(options) {
+        Ok(_) => -1,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            1
+        }
+    });
+    app.connect_command_line(|app, cmdline| {
+        let target = target(&cmdline.options_dict()).unwrap_or_default();
+        match navigate(app, target) {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("{}", e);
+                1
+            }
+        }
+    });
+    app.connect_open(|app, files, _| {
+        // Several links open one after the other; the window ends on the last.
+        for file in files {
+            let link = file.uri();
+            let opened = uri::parse(&link).and_then(|u| navigate(app, Target { at: u.reference.first_ayah(), translation: u.translation, tafsir: None }));
+            if let Err(e) = opened {
+                tracing::warn!("opening {}: {:#}", link, e);
+            }
+        }
+    });
+    app.connect_activate(|app| match app.active_window() {
+        Some(window) => window.present(),
+        None => {
+            if let Err(e) = crate::ui::build_ui_with_init(app, None) {
+                eprintln!("{}", e);
+            }
         }
     });
-    app.run();
+    let code = app.run_with_args(&args);
+    if code != glib::ExitCode::SUCCESS {
+        std::process::exit(code.value());
+    }
     Ok(())
 }
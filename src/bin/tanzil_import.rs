@@ -1,5 +1,6 @@
 #![cfg(feature = "sqlite")]
 use anyhow::{anyhow, Result};
+use hyprquran::db::EditionKind;
 use rusqlite::params;
 use std::env;
 use std::fs::File;
@@ -24,6 +25,23 @@ fn parse_line(line: &str) -> Option<(u16, u16, String)> {
     None
 }
 
+fn parse_tafsir_line(line: &str) -> Option<(u16, u16, u16, String)> {
+    let l = line.trim_start_matches('\u{feff}');
+    if l.is_empty() { return None; }
+    // Whichever separator comes first; the text itself may contain the other.
+    let sep = l.chars().find(|c| matches!(c, '|' | '\t'))?;
+    let mut parts = l.splitn(3, sep);
+    let s = parts.next()?.trim().parse::<u16>().ok()?;
+    let range = parts.next()?.trim();
+    let t = parts.next()?.trim().to_string();
+    let (start, end) = match range.split_once('-') {
+        Some((a, b)) => (a.trim().parse::<u16>().ok()?, b.trim().parse::<u16>().ok()?),
+        None => { let a = range.parse::<u16>().ok()?; (a, a) }
+    };
+    if end < start { return None; }
+    Some((s, start, end, t))
+}
+
 fn default_surahs() -> Vec<(u16, &'static str, &'static str)> {
     let names_en = [
         "Al-Fatiha","Al-Baqarah","Ali 'Imran","An-Nisa","Al-Ma'idah","Al-An'am","Al-A'raf","Al-Anfal","At-Tawbah","Yunus","Hud","Yusuf","Ar-Ra'd","Ibrahim","Al-Hijr","An-Nahl","Al-Isra","Al-Kahf","Maryam","Ta-Ha","Al-Anbiya","Al-Hajj","Al-Mu'minun","An-Nur","Al-Furqan","Ash-Shu'ara","An-Naml","Al-Qasas","Al-'Ankabut","Ar-Rum","Luqman","As-Sajdah","Al-Ahzab","Saba'","Fatir","Ya-Sin","As-Saffat","Sad","Az-Zumar","Ghafir","Fussilat","Ash-Shura","Az-Zukhruf","Ad-Dukhan","Al-Jathiyah","Al-Ahqaf","Muhammad","Al-Fath","Al-Hujurat","Qaf","Adh-Dhariyat","At-Tur","An-Najm","Al-Qamar","Ar-Rahman","Al-Waqi'ah","Al-Hadid","Al-Mujadila","Al-Hashr","Al-Mumtahanah","As-Saff","Al-Jumu'ah","Al-Munafiqun","At-Taghabun","At-Talaq","At-Tahrim","Al-Mulk","Al-Qalam","Al-Haqqah","Al-Ma'arij","Nuh","Al-Jinn","Al-Muzzammil","Al-Muddathir","Al-Qiyamah","Al-Insan","Al-Mursalat","An-Naba","An-Nazi'at","Abasa","At-Takwir","Al-Infitar","Al-Mutaffifin","Al-Inshiqaq","Al-Buruj","At-Tariq","Al-A'la","Al-Ghashiyah","Al-Fajr","Al-Balad","Ash-Shams","Al-Layl","Ad-Duha","Ash-Sharh","At-Tin","Al-'Alaq","Al-Qadr","Al-Bayyinah","Az-Zalzalah","Al-'Adiyat","Al-Qari'ah","At-Takathur","Al-'Asr","Al-Humazah","Al-Fil","Quraysh","Al-Ma'un","Al-Kawthar","Al-Kafirun","An-Nasr","Al-Masad","Al-Ikhlas","Al-Falaq","An-Nas"
@@ -38,15 +56,31 @@ fn main() -> Result<()> {
     let mut args = env::args().skip(1);
     let mut text_path: Option<String> = None;
     let mut translations: Vec<(String, String, String)> = Vec::new();
+    let mut tafsirs: Vec<(String, String, String)> = Vec::new();
+    let mut transliterations: Vec<(String, String, String)> = Vec::new();
+    let mut mushaf_path: Option<String> = None;
     while let Some(a) = args.next() {
         match a.as_str() {
             "--text" => { text_path = args.next(); }
+            "--mushaf" => { mushaf_path = args.next(); }
             "--translation" => {
                 let lang = args.next().ok_or_else(|| anyhow!("lang"))?;
                 let name = args.next().ok_or_else(|| anyhow!("name"))?;
                 let path = args.next().ok_or_else(|| anyhow!("path"))?;
                 translations.push((lang, name, path));
             }
+            "--transliteration" => {
+                let lang = args.next().ok_or_else(|| anyhow!("lang"))?;
+                let name = args.next().ok_or_else(|| anyhow!("name"))?;
+                let path = args.next().ok_or_else(|| anyhow!("path"))?;
+                transliterations.push((lang, name, path));
+            }
+            "--tafsir" => {
+                let lang = args.next().ok_or_else(|| anyhow!("lang"))?;
+                let name = args.next().ok_or_else(|| anyhow!("name"))?;
+                let path = args.next().ok_or_else(|| anyhow!("path"))?;
+                tafsirs.push((lang, name, path));
+            }
             _ => {}
         }
     }
@@ -75,12 +109,65 @@ fn main() -> Result<()> {
             let l = line?;
             if let Some((s, y, t)) = parse_line(&l) {
                 let ayah_id: i64 = (s as i64) * 1000 + (y as i64);
+                let (t, notes) = hyprquran::footnotes::extract(&t);
                 conn.execute(
                     "INSERT INTO translated_ayah(ayah_id,trans_id,text) VALUES(?,?,?) ON CONFLICT(ayah_id,trans_id) DO UPDATE SET text=excluded.text",
                     params![ayah_id, trans_id, t],
                 )?;
+                for n in notes {
+                    hyprquran::db::upsert_translation_footnote(&conn, ayah_id, trans_id, &n.marker, &n.text)?;
+                }
+            }
+        }
+    }
+    for (lang, name, path) in transliterations {
+        let trans_id = hyprquran::db::upsert_edition(&conn, &name, &lang, EditionKind::Transliteration)?;
+        let f = File::open(path)?;
+        let rdr = BufReader::new(f);
+        for line in rdr.lines() {
+            let l = line?;
+            if let Some((s, y, t)) = parse_line(&l) {
+                let ayah_id: i64 = (s as i64) * 1000 + (y as i64);
+                hyprquran::db::upsert_translated_ayah(&conn, ayah_id, trans_id, &t)?;
+            }
+        }
+    }
+    for (lang, name, path) in tafsirs {
+        let tafsir_id = hyprquran::db::upsert_tafsir(&conn, &name, &lang)?;
+        let f = File::open(path)?;
+        let rdr = BufReader::new(f);
+        for line in rdr.lines() {
+            let l = line?;
+            if let Some((s, start, end, t)) = parse_tafsir_line(&l) {
+                let start_id: i64 = (s as i64) * 1000 + (start as i64);
+                let end_id: i64 = (s as i64) * 1000 + (end as i64);
+                hyprquran::db::upsert_tafsir_entry(&conn, tafsir_id, start_id, end_id, &t)?;
+            }
+        }
+    }
+    if let Some(p) = mushaf_path {
+        let f = File::open(p)?;
+        let rdr = BufReader::new(f);
+        for line in rdr.lines() {
+            let l = line?;
+            if let Some(spec) = hyprquran::mushaf::parse_line_spec(&l) {
+                hyprquran::db::upsert_mushaf_line(&conn, &spec)?;
             }
         }
     }
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tafsir_lines() {
+        assert_eq!(parse_tafsir_line("2|255|Ayat al-Kursi"), Some((2, 255, 255, "Ayat al-Kursi".to_string())));
+        assert_eq!(parse_tafsir_line("\u{feff}2\t1-5\tAlif Lam Mim | the letters"), Some((2, 1, 5, "Alif Lam Mim | the letters".to_string())));
+        assert_eq!(parse_tafsir_line("2|5-1|backwards"), None);
+        assert_eq!(parse_tafsir_line("2|x|text"), None);
+        assert_eq!(parse_tafsir_line(""), None);
+    }
+}
@@ -0,0 +1,307 @@
+use crate::citation::{self, CopyContent, CopyFormat, CopySettings};
+use crate::data::{self, AyahText};
+use crate::range::AyahRange;
+use crate::state::Surah;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// "Verse cards": an ayah or a short range drawn as an image for sharing, with
+// the Arabic, one translation and a citation. Drawing needs the `render`
+// feature; it works offscreen, so the CLI can make cards without a display.
+
+/// Longer passages no longer fit legibly on one card.
+pub const MAX_CARD_AYAT: usize = 10;
+
+const CARD_CITATION: &str = "{surah} {ref} ({edition})";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CardTemplate {
+    #[default]
+    Parchment,
+    Light,
+    Night,
+    Emerald,
+}
+
+impl CardTemplate {
+    pub const ALL: [CardTemplate; 4] = [CardTemplate::Parchment, CardTemplate::Light, CardTemplate::Night, CardTemplate::Emerald];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            CardTemplate::Parchment => "parchment",
+            CardTemplate::Light => "light",
+            CardTemplate::Night => "night",
+            CardTemplate::Emerald => "emerald",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CardTemplate::Parchment => "Parchment",
+            CardTemplate::Light => "Light",
+            CardTemplate::Night => "Night",
+            CardTemplate::Emerald => "Emerald",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.id() == id)
+    }
+
+    /// Background gradient top and bottom, text and accent colours.
+    pub fn colours(&self) -> [&'static str; 4] {
+        match self {
+            CardTemplate::Parchment => ["#f6ead0", "#e8d3a6", "#3b2a14", "#8a5a24"],
+            CardTemplate::Light => ["#ffffff", "#f1f1ee", "#1a1a1a", "#8a6d3b"],
+            CardTemplate::Night => ["#1f262c", "#0e1216", "#f2f2f2", "#c9a45c"],
+            CardTemplate::Emerald => ["#125a45", "#08332a", "#fdf6e3", "#e0c27a"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AspectRatio {
+    #[default]
+    Square,
+    Portrait,
+    Story,
+    Landscape,
+}
+
+impl AspectRatio {
+    pub const ALL: [AspectRatio; 4] = [AspectRatio::Square, AspectRatio::Portrait, AspectRatio::Story, AspectRatio::Landscape];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            AspectRatio::Square => "square",
+            AspectRatio::Portrait => "portrait",
+            AspectRatio::Story => "story",
+            AspectRatio::Landscape => "landscape",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AspectRatio::Square => "Square (1:1)",
+            AspectRatio::Portrait => "Portrait (4:5)",
+            AspectRatio::Story => "Story (9:16)",
+            AspectRatio::Landscape => "Landscape (16:9)",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|r| r.id() == id)
+    }
+
+    /// Size in pixels.
+    pub fn size(&self) -> (i32, i32) {
+        match self {
+            AspectRatio::Square => (1080, 1080),
+            AspectRatio::Portrait => (1080, 1350),
+            AspectRatio::Story => (1080, 1920),
+            AspectRatio::Landscape => (1920, 1080),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardFormat {
+    Png,
+    Svg,
+}
+
+impl CardFormat {
+    /// Picks the format from the file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "png" => Some(CardFormat::Png),
+            "svg" => Some(CardFormat::Svg),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CardOptions {
+    pub template: CardTemplate,
+    pub ratio: AspectRatio,
+    /// Translation shown under the Arabic; `None` makes an Arabic-only card.
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Card {
+    pub arabic: String,
+    pub translation: Option<String>,
+    pub citation: String,
+}
+
+impl Card {
+    /// `edition` is the `(lang, name)` of the translation to show.
+    pub fn new(range: &AyahRange, ayat: &[AyahText], surah: &str, edition: Option<(&str, &str)>) -> Self {
+        let text = |content| {
+            let settings = CopySettings { content, format: CopyFormat::Plain, cite: false, ..Default::default() };
+            let editions: Vec<(String, String)> = edition.iter().map(|(l, n)| (l.to_string(), n.to_string())).collect();
+            citation::format_passage(&settings, range, ayat, surah, &editions)
+        };
+        let translation = Some(text(CopyContent::Translation)).filter(|t| edition.is_some() && !t.is_empty());
+        let names: Vec<String> = edition.iter().filter(|_| translation.is_some()).map(|(_, n)| n.to_string()).collect();
+        Self { arabic: text(CopyContent::Arabic), translation, citation: citation::citation(CARD_CITATION, range, surah, &names) }
+    }
+
+    pub fn load(range: &AyahRange, lang: Option<&str>, surahs: &[Surah]) -> Result<Self> {
+        if range.len() > MAX_CARD_AYAT {
+            bail!("{} has {} ayat; a card holds at most {}", range, range.len(), MAX_CARD_AYAT);
+        }
+        let langs: Vec<String> = lang.iter().map(|l| l.to_string()).collect();
+        let ayat = data::load_ayat_in_range(range, &langs)?;
+        let name = lang.map(|l| {
+            let names = data::get_available_translations().unwrap_or_default();
+            names.into_iter().find(|(code, _)| code == l).map(|(_, n)| n).unwrap_or_else(|| l.to_string())
+        });
+        let surah = surahs.iter().find(|s| s.id == range.start.surah_id).map(|s| s.name_en.clone()).unwrap_or_default();
+        Ok(Self::new(range, &ayat, &surah, lang.zip(name.as_deref())))
+    }
+}
+
+/// The largest of 1.0, 0.95, 0.95², ... down to `min` at which `measure`
+/// (content height at that scale) fits in `available`.
+pub fn fit_scale(measure: impl Fn(f64) -> f64, available: f64, min: f64) -> f64 {
+    let mut scale = 1.0;
+    while scale * 0.95 >= min && measure(scale) > available {
+        scale *= 0.95;
+    }
+    scale
+}
+
+#[cfg(feature = "render")]
+fn rgb(hex: &str) -> (f64, f64, f64) {
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or("00"), 16).unwrap_or(0) as f64 / 255.0;
+    (channel(1), channel(3), channel(5))
+}
+
+/// Draws `card` filling a surface of `options.ratio.size()` pixels.
+#[cfg(feature = "render")]
+pub fn draw(cr: &pangocairo::cairo::Context, card: &Card, options: &CardOptions) -> Result<()> {
+    use crate::pdf::{arabic_family, text_layout};
+    use pangocairo::{cairo, pango};
+
+    let (w, h) = options.ratio.size();
+    let (w, h) = (w as f64, h as f64);
+    let [top, bottom, ink, accent] = options.template.colours().map(rgb);
+    let gradient = cairo::LinearGradient::new(0.0, 0.0, 0.0, h);
+    gradient.add_color_stop_rgb(0.0, top.0, top.1, top.2);
+    gradient.add_color_stop_rgb(1.0, bottom.0, bottom.1, bottom.2);
+    cr.set_source(&gradient)?;
+    cr.paint()?;
+    let inset = w.min(h) * 0.04;
+    cr.set_source_rgb(accent.0, accent.1, accent.2);
+    cr.set_line_width(w.min(h) * 0.004);
+    cr.rectangle(inset, inset, w - 2.0 * inset, h - 2.0 * inset);
+    cr.stroke()?;
+
+    let pad = w.min(h) * 0.1;
+    let width = w - 2.0 * pad;
+    let base = w.min(h);
+    let family = arabic_family();
+    let layouts = |scale: f64| {
+        let centred = |text: &str, font: String| {
+            let layout = text_layout(cr, text, &font, width);
+            layout.set_alignment(pango::Alignment::Center);
+            layout
+        };
+        let arabic = centred(&card.arabic, format!("{} {:.1}", family, base * 0.055 * scale));
+        let translation = card.translation.as_deref().map(|t| centred(t, format!("Serif {:.1}", base * 0.03 * scale)));
+        let citation = centred(&card.citation, format!("Sans {:.1}", base * 0.022));
+        (arabic, translation, citation)
+    };
+    let gap = base * 0.05;
+    let height = |(arabic, translation, citation): &(pango::Layout, Option<pango::Layout>, pango::Layout)| {
+        let t = translation.as_ref().map_or(0.0, |t| t.pixel_size().1 as f64 + gap);
+        arabic.pixel_size().1 as f64 + t + gap + citation.pixel_size().1 as f64
+    };
+    let scale = fit_scale(|s| height(&layouts(s)), h - 2.0 * pad, 0.3);
+    let parts = layouts(scale);
+    let (arabic, translation, citation) = &parts;
+
+    let mut y = (h - height(&parts)) / 2.0;
+    let mut show = |layout: &pango::Layout, colour: (f64, f64, f64), space_after: f64| {
+        cr.set_source_rgb(colour.0, colour.1, colour.2);
+        cr.move_to(pad, y);
+        pangocairo::functions::show_layout(cr, layout);
+        y += layout.pixel_size().1 as f64 + space_after;
+    };
+    show(arabic, ink, gap);
+    if let Some(t) = translation {
+        show(t, ink, gap);
+    }
+    show(citation, accent, 0.0);
+    Ok(())
+}
+
+/// Writes the card as PNG or SVG.
+#[cfg(feature = "render")]
+pub fn save(card: &Card, options: &CardOptions, path: &Path, format: CardFormat) -> Result<()> {
+    use anyhow::Context;
+    use pangocairo::cairo;
+
+    let (w, h) = options.ratio.size();
+    match format {
+        CardFormat::Png => {
+            let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, w, h)?;
+            draw(&cairo::Context::new(&surface)?, card, options)?;
+            let mut file = std::fs::File::create(path).with_context(|| format!("writing {}", path.display()))?;
+            surface.write_to_png(&mut file)?;
+        }
+        CardFormat::Svg => {
+            let surface = cairo::SvgSurface::new(w as f64, h as f64, Some(path))?;
+            draw(&cairo::Context::new(&surface)?, card, options)?;
+            surface.finish();
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "render"))]
+pub fn save(_card: &Card, _options: &CardOptions, _path: &Path, _format: CardFormat) -> Result<()> {
+    bail!("verse cards need a build with the render feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TranslationEntry;
+    use crate::state::AyahRef;
+
+    #[test]
+    fn builds_card_text_and_citation() {
+        let ayah = |n: u16, arabic: &str, text: &str| AyahText {
+            ayah: AyahRef { surah_id: 112, ayah_index: n },
+            arabic: arabic.into(),
+            translations: vec![("en".into(), TranslationEntry { surah: 112, ayah: n, text: text.into(), footnotes: Vec::new() })],
+        };
+        let ayat = [ayah(1, "قُلْ هُوَ ٱللَّهُ أَحَدٌ", "Say, He is Allah, One"), ayah(2, "ٱللَّهُ ٱلصَّمَدُ", "Allah, the Eternal Refuge")];
+        let range = AyahRange::new(ayat[0].ayah.clone(), ayat[1].ayah.clone()).unwrap();
+        let card = Card::new(&range, &ayat, "Al-Ikhlas", Some(("en", "Sahih International")));
+        assert_eq!(card.arabic, "قُلْ هُوَ ٱللَّهُ أَحَدٌ ۝١ ٱللَّهُ ٱلصَّمَدُ ۝٢");
+        assert_eq!(card.translation.as_deref(), Some("(1) Say, He is Allah, One (2) Allah, the Eternal Refuge"));
+        assert_eq!(card.citation, "Al-Ikhlas 112:1-2 (Sahih International)");
+        let arabic_only = Card::new(&range, &ayat, "Al-Ikhlas", None);
+        assert_eq!((arabic_only.translation, arabic_only.citation.as_str()), (None, "Al-Ikhlas 112:1-2"));
+        assert!(Card::load(&AyahRange::surah(2).unwrap(), None, &[]).is_err());
+    }
+
+    #[test]
+    fn shrinks_until_content_fits() {
+        assert_eq!(fit_scale(|s| 800.0 * s, 1000.0, 0.3), 1.0);
+        let s = fit_scale(|s| 2000.0 * s, 1000.0, 0.3);
+        assert!(2000.0 * s <= 1000.0 && 2000.0 * s / 0.95 > 1000.0);
+        assert!(fit_scale(|s| 1e6 * s, 1000.0, 0.3) >= 0.3);
+        assert_eq!(CardFormat::from_path(Path::new("ayah.SVG")), Some(CardFormat::Svg));
+        assert_eq!(AspectRatio::Story.size(), (1080, 1920));
+    }
+}
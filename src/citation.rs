@@ -0,0 +1,252 @@
+use crate::data::AyahText;
+use crate::footnotes;
+use crate::range::AyahRange;
+use serde::{Deserialize, Serialize};
+
+// Text for copying ayat out of the app: which parts to include, the markup
+// to use, and a citation line built from a template.
+
+pub fn arabic_digits(n: u16) -> String {
+    n.to_string().chars().map(|c| char::from_u32('٠' as u32 + c.to_digit(10).unwrap_or(0)).unwrap_or(c)).collect()
+}
+
+/// End-of-ayah sign followed by the ayah number in Arabic-Indic digits.
+pub fn ayah_marker(n: u16) -> String {
+    format!("\u{06DD}{}", arabic_digits(n))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CopyContent {
+    Arabic,
+    Translation,
+    #[default]
+    Both,
+}
+
+impl CopyContent {
+    pub const ALL: [CopyContent; 3] = [CopyContent::Arabic, CopyContent::Translation, CopyContent::Both];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            CopyContent::Arabic => "arabic",
+            CopyContent::Translation => "translation",
+            CopyContent::Both => "both",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CopyContent::Arabic => "Arabic only",
+            CopyContent::Translation => "Translation only",
+            CopyContent::Both => "Arabic and translation",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.id() == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CopyFormat {
+    #[default]
+    Plain,
+    Markdown,
+    Html,
+}
+
+impl CopyFormat {
+    pub const ALL: [CopyFormat; 3] = [CopyFormat::Plain, CopyFormat::Markdown, CopyFormat::Html];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            CopyFormat::Plain => "plain",
+            CopyFormat::Markdown => "markdown",
+            CopyFormat::Html => "html",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CopyFormat::Plain => "Plain text",
+            CopyFormat::Markdown => "Markdown",
+            CopyFormat::Html => "HTML",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.id() == id)
+    }
+}
+
+/// `{ref}` is the reference such as `2:255-257`, `{surah}` the surah name
+/// and `{edition}` the translations used.
+pub const DEFAULT_TEMPLATE: &str = "— Qur'an {ref} ({edition})";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CopySettings {
+    pub content: CopyContent,
+    pub format: CopyFormat,
+    pub cite: bool,
+    pub template: String,
+}
+
+impl Default for CopySettings {
+    fn default() -> Self {
+        Self { content: CopyContent::default(), format: CopyFormat::default(), cite: true, template: DEFAULT_TEMPLATE.to_string() }
+    }
+}
+
+/// Fills in a citation template. A `({edition})` group is dropped when no
+/// translation is included.
+pub fn citation(template: &str, range: &AyahRange, surah: &str, editions: &[String]) -> String {
+    let mut t = template.to_string();
+    if editions.is_empty() {
+        t = t.replace(" ({edition})", "").replace("({edition})", "");
+    }
+    t.replace("{ref}", &range.to_string()).replace("{surah}", surah).replace("{edition}", &editions.join(", ")).trim().to_string()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn escape_markdown(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '>' | '#') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Formats `ayat` (the ayat of `range`, with their translations) for the
+/// clipboard. `editions` lists the translations to include as `(lang, name)`.
+pub fn format_passage(settings: &CopySettings, range: &AyahRange, ayat: &[AyahText], surah: &str, editions: &[(String, String)]) -> String {
+    let arabic = settings.content != CopyContent::Translation;
+    let translated = settings.content != CopyContent::Arabic;
+    let numbered = ayat.len() > 1;
+    let arabic_text = ayat
+        .iter()
+        .map(|a| format!("{} {}", a.arabic, ayah_marker(a.ayah.ayah_index)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    // One paragraph per translation, each ayah prefixed with its number in a range.
+    let mut translations = Vec::new();
+    let mut used = Vec::new();
+    for (lang, name) in editions.iter().filter(|_| translated) {
+        let text = ayat
+            .iter()
+            .filter_map(|a| {
+                let (_, entry) = a.translations.iter().find(|(l, _)| l == lang)?;
+                let text = footnotes::plain(&entry.text);
+                Some(match numbered {
+                    true => format!("({}) {}", a.ayah.ayah_index, text),
+                    false => text,
+                })
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !text.is_empty() {
+            translations.push(text);
+            used.push(name.clone());
+        }
+    }
+    let cite = settings.cite.then(|| citation(&settings.template, range, surah, &used));
+
+    match settings.format {
+        CopyFormat::Plain => {
+            let mut parts = Vec::new();
+            if arabic {
+                parts.push(arabic_text);
+            }
+            if translated {
+                parts.extend(translations);
+            }
+            parts.extend(cite);
+            parts.join("\n\n")
+        }
+        CopyFormat::Markdown => {
+            let mut parts = Vec::new();
+            if arabic {
+                parts.push(escape_markdown(&arabic_text));
+            }
+            if translated {
+                parts.extend(translations.iter().map(|t| escape_markdown(t)));
+            }
+            parts.extend(cite.map(|c| escape_markdown(&c)));
+            parts.iter().map(|p| format!("> {}", p)).collect::<Vec<_>>().join("\n>\n")
+        }
+        CopyFormat::Html => {
+            let mut out = String::from("<blockquote class=\"quran\">\n");
+            if arabic {
+                out.push_str(&format!("<p lang=\"ar\" dir=\"rtl\">{}</p>\n", escape_html(&arabic_text)));
+            }
+            if translated {
+                for t in &translations {
+                    out.push_str(&format!("<p>{}</p>\n", escape_html(t)));
+                }
+            }
+            if let Some(c) = cite {
+                out.push_str(&format!("<footer>{}</footer>\n", escape_html(&c)));
+            }
+            out.push_str("</blockquote>");
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TranslationEntry;
+    use crate::state::AyahRef;
+
+    fn ayah(n: u16, arabic: &str, translation: &str) -> AyahText {
+        AyahText {
+            ayah: AyahRef { surah_id: 1, ayah_index: n },
+            arabic: arabic.into(),
+            translations: vec![("en".into(), TranslationEntry { surah: 1, ayah: n, text: translation.into(), footnotes: Vec::new() })],
+        }
+    }
+
+    #[test]
+    fn formats_single_ayah_with_citation() {
+        let ayat = [ayah(2, "ٱلْحَمْدُ لِلَّهِ", "All praise is for Allah")];
+        let range = AyahRange::single(AyahRef { surah_id: 1, ayah_index: 2 });
+        let editions = vec![("en".to_string(), "Sahih International".to_string()), ("ur".to_string(), "Jalandhry".to_string())];
+        let plain = format_passage(&CopySettings::default(), &range, &ayat, "Al-Fatiha", &editions);
+        assert_eq!(plain, "ٱلْحَمْدُ لِلَّهِ ۝٢\n\nAll praise is for Allah\n\n— Qur'an 1:2 (Sahih International)");
+
+        let arabic_only = CopySettings { content: CopyContent::Arabic, template: "[{surah} {ref}]".into(), ..Default::default() };
+        assert_eq!(format_passage(&arabic_only, &range, &ayat, "Al-Fatiha", &editions), "ٱلْحَمْدُ لِلَّهِ ۝٢\n\n[Al-Fatiha 1:2]");
+        let uncited = CopySettings { content: CopyContent::Translation, cite: false, ..Default::default() };
+        assert_eq!(format_passage(&uncited, &range, &ayat, "Al-Fatiha", &editions), "All praise is for Allah");
+        assert_eq!(citation(DEFAULT_TEMPLATE, &range, "Al-Fatiha", &[]), "— Qur'an 1:2");
+
+        let noted = [ayah(2, "ٱلْحَمْدُ لِلَّهِ", "[All] praise is for Allah[^1]")];
+        assert_eq!(format_passage(&uncited, &range, &noted, "Al-Fatiha", &editions), "[All] praise is for Allah");
+    }
+
+    #[test]
+    fn formats_ranges_as_markdown_and_html() {
+        let ayat = [ayah(1, "بِسْمِ", "In the name"), ayah(2, "ٱلْحَمْدُ", "All *praise* <is>")];
+        let range = AyahRange::new(AyahRef { surah_id: 1, ayah_index: 1 }, AyahRef { surah_id: 1, ayah_index: 2 }).unwrap();
+        let editions = vec![("en".to_string(), "Sample".to_string())];
+        let md = CopySettings { format: CopyFormat::Markdown, ..Default::default() };
+        assert_eq!(
+            format_passage(&md, &range, &ayat, "Al-Fatiha", &editions),
+            "> بِسْمِ ۝١ ٱلْحَمْدُ ۝٢\n>\n> (1) In the name (2) All \\*praise\\* \\<is\\>\n>\n> — Qur'an 1:1-2 (Sample)"
+        );
+        let html = CopySettings { format: CopyFormat::Html, ..Default::default() };
+        let out = format_passage(&html, &range, &ayat, "Al-Fatiha", &editions);
+        assert!(out.contains("<p lang=\"ar\" dir=\"rtl\">بِسْمِ ۝١ ٱلْحَمْدُ ۝٢</p>"));
+        assert!(out.contains("<p>(1) In the name (2) All *praise* &lt;is&gt;</p>"));
+        assert!(out.ends_with("<footer>— Qur'an 1:1-2 (Sample)</footer>\n</blockquote>"));
+    }
+}
@@ -0,0 +1,497 @@
+use crate::data::{self, AyahText};
+use crate::footnotes;
+use crate::reference::{self, Reference};
+use crate::state::{AyahRef, Surah};
+use crate::storage::Bookmark;
+use crate::uri;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+// Terminal output for the `show`, `search` and `random` commands. Terminals
+// that do not run the bidi algorithm print Arabic in the order it is stored,
+// which reads backwards, so those get each line wrapped and reordered.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Plain,
+    Ansi,
+    Json,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 3] = [OutputFormat::Plain, OutputFormat::Ansi, OutputFormat::Json];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            OutputFormat::Plain => "plain",
+            OutputFormat::Ansi => "ansi",
+            OutputFormat::Json => "json",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.id() == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Bidi {
+    /// Visual on terminals not known to reorder text themselves, logical
+    /// when writing to a pipe or file.
+    #[default]
+    Auto,
+    Logical,
+    Visual,
+}
+
+impl Bidi {
+    pub const ALL: [Bidi; 3] = [Bidi::Auto, Bidi::Logical, Bidi::Visual];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Bidi::Auto => "auto",
+            Bidi::Logical => "logical",
+            Bidi::Visual => "visual",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|b| b.id() == id)
+    }
+
+    /// Whether to reorder right-to-left lines before printing them.
+    pub fn visual(&self, terminal: bool) -> bool {
+        match self {
+            Bidi::Auto => terminal && !terminal_reorders(),
+            Bidi::Logical => false,
+            Bidi::Visual => true,
+        }
+    }
+}
+
+// VTE (GNOME Terminal and friends), Konsole and mlterm implement bidi.
+fn terminal_reorders() -> bool {
+    ["VTE_VERSION", "KONSOLE_VERSION", "MLTERM"].iter().any(|v| std::env::var_os(v).is_some())
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub format: OutputFormat,
+    pub visual: bool,
+    /// Columns to wrap and right-align reordered lines to.
+    pub width: usize,
+}
+
+/// Picks a translation by language code, or by part of its edition name.
+pub fn resolve_translation(query: &str, available: &[(String, String)]) -> Option<String> {
+    let q = query.to_lowercase();
+    available
+        .iter()
+        .find(|(code, _)| code.to_lowercase() == q)
+        .or_else(|| available.iter().find(|(_, name)| name.to_lowercase().contains(&q)))
+        .map(|(code, _)| code.clone())
+}
+
+/// The `n`th ayah in mushaf order, wrapping around after the last.
+pub fn ayah_at(surahs: &[Surah], n: u64) -> AyahRef {
+    let total: u64 = surahs.iter().map(|s| s.ayah_count as u64).sum();
+    let mut n = n % total.max(1);
+    for s in surahs {
+        if n < s.ayah_count as u64 {
+            return AyahRef { surah_id: s.id, ayah_index: n as u16 + 1 };
+        }
+        n -= s.ayah_count as u64;
+    }
+    AyahRef::default()
+}
+
+/// An ayah picked evenly from `seed`; the same seed gives the same ayah.
+pub fn random_ayah(surahs: &[Surah], seed: u64) -> AyahRef {
+    // splitmix64, so that neighbouring seeds land far apart.
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    ayah_at(surahs, z ^ (z >> 31))
+}
+
+/// The verse of the day for `day` (days since the Unix epoch), the same for
+/// everyone on that date.
+pub fn verse_of_the_day(surahs: &[Surah], day: u64) -> AyahRef {
+    random_ayah(surahs, day)
+}
+
+/// Days since the epoch, in local time when the GUI libraries can tell the
+/// offset, so the verse of the day changes at midnight.
+pub fn local_day() -> u64 {
+    let secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    #[cfg(feature = "gui")]
+    let secs = secs + glib::DateTime::now_local().map(|d| d.utc_offset().as_seconds()).unwrap_or(0);
+    (secs.max(0) / 86_400) as u64
+}
+
+/// The ayah to open from a reference or `quran:` link, `--surah` and
+/// `--ayah`, or `--today`, in that order.
+pub fn navigation_target(reference: Option<&str>, surah: Option<u16>, ayah: Option<u16>, today: bool) -> Result<Option<AyahRef>> {
+    if let Some(reference) = reference.filter(|r| !r.trim().is_empty()) {
+        let r = match uri::is_quran_uri(reference) {
+            true => uri::parse(reference)?.reference,
+            false => reference::parse(reference)?,
+        };
+        return r.first_ayah().map(Some).ok_or_else(|| anyhow!("{} needs an imported mushaf layout", r));
+    }
+    Ok(match (surah, ayah) {
+        (Some(s), a) => Some(AyahRef { surah_id: s, ayah_index: a.unwrap_or(1) }),
+        _ if today => Some(verse_of_the_day(&crate::surah_index::default_surahs(), local_day())),
+        _ => None,
+    })
+}
+
+/// Which ayah the Waybar module shows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WaybarSource {
+    #[default]
+    Today,
+    Position,
+}
+
+impl WaybarSource {
+    pub const ALL: [WaybarSource; 2] = [WaybarSource::Today, WaybarSource::Position];
+
+    /// Also the CSS class of the module.
+    pub fn id(&self) -> &'static str {
+        match self {
+            WaybarSource::Today => "today",
+            WaybarSource::Position => "position",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|w| w.id() == id)
+    }
+}
+
+/// A Waybar custom module (`"return-type": "json"`).
+#[derive(Debug, Serialize)]
+pub struct WaybarModule {
+    pub text: String,
+    /// Pango markup.
+    pub tooltip: String,
+    pub class: String,
+}
+
+fn escape_markup(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// The reference in the bar, the ayah and its translations in the tooltip.
+pub fn waybar_module(ayah: &AyahText, surahs: &[Surah], editions: &[(String, String)], source: WaybarSource) -> WaybarModule {
+    let r = &ayah.ayah;
+    let name = surahs.iter().find(|s| s.id == r.surah_id).map(|s| s.name_en.as_str()).unwrap_or("");
+    let reference = format!("{} {}:{}", name, r.surah_id, r.ayah_index);
+    let mut tooltip = vec![format!("<big>{} {}</big>", escape_markup(&ayah.arabic), crate::citation::ayah_marker(r.ayah_index))];
+    for (lang, _) in editions {
+        if let Some((_, entry)) = ayah.translations.iter().find(|(l, _)| l == lang) {
+            tooltip.push(escape_markup(&footnotes::plain(&entry.text)));
+        }
+    }
+    tooltip.push(format!("<i>{}</i>", escape_markup(&reference)));
+    WaybarModule { text: escape_markup(reference.trim()), tooltip: tooltip.join("\n\n"), class: source.id().to_string() }
+}
+
+const BOOKMARK_MARK: &str = "★ ";
+
+/// Menu lines for dmenu-style launchers: bookmarks first as `★ 2:255 Name —
+/// note`, then every surah as `2. Al-Baqarah · البقرة · 286 ayat`.
+pub fn dmenu_lines(surahs: &[Surah], bookmarks: &[Bookmark]) -> Vec<String> {
+    let name = |id: u16| surahs.iter().find(|s| s.id == id).map(|s| s.name_en.as_str()).unwrap_or("");
+    let mut lines: Vec<String> = bookmarks
+        .iter()
+        .map(|b| {
+            let mut line = format!("{}{}:{} {}", BOOKMARK_MARK, b.ayah.surah_id, b.ayah.ayah_index, name(b.ayah.surah_id));
+            if let Some(note) = b.note.lines().next().filter(|n| !n.trim().is_empty()) {
+                line.push_str(&format!(" — {}", note.trim()));
+            }
+            line
+        })
+        .collect();
+    lines.extend(surahs.iter().map(|s| format!("{}. {} · {} · {} ayat", s.id, s.name_en, s.name_ar, s.ayah_count)));
+    lines
+}
+
+/// What a line picked from `dmenu_lines`, a reference such as `2:255` or
+/// `Yasin 12`, or a `quran:` link opens, with the translation a link asks
+/// for. Launcher picks and links opened without the reader both come here.
+pub fn launch_target(line: &str) -> Result<(Reference, Option<String>)> {
+    let line = line.trim();
+    if uri::is_quran_uri(line) {
+        let link = uri::parse(line)?;
+        return Ok((link.reference, link.translation));
+    }
+    let query = match line.strip_prefix(BOOKMARK_MARK.trim_end()) {
+        Some(rest) => rest.split_whitespace().next().unwrap_or(""),
+        None => match line.split_once(". ") {
+            Some((id, _)) if id.parse::<u16>().is_ok() => id,
+            _ => line,
+        },
+    };
+    Ok((reference::parse(query)?, None))
+}
+
+fn is_mark(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}' | '\u{0610}'..='\u{061A}' | '\u{064B}'..='\u{065F}' | '\u{0670}'
+        | '\u{06D6}'..='\u{06DC}' | '\u{06DF}'..='\u{06E4}' | '\u{06E7}' | '\u{06E8}' | '\u{06EA}'..='\u{06ED}'
+        | '\u{08D3}'..='\u{08FF}' | '\u{200C}' | '\u{200D}' | '\u{FE00}'..='\u{FE0F}' | '\u{FE20}'..='\u{FE2F}')
+}
+
+// Runs that keep their left-to-right order inside a right-to-left line. The
+// end-of-ayah sign is a number sign and stays before the digits it encloses.
+fn is_ltr(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '\u{0660}'..='\u{0669}' | '\u{06F0}'..='\u{06F9}' | '\u{06DD}')
+}
+
+/// Each base character with the marks that follow it.
+fn clusters(s: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if i > start && !is_mark(c) {
+            out.push(&s[start..i]);
+            start = i;
+        }
+    }
+    if start < s.len() {
+        out.push(&s[start..]);
+    }
+    out
+}
+
+fn columns(s: &str) -> usize {
+    s.chars().filter(|&c| !is_mark(c)).count()
+}
+
+fn wrap(s: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in s.split_whitespace() {
+        if !line.is_empty() && columns(&line) + 1 + columns(word) > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// A right-to-left line in display order for terminals that print characters
+/// left to right as stored. Digits and Latin runs keep their order.
+pub fn visual_line(s: &str) -> String {
+    let clusters = clusters(s);
+    let starts = |i: usize| clusters.get(i).and_then(|c| c.chars().next()).unwrap_or(' ');
+    let mut runs: Vec<String> = Vec::new();
+    let mut ltr = false;
+    for (i, &c) in clusters.iter().enumerate() {
+        let first = starts(i);
+        // Separators such as the colon in 2:255 join the runs on either side.
+        let is_run = is_ltr(first) || (ltr && matches!(first, ':' | '.' | ',' | '-' | '/') && is_ltr(starts(i + 1)));
+        if is_run && ltr {
+            if let Some(last) = runs.last_mut() {
+                last.push_str(c);
+                continue;
+            }
+        }
+        ltr = is_run;
+        runs.push(match first {
+            '(' => ")".to_string(),
+            ')' => "(".to_string(),
+            '[' => "]".to_string(),
+            ']' => "[".to_string(),
+            _ => c.to_string(),
+        });
+    }
+    runs.reverse();
+    runs.concat()
+}
+
+fn rtl_lines(text: &str, options: &RenderOptions) -> Vec<String> {
+    if !options.visual {
+        return vec![text.to_string()];
+    }
+    wrap(text, options.width)
+        .iter()
+        .map(|l| format!("{}{}", " ".repeat(options.width.saturating_sub(columns(l))), visual_line(l)))
+        .collect()
+}
+
+// Marks each case-insensitive match of `term` in bold yellow.
+fn highlight(text: &str, term: &str) -> String {
+    let lower = text.to_lowercase();
+    let term = term.to_lowercase();
+    // Lowercasing can change byte lengths; leave such text as it is.
+    if term.is_empty() || lower.len() != text.len() {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut last = 0;
+    for (i, m) in lower.match_indices(&term) {
+        out.push_str(&text[last..i]);
+        out.push_str(&format!("\x1b[1;33m{}\x1b[0m", &text[i..i + m.len()]));
+        last = i + m.len();
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+#[derive(Serialize)]
+struct JsonTranslation<'a> {
+    lang: &'a str,
+    edition: &'a str,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct JsonAyah<'a> {
+    reference: String,
+    surah: u16,
+    ayah: u16,
+    surah_name: &'a str,
+    arabic: &'a str,
+    translations: Vec<JsonTranslation<'a>>,
+}
+
+/// Formats `ayat` with the translations in `editions` (`(lang, name)`).
+/// Search hits are highlighted in coloured output when `term` is given.
+pub fn render(ayat: &[AyahText], surahs: &[Surah], editions: &[(String, String)], options: &RenderOptions, term: Option<&str>) -> String {
+    let surah_name = |id: u16| surahs.iter().find(|s| s.id == id).map(|s| s.name_en.as_str()).unwrap_or("");
+    if options.format == OutputFormat::Json {
+        let items: Vec<JsonAyah> = ayat
+            .iter()
+            .map(|a| JsonAyah {
+                reference: format!("{}:{}", a.ayah.surah_id, a.ayah.ayah_index),
+                surah: a.ayah.surah_id,
+                ayah: a.ayah.ayah_index,
+                surah_name: surah_name(a.ayah.surah_id),
+                arabic: &a.arabic,
+                translations: editions
+                    .iter()
+                    .filter_map(|(lang, name)| {
+                        let (_, entry) = a.translations.iter().find(|(l, _)| l == lang)?;
+                        Some(JsonTranslation { lang, edition: name, text: footnotes::plain(&entry.text) })
+                    })
+                    .collect(),
+            })
+            .collect();
+        return serde_json::to_string_pretty(&items).unwrap_or_default();
+    }
+    let ansi = options.format == OutputFormat::Ansi;
+    let paint = |code: &str, s: &str| if ansi { format!("\x1b[{}m{}\x1b[0m", code, s) } else { s.to_string() };
+    let tagged = editions.len() > 1;
+    let mut blocks = Vec::new();
+    for a in ayat {
+        let r = &a.ayah;
+        let mut lines = vec![format!("{} {}", paint("1;36", &format!("{}:{}", r.surah_id, r.ayah_index)), paint("2", surah_name(r.surah_id)))];
+        let arabic = format!("{} {}", a.arabic, crate::citation::ayah_marker(r.ayah_index));
+        lines.extend(rtl_lines(&arabic, options).iter().map(|l| paint("1", l)));
+        for (lang, _) in editions {
+            let Some((_, entry)) = a.translations.iter().find(|(l, _)| l == lang) else { continue };
+            let tag = if tagged { format!("{} ", paint("2", &format!("[{}]", lang))) } else { String::new() };
+            let plain = footnotes::plain(&entry.text);
+            let text = if data::is_rtl_language(lang) { rtl_lines(&plain, options) } else { vec![plain] };
+            for t in text {
+                let t = match (ansi, term) {
+                    (true, Some(term)) => highlight(&t, term),
+                    _ => t,
+                };
+                lines.push(format!("{}{}", tag, t));
+            }
+        }
+        blocks.push(lines.join("\n"));
+    }
+    blocks.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surah_index::default_surahs;
+
+    #[test]
+    fn reorders_arabic_for_terminals_without_bidi() {
+        // Marks stay on their letter, Arabic-Indic digits stay in reading order.
+        assert_eq!(visual_line("بِسْمِ ۝١٢"), "۝١٢ مِسْبِ");
+        assert_eq!(visual_line("قال (2:255)"), "(2:255) لاق");
+        assert_eq!(wrap("one two three", 8), vec!["one two", "three"]);
+    }
+
+    #[test]
+    fn picks_translations_and_ayat() {
+        let available = vec![("en".to_string(), "Sahih International".to_string()), ("ur".to_string(), "Jalandhry".to_string())];
+        assert_eq!(resolve_translation("sahih", &available).as_deref(), Some("en"));
+        assert_eq!(resolve_translation("UR", &available).as_deref(), Some("ur"));
+        assert_eq!(resolve_translation("pickthall", &available), None);
+
+        let surahs = default_surahs();
+        assert_eq!(ayah_at(&surahs, 0), AyahRef { surah_id: 1, ayah_index: 1 });
+        assert_eq!(ayah_at(&surahs, 7), AyahRef { surah_id: 2, ayah_index: 1 });
+        assert_eq!(ayah_at(&surahs, 6235), AyahRef { surah_id: 114, ayah_index: 6 });
+        assert_eq!(random_ayah(&surahs, 42), random_ayah(&surahs, 42));
+    }
+
+    #[test]
+    fn renders_translations_without_footnote_markers() {
+        let surahs = default_surahs();
+        let ayah = AyahText {
+            ayah: AyahRef { surah_id: 1, ayah_index: 2 },
+            arabic: "ٱلْحَمْدُ".into(),
+            translations: vec![("en".into(), data::TranslationEntry { surah: 1, ayah: 2, text: "[All] praise is for Allah[^1]".into(), footnotes: Vec::new() })],
+        };
+        let editions = [("en".to_string(), "Sample".to_string())];
+        let mut options = RenderOptions { format: OutputFormat::Plain, visual: false, width: 80 };
+        let plain = render(std::slice::from_ref(&ayah), &surahs, &editions, &options, None);
+        assert!(plain.ends_with("\n[All] praise is for Allah"));
+        options.format = OutputFormat::Json;
+        let json = render(std::slice::from_ref(&ayah), &surahs, &editions, &options, None);
+        assert!(json.contains("\"text\": \"[All] praise is for Allah\""));
+    }
+
+    #[test]
+    fn builds_waybar_module() {
+        let surahs = default_surahs();
+        let ayah = AyahText {
+            ayah: AyahRef { surah_id: 1, ayah_index: 2 },
+            arabic: "ٱلْحَمْدُ لِلَّهِ".into(),
+            translations: vec![("en".into(), data::TranslationEntry { surah: 1, ayah: 2, text: "Praise <be> to Allah & all[^1]".into(), footnotes: Vec::new() })],
+        };
+        let module = waybar_module(&ayah, &surahs, &[("en".into(), "Sample".into())], WaybarSource::Today);
+        assert_eq!(module.text, "Al-Fatiha 1:2");
+        assert_eq!(module.class, "today");
+        assert_eq!(module.tooltip, "<big>ٱلْحَمْدُ لِلَّهِ ۝٢</big>\n\nPraise &lt;be&gt; to Allah &amp; all\n\n<i>Al-Fatiha 1:2</i>");
+        let mut renamed = surahs.clone();
+        renamed[0].name_en = "Al-Fatiha <The Opening>".into();
+        assert_eq!(waybar_module(&ayah, &renamed, &[], WaybarSource::Position).text, "Al-Fatiha &lt;The Opening&gt; 1:2");
+        assert_eq!(verse_of_the_day(&surahs, 20_000), verse_of_the_day(&surahs, 20_000));
+        assert_ne!(verse_of_the_day(&surahs, 20_000), verse_of_the_day(&surahs, 20_001));
+    }
+
+    #[test]
+    fn round_trips_dmenu_lines() {
+        let surahs = default_surahs();
+        let mut bookmark = Bookmark::new(AyahRef { surah_id: 2, ayah_index: 255 });
+        bookmark.note = "Ayat al-Kursi\nread at night".into();
+        let lines = dmenu_lines(&surahs, &[bookmark]);
+        assert_eq!(lines.len(), 115);
+        assert_eq!(lines[0], "★ 2:255 Al-Baqarah — Ayat al-Kursi");
+        assert!(lines[2].starts_with("2. Al-Baqarah · "));
+        assert_eq!(launch_target(&lines[0]).unwrap(), (Reference::Ayah(AyahRef { surah_id: 2, ayah_index: 255 }), None));
+        assert_eq!(launch_target(&lines[114]).unwrap(), (Reference::Surah(114), None));
+        assert_eq!(launch_target("36:12\n").unwrap(), (Reference::Ayah(AyahRef { surah_id: 36, ayah_index: 12 }), None));
+        assert_eq!(launch_target("quran:2:255?translation=en").unwrap(), (Reference::Ayah(AyahRef { surah_id: 2, ayah_index: 255 }), Some("en".into())));
+        assert!(launch_target("not a surah").is_err());
+    }
+}
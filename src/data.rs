@@ -2,6 +2,11 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
+use crate::footnotes::{self, Footnote};
+use crate::range::AyahRange;
+use crate::state::AyahRef;
+#[cfg(feature = "sqlite")]
+pub use crate::db::EditionKind;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranslationFile {
@@ -14,6 +19,22 @@ pub struct TranslationEntry {
     pub surah: u16,
     pub ayah: u16,
     pub text: String,
+    #[serde(default)]
+    pub footnotes: Vec<Footnote>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TafsirEntry {
+    pub range: AyahRange,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AyahText {
+    pub ayah: AyahRef,
+    pub arabic: String,
+    /// One entry per selected translation that covers this ayah, as `(lang, entry)`.
+    pub translations: Vec<(String, TranslationEntry)>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -63,10 +84,14 @@ pub fn load_translation(lang: &str, surah: u16) -> Result<TranslationFile> {
         let mut entries = Vec::new();
         for (ayah_number, _) in db::get_ayat(&conn, surah)? {
             if let Some(text) = db::get_translation_for_ayah(&conn, surah, ayah_number, lang)? {
-                entries.push(TranslationEntry { surah, ayah: ayah_number, text });
+                let footnotes = db::get_footnotes_for_ayah(&conn, surah, ayah_number, lang)?
+                    .into_iter()
+                    .map(|(marker, text)| Footnote { marker, text })
+                    .collect();
+                entries.push(TranslationEntry { surah, ayah: ayah_number, text, footnotes });
             }
         }
-        return Ok(TranslationFile { lang: lang.to_string(), entries });
+        Ok(TranslationFile { lang: lang.to_string(), entries })
     }
     #[cfg(not(feature = "sqlite"))]
     {
@@ -82,6 +107,130 @@ pub fn load_translation(lang: &str, surah: u16) -> Result<TranslationFile> {
     }
 }
 
+pub fn load_surah_with_translations(surah: u16, langs: &[String]) -> Result<Vec<AyahText>> {
+    let sf = load_surah_text(surah)?;
+    let mut editions: Vec<(String, Vec<TranslationEntry>)> = langs
+        .iter()
+        .map(|l| (l.clone(), load_translation(l, surah).map(|tf| tf.entries).unwrap_or_default()))
+        .collect();
+    Ok(sf
+        .ayat
+        .into_iter()
+        .enumerate()
+        .map(|(i, arabic)| {
+            let ayah_index = (i + 1) as u16;
+            let translations = editions
+                .iter_mut()
+                .filter_map(|(lang, entries)| {
+                    let p = entries.iter().position(|e| e.ayah == ayah_index)?;
+                    Some((lang.clone(), entries.swap_remove(p)))
+                })
+                .collect();
+            AyahText { ayah: AyahRef { surah_id: surah, ayah_index }, arabic, translations }
+        })
+        .collect())
+}
+
+/// The ayat of `range` with their translations, across as many surahs as it spans.
+pub fn load_ayat_in_range(range: &AyahRange, langs: &[String]) -> Result<Vec<AyahText>> {
+    let mut out = Vec::with_capacity(range.len());
+    for chunk in range.surah_chunks() {
+        let rows = load_surah_with_translations(chunk.start.surah_id, langs)?;
+        out.extend(rows.into_iter().filter(|a| chunk.contains(&a.ayah)));
+    }
+    Ok(out)
+}
+
+/// Arabic text without harakat, tatweel and Qur'anic annotation marks, with
+/// the alef forms folded, so searches need not match the vowelling.
+pub fn arabic_search_key(s: &str) -> String {
+    s.chars()
+        .filter_map(|c| match c {
+            '\u{0610}'..='\u{061A}' | '\u{064B}'..='\u{065F}' | '\u{0670}' | '\u{0640}' => None,
+            '\u{06D6}'..='\u{06DC}' | '\u{06DF}'..='\u{06E8}' | '\u{06EA}'..='\u{06ED}' => None,
+            'أ' | 'إ' | 'آ' | 'ٱ' => Some('ا'),
+            'ى' => Some('ي'),
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Ayat whose `lang` translation contains `query`, ignoring case, or whose
+/// Arabic text does when `lang` is `None`.
+pub fn search_ayat(query: &str, lang: Option<&str>) -> Result<Vec<AyahRef>> {
+    #[cfg(feature = "sqlite")]
+    if let Some(lang) = lang {
+        use crate::db;
+        let conn = db::open()?;
+        let _ = db::init_schema(&conn);
+        let hits = db::search_translation_ayahs(&conn, lang, query)?;
+        return Ok(hits.into_iter().map(|id| AyahRef { surah_id: (id / 1000) as u16, ayah_index: (id % 1000) as u16 }).collect());
+    }
+    // Surahs that are not available (the samples in builds without SQLite) are skipped.
+    let mut out = Vec::new();
+    match lang {
+        Some(lang) => {
+            let q = query.to_lowercase();
+            for surah in 1..=114 {
+                let Ok(tf) = load_translation(lang, surah) else { continue };
+                out.extend(tf.entries.iter().filter(|e| footnotes::plain(&e.text).to_lowercase().contains(&q)).map(|e| AyahRef { surah_id: surah, ayah_index: e.ayah }));
+            }
+        }
+        None => {
+            let q = arabic_search_key(query);
+            for surah in 1..=114 {
+                let Ok(sf) = load_surah_text(surah) else { continue };
+                out.extend(
+                    sf.ayat
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, a)| arabic_search_key(a).contains(&q))
+                        .map(|(i, _)| AyahRef { surah_id: surah, ayah_index: (i + 1) as u16 }),
+                );
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Whether text in `lang` is written right to left (Urdu, Persian, ...).
+pub fn is_rtl_language(lang: &str) -> bool {
+    let base = lang.split(['-', '_']).next().unwrap_or(lang).to_ascii_lowercase();
+    matches!(base.as_str(), "ar" | "fa" | "ur" | "ps" | "sd" | "ug" | "ckb" | "dv" | "he" | "yi" | "ks")
+}
+
+#[cfg(feature = "sqlite")]
+pub fn load_transliteration(lang: &str, surah: u16) -> Result<TranslationFile> {
+    use crate::db;
+    let conn = db::open()?;
+    let _ = db::init_schema(&conn);
+    let mut entries = Vec::new();
+    for (ayah_number, _) in db::get_ayat(&conn, surah)? {
+        if let Some(text) = db::get_edition_text_for_ayah(&conn, surah, ayah_number, lang, EditionKind::Transliteration)? {
+            entries.push(TranslationEntry { surah, ayah: ayah_number, text, footnotes: Vec::new() });
+        }
+    }
+    Ok(TranslationFile { lang: lang.to_string(), entries })
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn load_transliteration(_lang: &str, _surah: u16) -> Result<TranslationFile> {
+    Err(anyhow::anyhow!("missing sample transliteration"))
+}
+
+#[cfg(feature = "sqlite")]
+pub fn get_available_transliterations() -> Result<Vec<(String, String)>> {
+    use crate::db;
+    let conn = db::open()?;
+    let _ = db::init_schema(&conn);
+    db::get_available_editions(&conn, EditionKind::Transliteration)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn get_available_transliterations() -> Result<Vec<(String, String)>> {
+    Ok(Vec::new())
+}
+
 #[cfg(feature = "sqlite")]
 pub fn get_available_translations() -> Result<Vec<(String, String)>> {
     use crate::db;
@@ -94,4 +243,116 @@ pub fn get_available_translations() -> Result<Vec<(String, String)>> {
 pub fn get_available_translations() -> Result<Vec<(String, String)>> {
     // For non-SQLite builds, we only have the sample translations
     Ok(vec![("en".to_string(), "Sample EN".to_string()), ("id".to_string(), "Sample ID".to_string())])
-}
\ No newline at end of file
+}
+
+#[cfg(feature = "sqlite")]
+pub fn get_available_tafsirs() -> Result<Vec<(i64, String, String)>> {
+    use crate::db;
+    let conn = db::open()?;
+    let _ = db::init_schema(&conn);
+    db::get_available_tafsirs(&conn)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn get_available_tafsirs() -> Result<Vec<(i64, String, String)>> {
+    // Tafsir is only stored in the SQLite database
+    Ok(Vec::new())
+}
+
+#[cfg(feature = "sqlite")]
+pub fn load_tafsir(tafsir_id: i64, at: &AyahRef) -> Result<Vec<TafsirEntry>> {
+    use crate::db;
+    let conn = db::open()?;
+    let _ = db::init_schema(&conn);
+    let split = |id: i64| AyahRef { surah_id: (id / 1000) as u16, ayah_index: (id % 1000) as u16 };
+    let rows = db::get_tafsir_for_ayah(&conn, tafsir_id, at.surah_id, at.ayah_index)?;
+    Ok(rows.into_iter().map(|(s, e, text)| TafsirEntry { range: AyahRange { start: split(s), end: split(e) }, text }).collect())
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn load_tafsir(_tafsir_id: i64, _at: &AyahRef) -> Result<Vec<TafsirEntry>> {
+    Err(anyhow::anyhow!("tafsir requires the sqlite feature"))
+}
+
+#[cfg(feature = "sqlite")]
+pub fn load_mushaf_page(page: u16) -> Result<crate::mushaf::MushafPage> {
+    use crate::db;
+    let conn = db::open()?;
+    let _ = db::init_schema(&conn);
+    let specs = db::get_mushaf_lines(&conn, page)?;
+    if specs.is_empty() {
+        return Err(anyhow::anyhow!("no mushaf layout for page {}", page));
+    }
+    let mut cache: std::collections::HashMap<u16, Vec<(u16, String)>> = std::collections::HashMap::new();
+    for s in &specs {
+        for id in [s.start.ayah.surah_id, s.end.ayah.surah_id] {
+            if let std::collections::hash_map::Entry::Vacant(e) = cache.entry(id) {
+                e.insert(db::get_ayat(&conn, id)?);
+            }
+        }
+    }
+    Ok(crate::mushaf::layout_page(page, &specs, |r| {
+        cache
+            .get(&r.surah_id)
+            .and_then(|ayat| ayat.iter().find(|(n, _)| *n == r.ayah_index))
+            .map(|(_, t)| t.clone())
+    }))
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn load_mushaf_page(_page: u16) -> Result<crate::mushaf::MushafPage> {
+    Err(anyhow::anyhow!("mushaf layout requires the sqlite feature"))
+}
+
+#[cfg(feature = "sqlite")]
+pub fn page_for_ayah(at: &AyahRef) -> Option<u16> {
+    use crate::db;
+    let conn = db::open().ok()?;
+    let _ = db::init_schema(&conn);
+    db::get_page_for_ayah(&conn, at.surah_id, at.ayah_index).ok().flatten()
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn page_for_ayah(_at: &AyahRef) -> Option<u16> {
+    None
+}
+
+#[cfg(feature = "sqlite")]
+pub fn load_notes(surah: Option<u16>) -> Result<Vec<crate::notes::Note>> {
+    let conn = crate::db::open_user()?;
+    crate::db::get_notes(&conn, surah)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn load_notes(_surah: Option<u16>) -> Result<Vec<crate::notes::Note>> {
+    Ok(Vec::new())
+}
+
+/// Inserts a new note or updates an existing one, stamping the update time.
+#[cfg(feature = "sqlite")]
+pub fn save_note(note: &mut crate::notes::Note) -> Result<()> {
+    let conn = crate::db::open_user()?;
+    note.updated = crate::notes::now();
+    if note.id == 0 {
+        note.id = crate::db::insert_note(&conn, note)?;
+    } else {
+        crate::db::update_note(&conn, note)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn save_note(_note: &mut crate::notes::Note) -> Result<()> {
+    Err(anyhow::anyhow!("notes require the sqlite feature"))
+}
+
+#[cfg(feature = "sqlite")]
+pub fn delete_note(id: i64) -> Result<()> {
+    let conn = crate::db::open_user()?;
+    crate::db::delete_note(&conn, id)
+}
+
+#[cfg(not(feature = "sqlite"))]
+pub fn delete_note(_id: i64) -> Result<()> {
+    Err(anyhow::anyhow!("notes require the sqlite feature"))
+}
@@ -2,6 +2,26 @@
 use anyhow::Result;
 use directories::ProjectDirs;
 use rusqlite::{params, Connection, OptionalExtension};
+use crate::mushaf::{LineKind, LineSpec, WordRef};
+use crate::notes::{Highlight, Note};
+use crate::state::AyahRef;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditionKind {
+    Translation,
+    Transliteration,
+}
+
+impl EditionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EditionKind::Translation => "translation",
+            EditionKind::Transliteration => "transliteration",
+        }
+    }
+}
 
 pub fn db_path() -> Result<std::path::PathBuf> {
     let dirs = ProjectDirs::from("org", "hyprquran", "HyprQuran").ok_or_else(|| anyhow::anyhow!("dirs"))?;
@@ -16,6 +36,37 @@ pub fn open() -> Result<Connection> {
     Ok(conn)
 }
 
+/// The user's own data lives apart from the imported text so that
+/// re-importing never touches it.
+pub fn user_db_path() -> Result<std::path::PathBuf> {
+    Ok(db_path()?.with_file_name("user.db"))
+}
+
+pub fn open_user() -> Result<Connection> {
+    let conn = Connection::open(user_db_path()?)?;
+    init_user_schema(&conn)?;
+    Ok(conn)
+}
+
+pub fn init_user_schema(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS note (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            ayah_id INTEGER NOT NULL,
+            start_word INTEGER,
+            end_word INTEGER,
+            highlight TEXT,
+            body TEXT NOT NULL DEFAULT '',
+            created INTEGER NOT NULL,
+            updated INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_note_ayah ON note(ayah_id);
+        "#,
+    )?;
+    Ok(())
+}
+
 pub fn init_schema(conn: &Connection) -> Result<()> {
     conn.execute_batch(
         r#"
@@ -35,7 +86,8 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
         CREATE TABLE IF NOT EXISTS translation (
             trans_id INTEGER PRIMARY KEY,
             name TEXT NOT NULL,
-            language TEXT NOT NULL
+            language TEXT NOT NULL,
+            kind TEXT NOT NULL DEFAULT 'translation'
         );
         CREATE TABLE IF NOT EXISTS translated_ayah (
             ayah_id INTEGER NOT NULL REFERENCES ayah(ayah_id) ON DELETE CASCADE,
@@ -43,8 +95,55 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
             text TEXT NOT NULL,
             PRIMARY KEY(ayah_id, trans_id)
         );
+        CREATE TABLE IF NOT EXISTS translation_footnote (
+            ayah_id INTEGER NOT NULL REFERENCES ayah(ayah_id) ON DELETE CASCADE,
+            trans_id INTEGER NOT NULL REFERENCES translation(trans_id) ON DELETE CASCADE,
+            marker TEXT NOT NULL,
+            text TEXT NOT NULL,
+            PRIMARY KEY(ayah_id, trans_id, marker)
+        );
+        CREATE TABLE IF NOT EXISTS tafsir (
+            tafsir_id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            language TEXT NOT NULL
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS tafsir_source ON tafsir(name, language);
+        CREATE TABLE IF NOT EXISTS tafsir_entry (
+            entry_id INTEGER PRIMARY KEY,
+            tafsir_id INTEGER NOT NULL REFERENCES tafsir(tafsir_id) ON DELETE CASCADE,
+            start_ayah_id INTEGER NOT NULL,
+            end_ayah_id INTEGER NOT NULL,
+            text TEXT NOT NULL,
+            UNIQUE(tafsir_id, start_ayah_id)
+        );
+        CREATE INDEX IF NOT EXISTS tafsir_entry_range ON tafsir_entry(tafsir_id, start_ayah_id, end_ayah_id);
+        CREATE TABLE IF NOT EXISTS mushaf_line (
+            page INTEGER NOT NULL,
+            line INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            surah_id INTEGER NOT NULL,
+            start_ayah_id INTEGER NOT NULL,
+            start_word INTEGER NOT NULL,
+            end_ayah_id INTEGER NOT NULL,
+            end_word INTEGER NOT NULL,
+            PRIMARY KEY(page, line)
+        );
+        CREATE INDEX IF NOT EXISTS mushaf_line_ayah ON mushaf_line(start_ayah_id, end_ayah_id);
         "#,
     )?;
+    ensure_column(conn, "translation", "kind", "TEXT NOT NULL DEFAULT 'translation'")?;
+    Ok(())
+}
+
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|r| r.ok())
+        .any(|c| c == column);
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, decl))?;
+    }
     Ok(())
 }
 
@@ -68,9 +167,13 @@ pub fn upsert_ayah(conn: &Connection, surah_id: u16, ayah_number: u16, text: &st
 }
 
 pub fn upsert_translation(conn: &Connection, name: &str, language: &str) -> Result<i64> {
+    upsert_edition(conn, name, language, EditionKind::Translation)
+}
+
+pub fn upsert_edition(conn: &Connection, name: &str, language: &str, kind: EditionKind) -> Result<i64> {
     conn.execute(
-        "INSERT INTO translation(name,language) VALUES(?,?)",
-        params![name, language],
+        "INSERT INTO translation(name,language,kind) VALUES(?,?,?)",
+        params![name, language, kind.as_str()],
     )?;
     let id = conn.last_insert_rowid();
     Ok(id)
@@ -85,6 +188,15 @@ pub fn upsert_translated_ayah(conn: &Connection, ayah_id: i64, trans_id: i64, te
     Ok(())
 }
 
+pub fn upsert_translation_footnote(conn: &Connection, ayah_id: i64, trans_id: i64, marker: &str, text: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO translation_footnote(ayah_id,trans_id,marker,text) VALUES(?,?,?,?) \
+         ON CONFLICT(ayah_id,trans_id,marker) DO UPDATE SET text=excluded.text",
+        params![ayah_id, trans_id, marker, text],
+    )?;
+    Ok(())
+}
+
 pub fn get_surah(conn: &Connection, surah_id: u16) -> Result<Option<(String, String)>> {
     conn.query_row(
         "SELECT name_arabic,name_english FROM surah WHERE surah_id=?",
@@ -112,31 +224,262 @@ pub fn get_ayat(conn: &Connection, surah_id: u16) -> Result<Vec<(u16, String)>>
 }
 
 pub fn get_translation_for_ayah(conn: &Connection, surah_id: u16, ayah_number: u16, language: &str) -> Result<Option<String>> {
+    get_edition_text_for_ayah(conn, surah_id, ayah_number, language, EditionKind::Translation)
+}
+
+pub fn get_edition_text_for_ayah(conn: &Connection, surah_id: u16, ayah_number: u16, language: &str, kind: EditionKind) -> Result<Option<String>> {
     let ayah_id: i64 = (surah_id as i64) * 1000 + (ayah_number as i64);
     conn.query_row(
-        "SELECT ta.text FROM translated_ayah ta JOIN translation t ON ta.trans_id=t.trans_id WHERE ta.ayah_id=? AND t.language=?",
-        params![ayah_id, language],
+        "SELECT ta.text FROM translated_ayah ta JOIN translation t ON ta.trans_id=t.trans_id WHERE ta.ayah_id=? AND t.language=? AND t.kind=?",
+        params![ayah_id, language, kind.as_str()],
         |row| row.get::<_, String>(0),
     )
     .optional()
     .map_err(Into::into)
 }
 
+pub fn get_footnotes_for_ayah(conn: &Connection, surah_id: u16, ayah_number: u16, language: &str) -> Result<Vec<(String, String)>> {
+    let ayah_id: i64 = (surah_id as i64) * 1000 + (ayah_number as i64);
+    let mut stmt = conn.prepare(
+        "SELECT f.marker, f.text FROM translation_footnote f JOIN translation t ON f.trans_id=t.trans_id WHERE f.ayah_id=? AND t.language=? AND t.kind='translation' ORDER BY CAST(f.marker AS INTEGER), f.marker",
+    )?;
+    let rows = stmt.query_map(params![ayah_id, language], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
 pub fn search_surah_translation_ayahs(conn: &Connection, surah_id: u16, language: &str, query: &str) -> Result<Vec<u16>> {
     let ayah_prefix: i64 = (surah_id as i64) * 1000;
     let mut stmt = conn.prepare(
-        "SELECT ta.ayah_id FROM translated_ayah ta JOIN translation t ON ta.trans_id=t.trans_id WHERE ta.ayah_id BETWEEN ? AND ? AND t.language=? AND ta.text LIKE '%' || ? || '%' ORDER BY ta.ayah_id",
+        "SELECT ta.ayah_id FROM translated_ayah ta JOIN translation t ON ta.trans_id=t.trans_id WHERE ta.ayah_id BETWEEN ? AND ? AND t.language=? AND t.kind='translation' AND ta.text LIKE '%' || ? || '%' ORDER BY ta.ayah_id",
     )?;
-    let rows = stmt.query_map(params![ayah_prefix, ayah_prefix + 999, language, query], |row| Ok(row.get::<_, i64>(0)?))?;
+    let rows = stmt.query_map(params![ayah_prefix, ayah_prefix + 999, language, query], |row| row.get::<_, i64>(0))?;
     let mut out = Vec::new();
     for r in rows { let id: i64 = r?; out.push((id % 1000) as u16); }
     Ok(out)
 }
 
+/// Ayah ids across the whole Qur'an, like `search_surah_translation_ayahs`.
+pub fn search_translation_ayahs(conn: &Connection, language: &str, query: &str) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT ta.ayah_id FROM translated_ayah ta JOIN translation t ON ta.trans_id=t.trans_id WHERE t.language=? AND t.kind='translation' AND ta.text LIKE '%' || ? || '%' ORDER BY ta.ayah_id",
+    )?;
+    let rows = stmt.query_map(params![language, query], |row| row.get::<_, i64>(0))?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
 pub fn get_available_translations(conn: &Connection) -> Result<Vec<(String, String)>> {
-    let mut stmt = conn.prepare("SELECT language, name FROM translation ORDER BY language")?;
-    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    get_available_editions(conn, EditionKind::Translation)
+}
+
+pub fn get_available_editions(conn: &Connection, kind: EditionKind) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare("SELECT language, name FROM translation WHERE kind=? ORDER BY language")?;
+    let rows = stmt.query_map(params![kind.as_str()], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+/// The id of the tafsir source, with its passages cleared so that importing
+/// it again replaces them.
+pub fn upsert_tafsir(conn: &Connection, name: &str, language: &str) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO tafsir(name,language) VALUES(?,?) ON CONFLICT(name,language) DO UPDATE SET name=excluded.name",
+        params![name, language],
+    )?;
+    let id: i64 = conn.query_row("SELECT tafsir_id FROM tafsir WHERE name=? AND language=?", params![name, language], |row| row.get(0))?;
+    conn.execute("DELETE FROM tafsir_entry WHERE tafsir_id=?", params![id])?;
+    Ok(id)
+}
+
+/// Stores a commentary passage covering `start..=end` (both given as `surah*1000+ayah` ids).
+pub fn upsert_tafsir_entry(conn: &Connection, tafsir_id: i64, start_ayah_id: i64, end_ayah_id: i64, text: &str) -> Result<()> {
+    conn.execute(
+        "INSERT INTO tafsir_entry(tafsir_id,start_ayah_id,end_ayah_id,text) VALUES(?,?,?,?) \
+         ON CONFLICT(tafsir_id,start_ayah_id) DO UPDATE SET end_ayah_id=excluded.end_ayah_id, text=excluded.text",
+        params![tafsir_id, start_ayah_id, end_ayah_id, text],
+    )?;
+    Ok(())
+}
+
+pub fn get_available_tafsirs(conn: &Connection) -> Result<Vec<(i64, String, String)>> {
+    let mut stmt = conn.prepare("SELECT tafsir_id, name, language FROM tafsir ORDER BY name")?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+/// Returns every entry of `tafsir_id` whose range covers the given ayah, as `(start_ayah_id, end_ayah_id, text)`.
+pub fn get_tafsir_for_ayah(conn: &Connection, tafsir_id: i64, surah_id: u16, ayah_number: u16) -> Result<Vec<(i64, i64, String)>> {
+    let ayah_id: i64 = (surah_id as i64) * 1000 + (ayah_number as i64);
+    let mut stmt = conn.prepare(
+        "SELECT start_ayah_id, end_ayah_id, text FROM tafsir_entry WHERE tafsir_id=? AND start_ayah_id<=? AND end_ayah_id>=? ORDER BY start_ayah_id",
+    )?;
+    let rows = stmt.query_map(params![tafsir_id, ayah_id, ayah_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+pub fn upsert_mushaf_line(conn: &Connection, spec: &LineSpec) -> Result<()> {
+    let id = |r: &AyahRef| (r.surah_id as i64) * 1000 + (r.ayah_index as i64);
+    conn.execute(
+        "INSERT INTO mushaf_line(page,line,kind,surah_id,start_ayah_id,start_word,end_ayah_id,end_word) VALUES(?,?,?,?,?,?,?,?) \
+         ON CONFLICT(page,line) DO UPDATE SET kind=excluded.kind, surah_id=excluded.surah_id, start_ayah_id=excluded.start_ayah_id, \
+         start_word=excluded.start_word, end_ayah_id=excluded.end_ayah_id, end_word=excluded.end_word",
+        params![spec.page, spec.line, spec.kind.as_str(), spec.surah_id, id(&spec.start.ayah), spec.start.word, id(&spec.end.ayah), spec.end.word],
+    )?;
+    Ok(())
+}
+
+pub fn get_mushaf_lines(conn: &Connection, page: u16) -> Result<Vec<LineSpec>> {
+    let split = |id: i64| AyahRef { surah_id: (id / 1000) as u16, ayah_index: (id % 1000) as u16 };
+    let mut stmt = conn.prepare(
+        "SELECT line, kind, surah_id, start_ayah_id, start_word, end_ayah_id, end_word FROM mushaf_line WHERE page=? ORDER BY line",
+    )?;
+    let rows = stmt.query_map(params![page], |row| {
+        let kind: String = row.get(1)?;
+        Ok(LineSpec {
+            page,
+            line: row.get(0)?,
+            kind: LineKind::parse(&kind).unwrap_or(LineKind::Ayah),
+            surah_id: row.get(2)?,
+            start: WordRef { ayah: split(row.get(3)?), word: row.get(4)? },
+            end: WordRef { ayah: split(row.get(5)?), word: row.get(6)? },
+        })
+    })?;
     let mut out = Vec::new();
     for r in rows { out.push(r?); }
     Ok(out)
 }
+
+/// Page on which the given ayah starts, if a mushaf layout has been imported.
+pub fn get_page_for_ayah(conn: &Connection, surah_id: u16, ayah_number: u16) -> Result<Option<u16>> {
+    let ayah_id: i64 = (surah_id as i64) * 1000 + (ayah_number as i64);
+    let page = conn
+        .query_row(
+            "SELECT MIN(page) FROM mushaf_line WHERE kind='ayah' AND start_ayah_id<=? AND end_ayah_id>=?",
+            params![ayah_id, ayah_id],
+            |row| row.get::<_, Option<u16>>(0),
+        )
+        .optional()?
+        .flatten();
+    Ok(page)
+}
+
+pub fn insert_note(conn: &Connection, note: &Note) -> Result<i64> {
+    let ayah_id: i64 = (note.ayah.surah_id as i64) * 1000 + (note.ayah.ayah_index as i64);
+    conn.execute(
+        "INSERT INTO note(ayah_id,start_word,end_word,highlight,body,created,updated) VALUES(?,?,?,?,?,?,?)",
+        params![ayah_id, note.words.map(|w| w.0), note.words.map(|w| w.1), note.highlight.map(|h| h.id()), note.body, note.created, note.updated],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn update_note(conn: &Connection, note: &Note) -> Result<()> {
+    conn.execute(
+        "UPDATE note SET start_word=?, end_word=?, highlight=?, body=?, updated=? WHERE id=?",
+        params![note.words.map(|w| w.0), note.words.map(|w| w.1), note.highlight.map(|h| h.id()), note.body, note.updated, note.id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_note(conn: &Connection, id: i64) -> Result<()> {
+    conn.execute("DELETE FROM note WHERE id=?", params![id])?;
+    Ok(())
+}
+
+/// Notes in reading order, for one surah or for all of them.
+pub fn get_notes(conn: &Connection, surah_id: Option<u16>) -> Result<Vec<Note>> {
+    let (lo, hi): (i64, i64) = match surah_id {
+        Some(s) => ((s as i64) * 1000, (s as i64) * 1000 + 999),
+        None => (0, i64::MAX),
+    };
+    let mut stmt = conn.prepare(
+        "SELECT id, ayah_id, start_word, end_word, highlight, body, created, updated FROM note \
+         WHERE ayah_id BETWEEN ? AND ? ORDER BY ayah_id, COALESCE(start_word, 0), id",
+    )?;
+    let rows = stmt.query_map(params![lo, hi], |row| {
+        let ayah_id: i64 = row.get(1)?;
+        let start: Option<u16> = row.get(2)?;
+        let end: Option<u16> = row.get(3)?;
+        let highlight: Option<String> = row.get(4)?;
+        Ok(Note {
+            id: row.get(0)?,
+            ayah: AyahRef { surah_id: (ayah_id / 1000) as u16, ayah_index: (ayah_id % 1000) as u16 },
+            words: start.zip(end),
+            highlight: highlight.as_deref().and_then(Highlight::from_id),
+            body: row.get(5)?,
+            created: row.get(6)?,
+            updated: row.get(7)?,
+        })
+    })?;
+    let mut out = Vec::new();
+    for r in rows { out.push(r?); }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_notes_in_user_schema() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_user_schema(&conn).unwrap();
+        let mut note = Note::new(AyahRef { surah_id: 2, ayah_index: 255 });
+        note.words = Some((3, 5));
+        note.highlight = Some(Highlight::Green);
+        note.body = "Ayat al-Kursi".into();
+        note.id = insert_note(&conn, &note).unwrap();
+        insert_note(&conn, &Note::new(AyahRef { surah_id: 1, ayah_index: 1 })).unwrap();
+        assert_eq!(get_notes(&conn, Some(2)).unwrap(), vec![note.clone()]);
+
+        note.words = None;
+        note.body.push_str("\nrecite nightly");
+        note.updated += 60;
+        update_note(&conn, &note).unwrap();
+        let all = get_notes(&conn, None).unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[1], note);
+
+        delete_note(&conn, note.id).unwrap();
+        assert!(get_notes(&conn, Some(2)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reimporting_a_tafsir_replaces_its_passages() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let id = upsert_tafsir(&conn, "Ibn Kathir", "en").unwrap();
+        upsert_tafsir_entry(&conn, id, 2001, 2005, "Alif Lam Mim").unwrap();
+        upsert_tafsir_entry(&conn, id, 2255, 2255, "Ayat al-Kursi").unwrap();
+        assert_eq!(get_tafsir_for_ayah(&conn, id, 2, 3).unwrap(), vec![(2001, 2005, "Alif Lam Mim".to_string())]);
+
+        assert_eq!(upsert_tafsir(&conn, "Ibn Kathir", "en").unwrap(), id);
+        upsert_tafsir_entry(&conn, id, 2255, 2257, "Ayat al-Kursi and after").unwrap();
+        assert!(get_tafsir_for_ayah(&conn, id, 2, 3).unwrap().is_empty());
+        assert_eq!(get_tafsir_for_ayah(&conn, id, 2, 256).unwrap(), vec![(2255, 2257, "Ayat al-Kursi and after".to_string())]);
+        let other = upsert_tafsir(&conn, "Ibn Kathir", "ar").unwrap();
+        assert_ne!(other, id);
+        assert_eq!(get_available_tafsirs(&conn).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn orders_footnotes_numerically() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        upsert_surah(&conn, 2, "البقرة", "Al-Baqarah").unwrap();
+        upsert_ayah(&conn, 2, 255, "ٱللَّهُ لَآ إِلَٰهَ إِلَّا هُوَ").unwrap();
+        let trans_id = upsert_edition(&conn, "Sahih International", "en", EditionKind::Translation).unwrap();
+        for marker in ["10", "2", "a", "1"] {
+            upsert_translation_footnote(&conn, 2255, trans_id, marker, "note").unwrap();
+        }
+        let markers: Vec<String> = get_footnotes_for_ayah(&conn, 2, 255, "en").unwrap().into_iter().map(|(m, _)| m).collect();
+        assert_eq!(markers, ["a", "1", "2", "10"]);
+    }
+}
@@ -0,0 +1,337 @@
+use crate::citation::ayah_marker;
+use crate::data::{self, AyahText, TafsirEntry};
+use crate::fonts;
+use crate::footnotes::{self, Footnote, Segment};
+use crate::range::AyahRange;
+use crate::state::{AyahRef, Surah};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Printable handouts of a run of ayat: the Arabic text, the chosen
+// translations and optionally a tafsir, grouped by surah. Written as PDF
+// (needs the `render` feature) or as a standalone HTML file.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Pdf,
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 2] = [ExportFormat::Pdf, ExportFormat::Html];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            ExportFormat::Pdf => "pdf",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Pdf => "PDF",
+            ExportFormat::Html => "HTML",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.id() == id)
+    }
+
+    /// Picks the format from the file extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "pdf" => Some(ExportFormat::Pdf),
+            "html" | "htm" => Some(ExportFormat::Html),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportAyah {
+    pub text: AyahText,
+    /// Tafsir passages that end at this ayah, or run past the end of the export.
+    pub tafsir: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportSection {
+    pub surah: Surah,
+    pub ayat: Vec<ExportAyah>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub title: String,
+    pub range: AyahRange,
+    /// `(lang, name)` of each translation, in display order.
+    pub editions: Vec<(String, String)>,
+    pub tafsir: Option<String>,
+    pub sections: Vec<ExportSection>,
+}
+
+impl Document {
+    pub fn load(range: &AyahRange, langs: &[String], tafsir_id: Option<i64>, surahs: &[Surah]) -> Result<Self> {
+        let ayat = data::load_ayat_in_range(range, langs)?;
+        let names = data::get_available_translations().unwrap_or_default();
+        let editions = langs
+            .iter()
+            .map(|lang| {
+                let name = names.iter().find(|(l, _)| l == lang).map(|(_, n)| n.clone()).unwrap_or_else(|| lang.clone());
+                (lang.clone(), name)
+            })
+            .collect();
+        let refs: Vec<AyahRef> = ayat.iter().map(|a| a.ayah.clone()).collect();
+        let (tafsir, notes) = match tafsir_id {
+            Some(id) => {
+                let name = data::get_available_tafsirs()?.into_iter().find(|t| t.0 == id).map(|t| t.1);
+                (name, place_tafsir(&refs, |r| data::load_tafsir(id, r).unwrap_or_default()))
+            }
+            None => (None, vec![Vec::new(); refs.len()]),
+        };
+        let mut sections: Vec<ExportSection> = Vec::new();
+        for (text, tafsir) in ayat.into_iter().zip(notes) {
+            let ayah = ExportAyah { text, tafsir };
+            match sections.last_mut() {
+                Some(s) if s.surah.id == ayah.text.ayah.surah_id => s.ayat.push(ayah),
+                _ => {
+                    let id = ayah.text.ayah.surah_id;
+                    let surah = surahs.iter().find(|s| s.id == id).cloned().unwrap_or_else(|| Surah {
+                        id,
+                        name_ar: String::new(),
+                        name_en: format!("Surah {}", id),
+                        ayah_count: 0,
+                    });
+                    sections.push(ExportSection { surah, ayat: vec![ayah] });
+                }
+            }
+        }
+        Ok(Self { title: title(range, surahs), range: range.clone(), editions, tafsir, sections })
+    }
+}
+
+/// "Al-Mulk 67:1-30" within one surah, "Qur'an 1:1-2:5" across several.
+pub fn title(range: &AyahRange, surahs: &[Surah]) -> String {
+    match surahs.iter().find(|s| s.id == range.start.surah_id) {
+        Some(s) if range.start.surah_id == range.end.surah_id => format!("{} {}", s.name_en, range),
+        _ => format!("Qur'an {}", range),
+    }
+}
+
+/// Looks up the tafsir of each ayah and places every passage once, after the
+/// last ayah it covers (or the last exported ayah when it runs further).
+fn place_tafsir(ayat: &[AyahRef], entries_for: impl Fn(&AyahRef) -> Vec<TafsirEntry>) -> Vec<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut pending: Vec<TafsirEntry> = Vec::new();
+    let mut out = Vec::with_capacity(ayat.len());
+    for r in ayat {
+        pending.extend(entries_for(r).into_iter().filter(|e| seen.insert(e.range.clone())));
+        let (done, rest): (Vec<_>, Vec<_>) = pending.into_iter().partition(|e| e.range.end <= *r);
+        pending = rest;
+        out.push(done.into_iter().map(|e| e.text).collect::<Vec<_>>());
+    }
+    if let Some(last) = out.last_mut() {
+        last.extend(pending.into_iter().map(|e| e.text));
+    }
+    out
+}
+
+/// The first bundled Quranic font, for embedding in exports.
+pub fn bundled_font() -> Option<PathBuf> {
+    fonts::bundled_fonts(&data::assets_dir().join("fonts")).into_iter().next().map(|(_, p)| p)
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Translation text with its footnote markers raised. HTML and Pango
+/// markup share these escapes and `<sup>`, so both writers use it.
+pub fn translation_markup(text: &str) -> String {
+    footnotes::segments(text)
+        .into_iter()
+        .map(|seg| match seg {
+            Segment::Text(t) | Segment::Insertion(t) => escape(t),
+            Segment::Marker(m) => format!("<sup>{}</sup>", escape(m)),
+        })
+        .collect()
+}
+
+/// A footnote body led by its raised marker, in the same markup.
+pub fn footnote_markup(note: &Footnote) -> String {
+    format!("<sup>{}</sup> {}", escape(&note.marker), escape(&note.text))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+const HTML_STYLE: &str = r#"
+body { max-width: 42em; margin: 2em auto; padding: 0 1em; font-family: serif; line-height: 1.5; color: #1a1a1a; }
+header { border-bottom: 1px solid #999; margin-bottom: 1.5em; }
+h1 { font-size: 1.4em; margin-bottom: 0.2em; }
+.meta { color: #555; margin-top: 0; }
+h2 { display: flex; justify-content: space-between; border-bottom: 1px solid #ddd; }
+.arabic { font-family: "Quran", "Amiri Quran", "KFGQPC Uthmanic Script HAFS", "Scheherazade New", serif; font-size: 1.8em; line-height: 2; text-align: justify; }
+.number { color: #777; font-size: 0.85em; }
+.footnote { color: #555; font-size: 0.8em; margin: -0.6em 0 0.6em 1em; }
+.tafsir { color: #444; font-size: 0.9em; border-left: 3px solid #ccc; padding-left: 0.8em; margin: 0.5em 0 1em; }
+article { break-inside: avoid; margin-bottom: 1.2em; }
+@page { margin: 2cm; }
+"#;
+
+/// A standalone page; `font` is embedded so the Arabic renders the same
+/// without the font installed.
+pub fn to_html(doc: &Document, font: Option<&Path>) -> Result<String> {
+    let mut style = String::new();
+    if let Some(path) = font {
+        let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        let (mime, format) = match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("otf") => ("font/otf", "opentype"),
+            _ => ("font/ttf", "truetype"),
+        };
+        style.push_str(&format!("@font-face {{ font-family: \"Quran\"; src: url(data:{};base64,{}) format(\"{}\"); }}\n", mime, base64(&bytes), format));
+    }
+    style.push_str(HTML_STYLE.trim_start());
+
+    let mut meta = Vec::new();
+    if !doc.editions.is_empty() {
+        meta.push(format!("Translation: {}", doc.editions.iter().map(|(_, n)| n.as_str()).collect::<Vec<_>>().join(", ")));
+    }
+    if let Some(t) = &doc.tafsir {
+        meta.push(format!("Tafsir: {}", t));
+    }
+
+    let mut body = format!("<header>\n<h1>{}</h1>\n", escape(&doc.title));
+    if !meta.is_empty() {
+        body.push_str(&format!("<p class=\"meta\">{}</p>\n", escape(&meta.join(" · "))));
+    }
+    body.push_str("</header>\n");
+    for section in &doc.sections {
+        let s = &section.surah;
+        body.push_str(&format!(
+            "<section class=\"surah\">\n<h2><span>{}. {}</span><span lang=\"ar\" dir=\"rtl\">{}</span></h2>\n",
+            s.id,
+            escape(&s.name_en),
+            escape(&s.name_ar)
+        ));
+        for a in &section.ayat {
+            let r = &a.text.ayah;
+            body.push_str(&format!("<article id=\"ayah-{}-{}\">\n", r.surah_id, r.ayah_index));
+            body.push_str(&format!("<p class=\"arabic\" lang=\"ar\" dir=\"rtl\">{} {}</p>\n", escape(&a.text.arabic), ayah_marker(r.ayah_index)));
+            for (lang, _) in &doc.editions {
+                let Some((_, entry)) = a.text.translations.iter().find(|(l, _)| l == lang) else { continue };
+                let dir = if data::is_rtl_language(lang) { "rtl" } else { "ltr" };
+                body.push_str(&format!(
+                    "<p class=\"translation\" lang=\"{}\" dir=\"{}\"><span class=\"number\">{}:{}</span> {}</p>\n",
+                    escape(lang),
+                    dir,
+                    r.surah_id,
+                    r.ayah_index,
+                    translation_markup(&entry.text)
+                ));
+                for note in &entry.footnotes {
+                    body.push_str(&format!("<p class=\"footnote\" lang=\"{}\" dir=\"{}\">{}</p>\n", escape(lang), dir, footnote_markup(note)));
+                }
+            }
+            for t in &a.tafsir {
+                body.push_str(&format!("<div class=\"tafsir\">{}</div>\n", escape(t).replace('\n', "<br>\n")));
+            }
+            body.push_str("</article>\n");
+        }
+        body.push_str("</section>\n");
+    }
+    Ok(format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(&doc.title),
+        style,
+        body
+    ))
+}
+
+/// Writes `doc` to `path` in `format`.
+pub fn export(doc: &Document, path: &Path, format: ExportFormat) -> Result<()> {
+    match format {
+        ExportFormat::Html => {
+            let html = to_html(doc, bundled_font().as_deref())?;
+            fs::write(path, html).with_context(|| format!("writing {}", path.display()))
+        }
+        #[cfg(feature = "render")]
+        ExportFormat::Pdf => crate::pdf::write_pdf(doc, path),
+        #[cfg(not(feature = "render"))]
+        ExportFormat::Pdf => Err(anyhow::anyhow!("PDF export needs a build with the render feature")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::TranslationEntry;
+
+    fn at(s: u16, a: u16) -> AyahRef {
+        AyahRef { surah_id: s, ayah_index: a }
+    }
+
+    fn entry(start: AyahRef, end: AyahRef, text: &str) -> TafsirEntry {
+        TafsirEntry { range: AyahRange { start, end }, text: text.into() }
+    }
+
+    #[test]
+    fn places_each_tafsir_passage_once() {
+        let ayat = [at(2, 1), at(2, 2), at(2, 3)];
+        let placed = place_tafsir(&ayat, |r| match r.ayah_index {
+            1 => vec![entry(at(2, 1), at(2, 2), "one-two")],
+            2 => vec![entry(at(2, 1), at(2, 2), "one-two"), entry(at(2, 2), at(2, 5), "two-five")],
+            _ => vec![entry(at(2, 2), at(2, 5), "two-five")],
+        });
+        assert_eq!(placed, vec![Vec::<String>::new(), vec!["one-two".to_string()], vec!["two-five".to_string()]]);
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+    }
+
+    #[test]
+    fn writes_standalone_html() {
+        let surah = Surah { id: 1, name_ar: "الفاتحة".into(), name_en: "Al-Fatiha".into(), ayah_count: 7 };
+        let text = |lang: &str, t: &str| (lang.to_string(), TranslationEntry { surah: 1, ayah: 2, text: t.into(), footnotes: Vec::new() });
+        let mut en = text("en", "[All] Praise <be> to Allah[^1]");
+        en.1.footnotes.push(Footnote { marker: "1".into(), text: "Lord & Sustainer.".into() });
+        let ayah = ExportAyah {
+            text: AyahText { ayah: at(1, 2), arabic: "ٱلْحَمْدُ لِلَّهِ".into(), translations: vec![en, text("ur", "سب تعریف")] },
+            tafsir: vec!["Line one\nline two".into()],
+        };
+        let range = AyahRange::single(at(1, 2));
+        let doc = Document {
+            title: title(&range, std::slice::from_ref(&surah)),
+            range,
+            editions: vec![("en".into(), "Sample".into()), ("ur".into(), "Jalandhry".into())],
+            tafsir: Some("Ibn Kathir".into()),
+            sections: vec![ExportSection { surah, ayat: vec![ayah] }],
+        };
+        let html = to_html(&doc, None).unwrap();
+        assert!(html.contains("<title>Al-Fatiha 1:2</title>"));
+        assert!(html.contains("Translation: Sample, Jalandhry · Tafsir: Ibn Kathir"));
+        assert!(html.contains("<p class=\"arabic\" lang=\"ar\" dir=\"rtl\">ٱلْحَمْدُ لِلَّهِ ۝٢</p>"));
+        assert!(html.contains("lang=\"en\" dir=\"ltr\"><span class=\"number\">1:2</span> [All] Praise &lt;be&gt; to Allah<sup>1</sup></p>"));
+        assert!(html.contains("<p class=\"footnote\" lang=\"en\" dir=\"ltr\"><sup>1</sup> Lord &amp; Sustainer.</p>"));
+        assert!(!html.contains("[^1]"));
+        assert!(html.contains("lang=\"ur\" dir=\"rtl\">"));
+        assert!(html.contains("Line one<br>\nline two"));
+        assert!(!html.contains("@font-face"));
+        assert_eq!(ExportFormat::from_path(Path::new("circle.HTM")), Some(ExportFormat::Html));
+    }
+}
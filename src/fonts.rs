@@ -0,0 +1,105 @@
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// Quranic fonts that may ship in `assets/fonts`, in order of preference.
+// They are registered with fontconfig for this process only, so they work
+// without being installed system-wide.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuranFont {
+    pub family: &'static str,
+    pub files: &'static [&'static str],
+}
+
+pub const QURAN_FONTS: [QuranFont; 3] = [
+    QuranFont { family: "Amiri Quran", files: &["AmiriQuran.ttf", "AmiriQuran-Regular.ttf"] },
+    QuranFont { family: "KFGQPC Uthmanic Script HAFS", files: &["UthmanicHafs.otf", "UthmanicHafs1Ver18.ttf", "KFGQPCUthmanicScriptHAFS.otf"] },
+    QuranFont { family: "Scheherazade New", files: &["ScheherazadeNew-Regular.ttf", "Scheherazade-Regular.ttf"] },
+];
+
+static DEFAULT_FAMILY: OnceLock<Option<String>> = OnceLock::new();
+
+/// Bundled font files present in `dir`, in preference order.
+pub fn bundled_fonts(dir: &Path) -> Vec<(QuranFont, PathBuf)> {
+    QURAN_FONTS
+        .iter()
+        .filter_map(|f| f.files.iter().map(|file| dir.join(file)).find(|p| p.is_file()).map(|p| (*f, p)))
+        .collect()
+}
+
+/// The preferred Quranic family among the families Pango can see.
+pub fn pick_family<S: AsRef<str>>(available: &[S]) -> Option<&'static str> {
+    QURAN_FONTS
+        .iter()
+        .map(|f| f.family)
+        .find(|family| available.iter().any(|a| a.as_ref().eq_ignore_ascii_case(family)))
+}
+
+/// Records the family picked at startup; later calls are ignored.
+pub fn set_default_family(family: Option<&str>) {
+    let _ = DEFAULT_FAMILY.set(family.map(str::to_string));
+}
+
+/// Family for Arabic text when the user has not chosen one. `None` means no
+/// Quranic font is available and the theme font is used.
+pub fn default_family() -> Option<&'static str> {
+    DEFAULT_FAMILY.get().and_then(|f| f.as_deref())
+}
+
+#[cfg(all(any(feature = "gui", feature = "render"), target_os = "linux"))]
+mod ffi {
+    use std::os::raw::{c_int, c_uchar, c_void};
+
+    #[link(name = "fontconfig")]
+    extern "C" {
+        pub fn FcConfigGetCurrent() -> *mut c_void;
+        pub fn FcConfigAppFontAddFile(config: *mut c_void, file: *const c_uchar) -> c_int;
+    }
+}
+
+/// Adds the bundled fonts to the application font set. Returns the families
+/// that were registered.
+#[cfg(all(any(feature = "gui", feature = "render"), target_os = "linux"))]
+pub fn register_bundled(dir: &Path) -> Vec<&'static str> {
+    use std::os::unix::ffi::OsStrExt;
+    let mut out = Vec::new();
+    for (font, path) in bundled_fonts(dir) {
+        let Ok(c_path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else { continue };
+        // SAFETY: fontconfig copies the path; the current config is owned by the library.
+        let ok = unsafe { ffi::FcConfigAppFontAddFile(ffi::FcConfigGetCurrent(), c_path.as_ptr() as *const _) } != 0;
+        if ok {
+            out.push(font.family);
+        } else {
+            tracing::warn!("could not register font {}", path.display());
+        }
+    }
+    out
+}
+
+#[cfg(all(any(feature = "gui", feature = "render"), not(target_os = "linux")))]
+pub fn register_bundled(_dir: &Path) -> Vec<&'static str> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_bundled_files_in_preference_order() {
+        let dir = std::env::temp_dir().join(format!("hyprquran-fonts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("ScheherazadeNew-Regular.ttf"), b"").unwrap();
+        std::fs::write(dir.join("AmiriQuran.ttf"), b"").unwrap();
+        let found: Vec<&str> = bundled_fonts(&dir).iter().map(|(f, _)| f.family).collect();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found, vec!["Amiri Quran", "Scheherazade New"]);
+    }
+
+    #[test]
+    fn picks_first_available_family() {
+        assert_eq!(pick_family(&["DejaVu Sans", "scheherazade new", "Amiri Quran"]), Some("Amiri Quran"));
+        assert_eq!(pick_family(&["Scheherazade New"]), Some("Scheherazade New"));
+        assert_eq!(pick_family(&["DejaVu Sans"]), None);
+    }
+}
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+// Translation text keeps footnote markers inline as `[^label]`, with the
+// bodies stored separately. Plain `[...]` spans are translator insertions.
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Footnote {
+    pub marker: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Insertion(&'a str),
+    Marker(&'a str),
+}
+
+/// Splits a raw dump line such as `Guide us.[^1] [^1]: i.e. Islam.` into the
+/// marker-bearing text and its footnote bodies. Also accepts quran.com style
+/// `<sup foot_note=123>1</sup>` markers, which are rewritten to `[^1]`; their
+/// bodies are found by the `foot_note` id, as in `[^123]: ...`.
+pub fn extract(raw: &str) -> (String, Vec<Footnote>) {
+    let (raw, ids) = rewrite_sup_markers(raw);
+    let mut defs = Vec::new();
+    let body_end = find_definition(&raw, 0).unwrap_or(raw.len());
+    let text = raw[..body_end].trim_end().to_string();
+    let mut pos = body_end;
+    while pos < raw.len() {
+        let Some(close) = raw[pos..].find("]:") else { break };
+        let key = &raw[pos + 2..pos + close];
+        let marker = ids.iter().find(|(id, _)| id == key).map_or(key, |(_, label)| label).to_string();
+        let start = pos + close + 2;
+        let end = find_definition(&raw, start).unwrap_or(raw.len());
+        defs.push(Footnote { marker, text: raw[start..end].trim().to_string() });
+        pos = end;
+    }
+    (text, defs)
+}
+
+fn find_definition(s: &str, from: usize) -> Option<usize> {
+    let mut at = from;
+    while let Some(i) = s[at..].find("[^") {
+        let start = at + i;
+        if let Some(close) = s[start..].find(']') {
+            if s[start + close + 1..].starts_with(':') {
+                return Some(start);
+            }
+            at = start + close + 1;
+        } else {
+            return None;
+        }
+    }
+    None
+}
+
+// Returns the rewritten text and the `foot_note` id of each label.
+fn rewrite_sup_markers(raw: &str) -> (String, Vec<(String, String)>) {
+    let mut out = String::with_capacity(raw.len());
+    let mut ids = Vec::new();
+    let mut rest = raw;
+    while let Some(i) = rest.find("<sup") {
+        let tag = &rest[i..];
+        let (Some(open_end), Some(close)) = (tag.find('>'), tag.find("</sup>")) else { break };
+        if open_end > close {
+            out.push_str(&rest[..i + "<sup".len()]);
+            rest = &rest[i + "<sup".len()..];
+            continue;
+        }
+        out.push_str(&rest[..i]);
+        let label = tag[open_end + 1..close].trim();
+        let id = tag["<sup".len()..open_end]
+            .split_whitespace()
+            .find_map(|attr| attr.strip_prefix("foot_note="))
+            .map(|id| id.trim_matches(|c| c == '"' || c == '\''));
+        if let Some(id) = id.filter(|id| !id.is_empty()) {
+            ids.push((id.to_string(), label.to_string()));
+        }
+        out.push_str(&format!("[^{}]", label));
+        rest = &tag[close + "</sup>".len()..];
+    }
+    out.push_str(rest);
+    (out, ids)
+}
+
+pub fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut out = Vec::new();
+    let mut rest = text;
+    while let Some(i) = rest.find('[') {
+        let Some(close) = rest[i..].find(']') else { break };
+        if i > 0 {
+            out.push(Segment::Text(&rest[..i]));
+        }
+        let inner = &rest[i + 1..i + close];
+        match inner.strip_prefix('^') {
+            Some(marker) => out.push(Segment::Marker(marker)),
+            None => out.push(Segment::Insertion(&rest[i..i + close + 1])),
+        }
+        rest = &rest[i + close + 1..];
+    }
+    if !rest.is_empty() {
+        out.push(Segment::Text(rest));
+    }
+    out
+}
+
+/// Text with footnote markers removed, for search, copying and plain-text output.
+pub fn plain(text: &str) -> String {
+    segments(text)
+        .into_iter()
+        .filter_map(|s| match s {
+            Segment::Text(t) | Segment::Insertion(t) => Some(t),
+            Segment::Marker(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_inline_definitions() {
+        let (text, notes) = extract("Guide us to the straight path.[^1] [^1]: i.e. Islam.");
+        assert_eq!(text, "Guide us to the straight path.[^1]");
+        assert_eq!(notes, vec![Footnote { marker: "1".into(), text: "i.e. Islam.".into() }]);
+    }
+
+    #[test]
+    fn rewrites_sup_markers() {
+        let (text, notes) = extract("Lord<sup foot_note=77>1</sup> of the worlds.");
+        assert_eq!(text, "Lord[^1] of the worlds.");
+        assert!(notes.is_empty());
+        let (text, notes) = extract("Lord<sup foot_note=\"77\">1</sup> of the worlds. [^77]: Or Sustainer.");
+        assert_eq!(text, "Lord[^1] of the worlds.");
+        assert_eq!(notes, vec![Footnote { marker: "1".into(), text: "Or Sustainer.".into() }]);
+    }
+
+    #[test]
+    fn keeps_malformed_sup_tags() {
+        assert_eq!(extract("a<sup</sup> b").0, "a<sup</sup> b");
+        assert_eq!(extract("a<sup>2").0, "a<sup>2");
+    }
+
+    #[test]
+    fn segments_insertions_and_markers() {
+        let segs = segments("[All] praise is [due] to Allah.[^a]");
+        assert_eq!(
+            segs,
+            vec![
+                Segment::Insertion("[All]"),
+                Segment::Text(" praise is "),
+                Segment::Insertion("[due]"),
+                Segment::Text(" to Allah."),
+                Segment::Marker("a"),
+            ]
+        );
+        assert_eq!(plain("[All] praise.[^a]"), "[All] praise.");
+    }
+}
@@ -1,14 +1,32 @@
+pub mod card;
+pub mod citation;
+pub mod cli;
 pub mod data;
+pub mod export;
+pub mod fonts;
+pub mod footnotes;
 pub mod state;
 pub mod storage;
 pub mod logging;
+pub mod mushaf;
+pub mod notes;
+pub mod range;
+pub mod reference;
 pub mod surah_index;
+pub mod tajweed;
+pub mod theme;
+pub mod transliteration;
+pub mod uri;
 #[cfg(feature = "gui")]
 pub mod app;
 #[cfg(feature = "gui")]
+pub mod portal;
+#[cfg(feature = "gui")]
 pub mod ui;
 #[cfg(feature = "sqlite")]
 pub mod db;
+#[cfg(feature = "render")]
+pub mod pdf;
 
 #[cfg(test)]
 mod tests {
@@ -1,35 +1,328 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::env;
-use hyprquran::state::AyahRef;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use hyprquran::card::{self, AspectRatio, Card, CardFormat, CardTemplate};
+use hyprquran::cli::{self, Bidi, OutputFormat, RenderOptions, WaybarSource};
+use hyprquran::data;
+use hyprquran::export::{self, Document, ExportFormat};
+use hyprquran::range::AyahRange;
+use hyprquran::reference;
+use hyprquran::state::AppState;
+use hyprquran::storage;
+use hyprquran::surah_index::default_surahs;
+
+/// Options that print or write something and exit instead of opening the reader.
+const OUTPUT_OPTIONS: [&str; 5] = ["--export", "--card", "--waybar", "--dmenu", "--pick"];
 
 fn main() -> Result<()> {
     hyprquran::logging::init();
-    let mut surah: Option<u16> = None;
-    let mut ayah: Option<u16> = None;
-    let mut args = env::args().skip(1).peekable();
+    let args: Vec<String> = env::args().collect();
+    let command = args.get(1).filter(|a| matches!(a.as_str(), "show" | "search" | "random" | "help" | "--help" | "-h"));
+    if let Some(command) = command {
+        return run_command(command, args[2..].to_vec());
+    }
+    if args.iter().skip(1).any(|a| OUTPUT_OPTIONS.contains(&a.as_str())) {
+        return run_output(args[1..].to_vec());
+    }
+    // The reader parses its own options, in whichever instance is already running.
+    #[cfg(feature = "gui")]
+    {
+        hyprquran::app::run(args)
+    }
+    #[cfg(not(feature = "gui"))]
+    {
+        // Without the GUI, opening an ayah or a link prints it instead.
+        let words: Vec<String> = args[1..].iter().take_while(|a| !a.starts_with('-')).cloned().collect();
+        if words.is_empty() {
+            return run_command("help", Vec::new());
+        }
+        let mut show = launch_args(&words.join(" "))?;
+        show.extend(args[1 + words.len()..].iter().cloned());
+        run_command("show", show)
+    }
+}
+
+/// The reference a launcher pick, reference or `quran:` link opens, plus the
+/// `--lang` a link asks for. `show` and the reader take the same arguments.
+fn launch_args(input: &str) -> Result<Vec<String>> {
+    let (r, translation) = cli::launch_target(input)?;
+    let mut args = vec![r.to_string()];
+    args.extend(translation.into_iter().flat_map(|t| ["--lang".to_string(), t]));
+    Ok(args)
+}
+
+/// Runs `--waybar`, `--dmenu`, `--pick`, `--export` or `--card`.
+fn run_output(args: Vec<String>) -> Result<()> {
+    let mut target: Option<String> = None;
+    let mut export_to: Option<PathBuf> = None;
+    let mut langs: Vec<String> = Vec::new();
+    let mut tafsir: Option<i64> = None;
+    let mut card_to: Option<PathBuf> = None;
+    let mut template: Option<String> = None;
+    let mut ratio: Option<String> = None;
+    let mut waybar: Option<String> = None;
+    let mut today = false;
+    let mut dmenu = false;
+    let mut with_bookmarks = false;
+    let mut pick: Option<String> = None;
+    let mut print = false;
+    let mut args = args.into_iter().peekable();
     while let Some(arg) = args.next() {
         match arg.as_str() {
-            "--surah" => {
-                if let Some(v) = args.next() { surah = v.parse::<u16>().ok(); }
+            "--export" => { export_to = args.next().map(PathBuf::from); }
+            "--lang" => {
+                if let Some(v) = args.next() { langs.push(v); }
             }
-            "--ayah" => {
-                if let Some(v) = args.next() { ayah = v.parse::<u16>().ok(); }
+            "--card" => { card_to = args.next().map(PathBuf::from); }
+            "--template" => { template = args.next(); }
+            "--ratio" => { ratio = args.next(); }
+            "--waybar" => { waybar = Some(args.next_if(|a| !a.starts_with('-')).unwrap_or_else(|| "today".into())); }
+            "--today" => { today = true; }
+            "--dmenu" => { dmenu = true; }
+            "--bookmarks" => { with_bookmarks = true; }
+            "--pick" => { pick = Some(args.next_if(|a| !a.starts_with('-')).unwrap_or_default()); }
+            "--print" => { print = true; }
+            "--tafsir" => {
+                if let Some(v) = args.next() { tafsir = v.parse::<i64>().ok(); }
             }
-            _ => {}
+            _ if !arg.starts_with("--") => { target = Some(arg); }
+            _ => return Err(anyhow!("unknown option {}; see hyprquran --help", arg)),
+        }
+    }
+    if let Some(source) = waybar {
+        let ids: Vec<&str> = WaybarSource::ALL.iter().map(|w| w.id()).collect();
+        let source = WaybarSource::from_id(&source).ok_or_else(|| anyhow!("unknown --waybar source {}; try {}", source, ids.join(", ")))?;
+        return print_waybar(source);
+    }
+    if dmenu {
+        let bookmarks = if with_bookmarks { storage::load().map(|p| p.bookmarks).unwrap_or_default() } else { Vec::new() };
+        for line in cli::dmenu_lines(&default_surahs(), &bookmarks) {
+            println!("{}", line);
         }
+        return Ok(());
     }
-    let init = match (surah, ayah) {
-        (Some(s), Some(a)) => Some(AyahRef { surah_id: s, ayah_index: a }),
-        _ => None,
+    if let Some(mut line) = pick {
+        // The launcher's choice comes on stdin when it is not given inline.
+        if line.is_empty() {
+            std::io::stdin().read_line(&mut line)?;
+        }
+        // Nothing chosen: the launcher was dismissed.
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+        let mut args = launch_args(&line)?;
+        // Without the reader the choice is printed.
+        if print || !cfg!(feature = "gui") {
+            args.extend(langs.iter().flat_map(|l| ["--lang".to_string(), l.clone()]));
+            return run_command("show", args);
+        }
+        #[cfg(feature = "gui")]
+        return hyprquran::app::run([vec![env::args().next().unwrap_or_default()], args].concat());
+    }
+    if today && target.is_none() {
+        let at = cli::verse_of_the_day(&default_surahs(), cli::local_day());
+        target = Some(format!("{}:{}", at.surah_id, at.ayah_index));
+    }
+    if let Some(path) = export_to {
+        let target = target.ok_or_else(|| anyhow!("--export needs a reference such as 67 or 2:255-257"))?;
+        return export_range(&target, &path, langs, tafsir);
+    }
+    if let Some(path) = card_to {
+        let target = target.ok_or_else(|| anyhow!("--card needs a reference such as 2:255"))?;
+        return make_card(&target, &path, langs.first().cloned(), template.as_deref(), ratio.as_deref());
+    }
+    Ok(())
+}
+
+const USAGE: &str = "\
+Usage:
+  hyprquran [REFERENCE]                open the reader, optionally at 2:255 or Yasin 12
+  hyprquran show REFERENCE             print ayat, e.g. 2:255-257, 112 or Al-Kahf 10
+  hyprquran search QUERY               search a translation, or the Arabic text for Arabic queries
+  hyprquran random                     print a random ayah
+
+Options for show, search and random:
+  -t, --translation NAME   language code or part of an edition name; repeatable
+  -l, --lang CODE          same as --translation
+  -f, --format FORMAT      plain, ansi or json (default: ansi on a terminal, plain otherwise)
+      --bidi MODE          auto, logical or visual ordering of right-to-left lines
+  -n, --limit N            most search results to print (default: 20)
+
+Reader options (a window that is already open moves there):
+  -s, --surah N            open at a surah
+  -a, --ayah N             and at this ayah of it
+  -r, --ref REFERENCE      same as giving REFERENCE
+      --today              open the verse of the day
+  -l, --lang NAME          show a translation, by language code or part of an edition name
+      --tafsir ID          open a tafsir source beside the text
+
+Output options:
+  --waybar [SOURCE]        print Waybar module JSON for today's ayah (today) or the reading position (position)
+  --dmenu [--bookmarks]    list surahs (and bookmarks) for rofi, wofi or fuzzel
+  --pick [LINE] [--print]  open the line chosen from --dmenu (read from stdin if not given), or print it
+  --export PATH            write REFERENCE, or --today's ayah, to a .pdf or .html file (--lang, --tafsir)
+  --card PATH              draw REFERENCE, or --today's ayah, as a .png or .svg verse card (--lang, --template, --ratio)
+";
+
+/// The translations and reading position saved by the app.
+fn saved_state() -> AppState {
+    let mut st = AppState::new();
+    if let Some(p) = storage::load() {
+        st.translation_lang = p.translation_lang;
+        st.compare_langs = p.compare_langs;
+        st.current = p.last;
+    }
+    st
+}
+
+/// `(lang, edition name)` for each of `langs`.
+fn edition_names(langs: &[String], available: &[(String, String)]) -> Vec<(String, String)> {
+    langs
+        .iter()
+        .map(|l| (l.clone(), available.iter().find(|(code, _)| code == l).map(|(_, n)| n.clone()).unwrap_or_else(|| l.clone())))
+        .collect()
+}
+
+/// One line of Waybar JSON. Clicking the module should run `hyprquran
+/// --today`, or plain `hyprquran` for the reading position.
+fn print_waybar(source: WaybarSource) -> Result<()> {
+    let st = saved_state();
+    let at = match source {
+        WaybarSource::Today => cli::verse_of_the_day(&st.surahs, cli::local_day()),
+        WaybarSource::Position => st.current.clone(),
     };
-    
-    #[cfg(feature = "gui")]
-    {
-        hyprquran::app::run(init)
+    let langs = st.translation_langs();
+    let editions = edition_names(&langs, &data::get_available_translations().unwrap_or_default());
+    let ayat = data::load_ayat_in_range(&AyahRange::single(at), &langs)?;
+    let ayah = ayat.first().ok_or_else(|| anyhow!("no text for the ayah"))?;
+    println!("{}", serde_json::to_string(&cli::waybar_module(ayah, &st.surahs, &editions, source))?);
+    Ok(())
+}
+
+/// Runs `show`, `search` or `random`, which print to the terminal and work
+/// without the GUI.
+fn run_command(command: &str, args: Vec<String>) -> Result<()> {
+    if matches!(command, "help" | "--help" | "-h") {
+        print!("{}", USAGE);
+        return Ok(());
     }
-    #[cfg(not(feature = "gui"))]
-    {
-        println!("GUI feature is not enabled. Please compile with --features gui");
-        Ok(())
+    let mut words: Vec<String> = Vec::new();
+    let mut wanted: Vec<String> = Vec::new();
+    let mut format: Option<OutputFormat> = None;
+    let mut bidi = Bidi::default();
+    let mut limit = 20;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-t" | "--translation" | "-l" | "--lang" => {
+                if let Some(v) = args.next() { wanted.push(v); }
+            }
+            "-f" | "--format" => {
+                let v = args.next().unwrap_or_default();
+                let ids: Vec<&str> = OutputFormat::ALL.iter().map(|f| f.id()).collect();
+                format = Some(OutputFormat::from_id(&v).ok_or_else(|| anyhow!("unknown format {}; try {}", v, ids.join(", ")))?);
+            }
+            "--bidi" => {
+                let v = args.next().unwrap_or_default();
+                let ids: Vec<&str> = Bidi::ALL.iter().map(|b| b.id()).collect();
+                bidi = Bidi::from_id(&v).ok_or_else(|| anyhow!("unknown bidi mode {}; try {}", v, ids.join(", ")))?;
+            }
+            "-n" | "--limit" => {
+                let v = args.next().unwrap_or_default();
+                limit = v.parse().map_err(|_| anyhow!("--limit needs a number, not {}", v))?;
+            }
+            _ => words.push(arg),
+        }
+    }
+    let st = saved_state();
+    let available = data::get_available_translations().unwrap_or_default();
+    let langs = if wanted.is_empty() {
+        st.translation_langs()
+    } else {
+        wanted
+            .iter()
+            .map(|w| cli::resolve_translation(w, &available).ok_or_else(|| anyhow!("no translation matches {}", w)))
+            .collect::<Result<Vec<_>>>()?
+    };
+    let editions = edition_names(&langs, &available);
+    let terminal = std::io::stdout().is_terminal();
+    let options = RenderOptions {
+        format: format.unwrap_or(if terminal { OutputFormat::Ansi } else { OutputFormat::Plain }),
+        visual: bidi.visual(terminal),
+        width: env::var("COLUMNS").ok().and_then(|c| c.parse().ok()).unwrap_or(80),
+    };
+    let query = words.join(" ");
+    let mut term = None;
+    let ayat = match command {
+        "show" => {
+            let r = reference::parse(&query)?;
+            let range = r.range().ok_or_else(|| anyhow!("{} needs an imported mushaf layout", r))?;
+            data::load_ayat_in_range(&range, &langs)?
+        }
+        "search" => {
+            if query.is_empty() {
+                return Err(anyhow!("search needs a query"));
+            }
+            // Arabic queries search the Arabic text, anything else the first translation.
+            let arabic = query.chars().any(|c| ('\u{0600}'..='\u{06FF}').contains(&c));
+            let lang = if arabic { None } else { Some(langs.first().ok_or_else(|| anyhow!("no translation to search; pass --lang"))?.as_str()) };
+            let hits = data::search_ayat(&query, lang)?;
+            if hits.len() > limit && options.format != OutputFormat::Json {
+                eprintln!("{} results, showing the first {} (see --limit)", hits.len(), limit);
+            }
+            term = lang.map(|_| query.as_str());
+            let mut ayat = Vec::new();
+            for at in hits.iter().take(limit) {
+                ayat.extend(data::load_ayat_in_range(&AyahRange::single(at.clone()), &langs)?);
+            }
+            ayat
+        }
+        _ => {
+            let seed = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+            let at = cli::random_ayah(&st.surahs, seed);
+            data::load_ayat_in_range(&AyahRange::single(at), &langs)?
+        }
+    };
+    let out = cli::render(&ayat, &st.surahs, &editions, &options, term);
+    if !out.is_empty() {
+        println!("{}", out);
+    }
+    Ok(())
+}
+
+/// Writes a handout for `target`. Without `--lang` the translations shown in
+/// the app are used.
+fn export_range(target: &str, path: &std::path::Path, langs: Vec<String>, tafsir: Option<i64>) -> Result<()> {
+    let range: AyahRange = target.parse()?;
+    let format = ExportFormat::from_path(path).ok_or_else(|| anyhow!("{} should end in .pdf or .html", path.display()))?;
+    let st = saved_state();
+    let langs = if langs.is_empty() { st.translation_langs() } else { langs };
+    let doc = Document::load(&range, &langs, tafsir, &st.surahs)?;
+    export::export(&doc, path, format)?;
+    println!("{}", path.display());
+    Ok(())
+}
+
+/// Draws a verse card for `target`. Options left out fall back to the ones
+/// last used in the app.
+fn make_card(target: &str, path: &std::path::Path, lang: Option<String>, template: Option<&str>, ratio: Option<&str>) -> Result<()> {
+    let range: AyahRange = target.parse()?;
+    let format = CardFormat::from_path(path).ok_or_else(|| anyhow!("{} should end in .png or .svg", path.display()))?;
+    let mut options = storage::load().map(|p| p.card).unwrap_or_default();
+    if let Some(t) = template {
+        let ids: Vec<&str> = CardTemplate::ALL.iter().map(|t| t.id()).collect();
+        options.template = CardTemplate::from_id(t).ok_or_else(|| anyhow!("unknown template {}; try {}", t, ids.join(", ")))?;
+    }
+    if let Some(r) = ratio {
+        let ids: Vec<&str> = AspectRatio::ALL.iter().map(|r| r.id()).collect();
+        options.ratio = AspectRatio::from_id(r).ok_or_else(|| anyhow!("unknown ratio {}; try {}", r, ids.join(", ")))?;
+    }
+    if lang.is_some() {
+        options.lang = lang;
     }
+    let card = Card::load(&range, options.lang.as_deref(), &AppState::new().surahs)?;
+    card::save(&card, &options, path, format)?;
+    println!("{}", path.display());
+    Ok(())
 }
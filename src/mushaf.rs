@@ -0,0 +1,215 @@
+use crate::state::AyahRef;
+use serde::{Deserialize, Serialize};
+
+// Layout of the standard 15-line Madani mushaf. Each line of a page is
+// described by the words it carries, addressed as (ayah, word index), so a
+// page can be rebuilt from the plain ayah text.
+
+pub const PAGE_COUNT: u16 = 604;
+pub const LINES_PER_PAGE: u8 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineKind {
+    SurahName,
+    Basmala,
+    Ayah,
+}
+
+impl LineKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineKind::SurahName => "surah_name",
+            LineKind::Basmala => "basmala",
+            LineKind::Ayah => "ayah",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "surah_name" => Some(LineKind::SurahName),
+            "basmala" => Some(LineKind::Basmala),
+            "ayah" => Some(LineKind::Ayah),
+            _ => None,
+        }
+    }
+}
+
+/// A word position: `word` is 1-based within the ayah's whitespace-separated text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WordRef {
+    pub ayah: AyahRef,
+    pub word: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineSpec {
+    pub page: u16,
+    pub line: u8,
+    pub kind: LineKind,
+    pub surah_id: u16,
+    pub start: WordRef,
+    pub end: WordRef,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Word {
+    pub text: String,
+    pub ayah: AyahRef,
+    pub ends_ayah: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageLine {
+    pub line: u8,
+    pub kind: LineKind,
+    pub surah_id: u16,
+    pub words: Vec<Word>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MushafPage {
+    pub number: u16,
+    pub juz: u8,
+    pub lines: Vec<PageLine>,
+}
+
+impl MushafPage {
+    pub fn first_ayah(&self) -> Option<AyahRef> {
+        self.lines.iter().flat_map(|l| l.words.first()).map(|w| w.ayah.clone()).next()
+    }
+
+    pub fn last_ayah(&self) -> Option<AyahRef> {
+        self.lines.iter().rev().flat_map(|l| l.words.last()).map(|w| w.ayah.clone()).next()
+    }
+
+    pub fn contains(&self, r: &AyahRef) -> bool {
+        self.lines.iter().flat_map(|l| &l.words).any(|w| &w.ayah == r)
+    }
+
+    /// Surahs with text on this page, in reading order.
+    pub fn surahs(&self) -> Vec<u16> {
+        let mut out: Vec<u16> = Vec::new();
+        for l in &self.lines {
+            if !out.contains(&l.surah_id) {
+                out.push(l.surah_id);
+            }
+        }
+        out
+    }
+}
+
+/// Parses one layout dump line: `page|line|kind|surah|ayah:word|ayah:word`,
+/// pipe or tab separated. Name and basmala lines may leave the ranges empty.
+pub fn parse_line_spec(line: &str) -> Option<LineSpec> {
+    let l = line.trim_start_matches('\u{feff}').trim();
+    if l.is_empty() || l.starts_with('#') { return None; }
+    let sep = if l.contains('|') { '|' } else { '\t' };
+    let parts: Vec<&str> = l.split(sep).map(str::trim).collect();
+    let page = parts.first()?.parse::<u16>().ok()?;
+    let line_no = parts.get(1)?.parse::<u8>().ok()?;
+    let kind = LineKind::parse(parts.get(2)?)?;
+    let surah_id = parts.get(3)?.parse::<u16>().ok()?;
+    let word = |s: Option<&&str>| -> Option<WordRef> {
+        match s.filter(|s| !s.is_empty()) {
+            Some(s) => {
+                let (a, w) = s.split_once(':')?;
+                Some(WordRef { ayah: AyahRef { surah_id, ayah_index: a.parse().ok()? }, word: w.parse().ok()? })
+            }
+            None => Some(WordRef { ayah: AyahRef { surah_id, ayah_index: 1 }, word: 0 }),
+        }
+    };
+    let start = word(parts.get(4))?;
+    let end = word(parts.get(5))?;
+    if page == 0 || page > PAGE_COUNT || line_no == 0 || line_no > LINES_PER_PAGE { return None; }
+    Some(LineSpec { page, line: line_no, kind, surah_id, start, end })
+}
+
+/// The page facing `page` in a two-page spread, as (right, left). Odd pages
+/// sit on the right of an open mushaf.
+pub fn spread(page: u16) -> (u16, Option<u16>) {
+    let right = if page % 2 == 1 { page } else { page - 1 };
+    let left = right + 1;
+    (right, (left <= PAGE_COUNT).then_some(left))
+}
+
+/// Builds the lines of one page. `ayah_text` returns the text of an ayah; a
+/// line spanning several ayat takes the trailing words of the first, all of
+/// the middle ones and the leading words of the last.
+pub fn layout_page(number: u16, specs: &[LineSpec], ayah_text: impl Fn(&AyahRef) -> Option<String>) -> MushafPage {
+    let mut lines = Vec::new();
+    for spec in specs.iter().filter(|s| s.page == number) {
+        let mut words = Vec::new();
+        if spec.kind == LineKind::Ayah {
+            let mut at = spec.start.ayah.clone();
+            loop {
+                let text = ayah_text(&at).unwrap_or_default();
+                let all: Vec<&str> = text.split_whitespace().collect();
+                let from = if at == spec.start.ayah { spec.start.word.max(1) as usize } else { 1 };
+                let to = if at == spec.end.ayah { (spec.end.word as usize).min(all.len()) } else { all.len() };
+                for (i, w) in all.iter().enumerate().take(to).skip(from - 1) {
+                    words.push(Word { text: w.to_string(), ayah: at.clone(), ends_ayah: i + 1 == all.len() });
+                }
+                if at.surah_id != spec.end.ayah.surah_id || at.ayah_index >= spec.end.ayah.ayah_index {
+                    break;
+                }
+                at = AyahRef { surah_id: at.surah_id, ayah_index: at.ayah_index + 1 };
+            }
+        }
+        lines.push(PageLine { line: spec.line, kind: spec.kind, surah_id: spec.surah_id, words });
+    }
+    lines.sort_by_key(|l| l.line);
+    let juz = lines
+        .iter()
+        .flat_map(|l| l.words.first())
+        .map(|w| crate::surah_index::juz_of(&w.ayah))
+        .next()
+        .unwrap_or(1);
+    MushafPage { number, juz, lines }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn w(s: u16, a: u16, word: u16) -> WordRef {
+        WordRef { ayah: AyahRef { surah_id: s, ayah_index: a }, word }
+    }
+
+    #[test]
+    fn lays_out_words_across_ayah_boundaries() {
+        let sf = crate::data::load_surah_text_fatiha().unwrap();
+        let specs = vec![
+            LineSpec { page: 1, line: 1, kind: LineKind::SurahName, surah_id: 1, start: w(1, 1, 0), end: w(1, 1, 0) },
+            LineSpec { page: 1, line: 2, kind: LineKind::Ayah, surah_id: 1, start: w(1, 1, 1), end: w(1, 2, 2) },
+            LineSpec { page: 1, line: 3, kind: LineKind::Ayah, surah_id: 1, start: w(1, 2, 3), end: w(1, 3, 2) },
+        ];
+        let page = layout_page(1, &specs, |r| sf.ayat.get(r.ayah_index as usize - 1).cloned());
+        assert_eq!(page.juz, 1);
+        assert_eq!(page.lines.len(), 3);
+        assert!(page.lines[0].words.is_empty());
+        let line2: Vec<&str> = page.lines[1].words.iter().map(|w| w.text.as_str()).collect();
+        let expected: Vec<&str> = sf.ayat[0].split_whitespace().chain(sf.ayat[1].split_whitespace().take(2)).collect();
+        assert_eq!(line2, expected);
+        assert!(page.lines[1].words[3].ends_ayah);
+        assert_eq!(page.lines[2].words.len(), 4);
+        assert_eq!(page.first_ayah(), Some(AyahRef { surah_id: 1, ayah_index: 1 }));
+    }
+
+    #[test]
+    fn spreads_pair_odd_pages_on_the_right() {
+        assert_eq!(spread(1), (1, Some(2)));
+        assert_eq!(spread(2), (1, Some(2)));
+        assert_eq!(spread(603), (603, Some(604)));
+    }
+
+    #[test]
+    fn parses_layout_lines() {
+        assert_eq!(
+            parse_line_spec("2|3|ayah|2|1:1|2:4"),
+            Some(LineSpec { page: 2, line: 3, kind: LineKind::Ayah, surah_id: 2, start: w(2, 1, 1), end: w(2, 2, 4) })
+        );
+        assert_eq!(parse_line_spec("2\t1\tsurah_name\t2").map(|s| s.kind), Some(LineKind::SurahName));
+        assert_eq!(parse_line_spec("605|1|ayah|114|1:1|1:3"), None);
+    }
+}
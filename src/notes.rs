@@ -0,0 +1,223 @@
+use crate::state::AyahRef;
+use serde::{Deserialize, Serialize};
+
+// Study notes attached to an ayah or to a run of its words. A note may carry
+// a highlight colour, a markdown body, or both.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Highlight {
+    Yellow,
+    Green,
+    Blue,
+    Pink,
+    Orange,
+}
+
+impl Highlight {
+    pub const ALL: [Highlight; 5] = [Highlight::Yellow, Highlight::Green, Highlight::Blue, Highlight::Pink, Highlight::Orange];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Highlight::Yellow => "yellow",
+            Highlight::Green => "green",
+            Highlight::Blue => "blue",
+            Highlight::Pink => "pink",
+            Highlight::Orange => "orange",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Highlight::Yellow => "Yellow",
+            Highlight::Green => "Green",
+            Highlight::Blue => "Blue",
+            Highlight::Pink => "Pink",
+            Highlight::Orange => "Orange",
+        }
+    }
+
+    /// Marker-pen background; highlighted text is drawn dark on top of it so
+    /// it reads the same in light and dark themes.
+    pub fn colour(&self) -> &'static str {
+        match self {
+            Highlight::Yellow => "#fff176",
+            Highlight::Green => "#aed581",
+            Highlight::Blue => "#81d4fa",
+            Highlight::Pink => "#f8bbd0",
+            Highlight::Orange => "#ffcc80",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|h| h.id() == id)
+    }
+}
+
+pub const HIGHLIGHT_TEXT: &str = "#1a1a1a";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Note {
+    /// Row id in the user database; 0 until first saved.
+    pub id: i64,
+    pub ayah: AyahRef,
+    /// 1-based inclusive word range; `None` covers the whole ayah.
+    pub words: Option<(u16, u16)>,
+    pub highlight: Option<Highlight>,
+    /// Markdown.
+    pub body: String,
+    /// Unix seconds.
+    pub created: u64,
+    pub updated: u64,
+}
+
+pub fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl Note {
+    pub fn new(ayah: AyahRef) -> Self {
+        let created = now();
+        Self { id: 0, ayah, words: None, highlight: None, body: String::new(), created, updated: created }
+    }
+
+    /// Byte range of the annotated words in the ayah text.
+    pub fn span(&self, text: &str) -> Option<(usize, usize)> {
+        match self.words {
+            Some(words) => word_span(text, words),
+            None => (!text.is_empty()).then_some((0, text.len())),
+        }
+    }
+
+    /// First line of the body without heading or list markers.
+    pub fn title(&self) -> &str {
+        self.body
+            .lines()
+            .map(|l| l.trim().trim_start_matches('#').trim_start_matches(['-', '*', '>']).trim())
+            .find(|l| !l.is_empty())
+            .unwrap_or("")
+    }
+
+    /// Free text matches the surah name, the `s:a` reference and the body.
+    pub fn matches(&self, query: &str, surah_name: &str) -> bool {
+        let q = query.trim().to_lowercase();
+        if q.is_empty() {
+            return true;
+        }
+        let reference = format!("{}:{}", self.ayah.surah_id, self.ayah.ayah_index);
+        surah_name.to_lowercase().contains(&q) || reference.starts_with(&q) || self.body.to_lowercase().contains(&q)
+    }
+}
+
+/// Byte range covering words `from..=to` (1-based) of `text`. A range running
+/// past the last word is cut short.
+pub fn word_span(text: &str, (from, to): (u16, u16)) -> Option<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() {
+            if let Some(s) = start.take() {
+                words.push((s, i));
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, text.len()));
+    }
+    let from = from.max(1) as usize;
+    let to = (to as usize).max(from).min(words.len());
+    let first = words.get(from - 1)?;
+    let last = words.get(to - 1)?;
+    Some((first.0, last.1))
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+// Emphasis, strong and code spans. Unclosed markers are kept as text.
+fn inline_markup(s: &str) -> String {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        let (marker, open, close) = match c {
+            '`' => ("`", "<tt>", "</tt>"),
+            '*' if rest.starts_with("**") => ("**", "<b>", "</b>"),
+            '*' | '_' => (&rest[..1], "<i>", "</i>"),
+            _ => ("", "", ""),
+        };
+        if !marker.is_empty() {
+            let after = &rest[marker.len()..];
+            if let Some(end) = after.find(marker).filter(|&e| e > 0) {
+                let inner = &after[..end];
+                out.push_str(open);
+                out.push_str(&if marker == "`" { escape(inner) } else { inline_markup(inner) });
+                out.push_str(close);
+                rest = &after[end + marker.len()..];
+                continue;
+            }
+        }
+        out.push_str(&escape(&rest[..c.len_utf8()]));
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// Renders the markdown subset used in notes (headings, lists, quotes,
+/// emphasis and code) as Pango markup.
+pub fn markdown_markup(md: &str) -> String {
+    md.lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            let level = trimmed.chars().take_while(|&c| c == '#').count();
+            if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+                let size = if level == 1 { "x-large" } else if level == 2 { "large" } else { "medium" };
+                format!("<span size=\"{}\" weight=\"bold\">{}</span>", size, inline_markup(trimmed[level..].trim()))
+            } else if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+                format!("  • {}", inline_markup(item))
+            } else if let Some(quote) = trimmed.strip_prefix('>') {
+                format!("<i>{}</i>", inline_markup(quote.trim_start()))
+            } else {
+                inline_markup(line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans_word_ranges() {
+        let text = "بِسْمِ ٱللَّهِ ٱلرَّحْمَٰنِ ٱلرَّحِيمِ";
+        let (s, e) = word_span(text, (2, 3)).unwrap();
+        assert_eq!(&text[s..e], "ٱللَّهِ ٱلرَّحْمَٰنِ");
+        let (s, e) = word_span(text, (4, 9)).unwrap();
+        assert_eq!(&text[s..e], "ٱلرَّحِيمِ");
+        assert_eq!(word_span(text, (5, 6)), None);
+        let mut note = Note::new(AyahRef { surah_id: 1, ayah_index: 1 });
+        assert_eq!(note.span(text), Some((0, text.len())));
+        note.words = Some((1, 1));
+        assert_eq!(note.span(text).map(|(s, e)| &text[s..e]), Some("بِسْمِ"));
+    }
+
+    #[test]
+    fn renders_markdown_subset() {
+        assert_eq!(markdown_markup("## On *mercy*"), "<span size=\"large\" weight=\"bold\">On <i>mercy</i></span>");
+        assert_eq!(markdown_markup("- **both** names & `ar-Rahman`"), "  • <b>both</b> names &amp; <tt>ar-Rahman</tt>");
+        assert_eq!(markdown_markup("> quote\n2 * 3 < 4"), "<i>quote</i>\n2 * 3 &lt; 4");
+        let mut note = Note::new(AyahRef { surah_id: 2, ayah_index: 255 });
+        note.body = "\n# Ayat al-Kursi\nread nightly".into();
+        assert_eq!(note.title(), "Ayat al-Kursi");
+        assert!(note.matches("2:25", "Al-Baqarah"));
+        assert!(note.matches("NIGHTLY", "Al-Baqarah"));
+        assert!(!note.matches("kahf", "Al-Baqarah"));
+    }
+}
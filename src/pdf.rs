@@ -0,0 +1,182 @@
+use crate::citation::ayah_marker;
+use crate::data;
+use crate::export::{self, Document};
+use crate::fonts;
+use anyhow::Result;
+use pangocairo::{cairo, pango};
+use std::path::Path;
+
+// Lays a `Document` out on A4 pages with Pango, line by line so long
+// passages break across pages. Pango picks the paragraph direction from the
+// text, so Arabic and Urdu lines are shaped and aligned right to left.
+
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 56.0;
+const HEADER_HEIGHT: f64 = 28.0;
+const TEXT_WIDTH: f64 = PAGE_WIDTH - 2.0 * MARGIN;
+
+/// Family for the Arabic text, registering the bundled fonts when the GUI
+/// has not already done so.
+pub fn arabic_family() -> String {
+    if let Some(family) = fonts::default_family() {
+        return family.to_string();
+    }
+    let registered = fonts::register_bundled(&data::assets_dir().join("fonts"));
+    registered.first().copied().unwrap_or("Serif").to_string()
+}
+
+/// A layout `width` points wide with sizes in points rather than pixels.
+pub fn text_layout(cr: &cairo::Context, text: &str, font: &str, width: f64) -> pango::Layout {
+    let layout = pangocairo::functions::create_layout(cr);
+    pangocairo::functions::context_set_resolution(&layout.context(), 72.0);
+    layout.context_changed();
+    layout.set_font_description(Some(&pango::FontDescription::from_string(font)));
+    layout.set_width((width * pango::SCALE as f64) as i32);
+    layout.set_wrap(pango::WrapMode::WordChar);
+    // With auto direction on, Left means the start edge of each paragraph.
+    layout.set_auto_dir(true);
+    layout.set_alignment(pango::Alignment::Left);
+    layout.set_text(text);
+    layout
+}
+
+struct Writer<'a> {
+    cr: cairo::Context,
+    doc: &'a Document,
+    arabic: String,
+    surah: String,
+    page: u32,
+    y: f64,
+}
+
+impl Writer<'_> {
+    fn bottom(&self) -> f64 {
+        PAGE_HEIGHT - MARGIN
+    }
+
+    fn new_page(&mut self) -> Result<()> {
+        if self.page > 0 {
+            self.cr.show_page()?;
+        }
+        self.page += 1;
+        self.header()?;
+        self.y = MARGIN + HEADER_HEIGHT;
+        Ok(())
+    }
+
+    // Title and surah at the top, page number at the foot.
+    fn header(&self) -> Result<()> {
+        self.cr.save()?;
+        self.cr.set_source_rgb(0.4, 0.4, 0.4);
+        let title = text_layout(&self.cr, &self.doc.title, "Sans 8", TEXT_WIDTH);
+        self.cr.move_to(MARGIN, MARGIN - 8.0);
+        pangocairo::functions::show_layout(&self.cr, &title);
+        let surah = text_layout(&self.cr, &self.surah, "Sans 8", TEXT_WIDTH);
+        surah.set_auto_dir(false);
+        surah.set_alignment(pango::Alignment::Right);
+        self.cr.move_to(MARGIN, MARGIN - 8.0);
+        pangocairo::functions::show_layout(&self.cr, &surah);
+        self.cr.set_line_width(0.5);
+        self.cr.move_to(MARGIN, MARGIN + 6.0);
+        self.cr.line_to(PAGE_WIDTH - MARGIN, MARGIN + 6.0);
+        self.cr.stroke()?;
+        let number = text_layout(&self.cr, &self.page.to_string(), "Sans 8", TEXT_WIDTH);
+        number.set_alignment(pango::Alignment::Center);
+        self.cr.move_to(MARGIN, PAGE_HEIGHT - MARGIN + 16.0);
+        pangocairo::functions::show_layout(&self.cr, &number);
+        self.cr.restore()?;
+        Ok(())
+    }
+
+    /// Starts a new page unless `height` points still fit on this one.
+    fn keep(&mut self, height: f64) -> Result<()> {
+        if self.y + height > self.bottom() {
+            self.new_page()?;
+        }
+        Ok(())
+    }
+
+    fn paragraph(&mut self, layout: &pango::Layout, indent: f64, space_after: f64) -> Result<()> {
+        let scale = pango::SCALE as f64;
+        let mut iter = layout.iter();
+        loop {
+            let (_, logical) = iter.line_extents();
+            let (top, bottom) = iter.line_yrange();
+            let height = (bottom - top) as f64 / scale;
+            self.keep(height)?;
+            if let Some(line) = iter.line_readonly() {
+                let baseline = (iter.baseline() - top) as f64 / scale;
+                self.cr.move_to(MARGIN + indent + logical.x() as f64 / scale, self.y + baseline);
+                pangocairo::functions::show_layout_line(&self.cr, &line);
+            }
+            self.y += height;
+            if !iter.next_line() {
+                break;
+            }
+        }
+        self.y += space_after;
+        Ok(())
+    }
+
+    fn write(&mut self) -> Result<()> {
+        let arabic_font = format!("{} 18", self.arabic);
+        for (i, section) in self.doc.sections.iter().enumerate() {
+            let s = &section.surah;
+            self.surah = format!("{} · {}", s.name_en, s.name_ar);
+            if i == 0 {
+                self.new_page()?;
+            }
+            let heading = text_layout(&self.cr, &format!("{}. {}", s.id, s.name_en), "Serif Bold 14", TEXT_WIDTH);
+            let name = text_layout(&self.cr, &s.name_ar, &format!("{} 20", self.arabic), TEXT_WIDTH);
+            // Keep the heading with the start of the first ayah.
+            self.keep((heading.pixel_size().1 + name.pixel_size().1) as f64 + 60.0)?;
+            self.y += 8.0;
+            self.paragraph(&name, 0.0, 0.0)?;
+            self.paragraph(&heading, 0.0, 14.0)?;
+            for a in &section.ayat {
+                let r = &a.text.ayah;
+                let text = format!("{} {}", a.text.arabic, ayah_marker(r.ayah_index));
+                let arabic = text_layout(&self.cr, &text, &arabic_font, TEXT_WIDTH);
+                arabic.set_justify(true);
+                self.paragraph(&arabic, 0.0, 6.0)?;
+                for (lang, _) in &self.doc.editions {
+                    let Some((_, entry)) = a.text.translations.iter().find(|(l, _)| l == lang) else { continue };
+                    let layout = text_layout(&self.cr, "", "Serif 10.5", TEXT_WIDTH);
+                    layout.set_markup(&format!("({}) {}", r.ayah_index, export::translation_markup(&entry.text)));
+                    self.paragraph(&layout, 0.0, 4.0)?;
+                    for note in &entry.footnotes {
+                        let layout = text_layout(&self.cr, "", "Serif 8.5", TEXT_WIDTH - 12.0);
+                        layout.set_markup(&export::footnote_markup(note));
+                        self.paragraph(&layout, 12.0, 3.0)?;
+                    }
+                }
+                self.cr.set_source_rgb(0.3, 0.3, 0.3);
+                for t in &a.tafsir {
+                    let layout = text_layout(&self.cr, t, "Serif 9", TEXT_WIDTH - 12.0);
+                    self.paragraph(&layout, 12.0, 4.0)?;
+                }
+                self.cr.set_source_rgb(0.1, 0.1, 0.1);
+                self.y += 10.0;
+            }
+        }
+        if self.page == 0 {
+            self.new_page()?;
+        }
+        self.cr.show_page()?;
+        Ok(())
+    }
+}
+
+pub fn write_pdf(doc: &Document, path: &Path) -> Result<()> {
+    let surface = cairo::PdfSurface::new(PAGE_WIDTH, PAGE_HEIGHT, path)?;
+    surface.set_metadata(cairo::PdfMetadata::Title, &doc.title)?;
+    surface.set_metadata(cairo::PdfMetadata::Creator, "HyprQur'an")?;
+    let cr = cairo::Context::new(&surface)?;
+    cr.set_source_rgb(0.1, 0.1, 0.1);
+    let mut writer = Writer { cr, doc, arabic: arabic_family(), surah: String::new(), page: 0, y: 0.0 };
+    writer.write()?;
+    drop(writer);
+    surface.finish();
+    Ok(())
+}
@@ -0,0 +1,192 @@
+use crate::theme::ColorScheme;
+use anyhow::{anyhow, Result};
+use gio::prelude::*;
+
+// Reads and watches `org.freedesktop.appearance color-scheme` through the
+// XDG desktop portal's Settings interface.
+
+pub const DESTINATION: &str = "org.freedesktop.portal.Desktop";
+pub const PATH: &str = "/org/freedesktop/portal/desktop";
+pub const INTERFACE: &str = "org.freedesktop.portal.Settings";
+const NAMESPACE: &str = "org.freedesktop.appearance";
+const KEY: &str = "color-scheme";
+const TIMEOUT_MS: i32 = 500;
+
+fn call(conn: &gio::DBusConnection, method: &str) -> Result<glib::Variant> {
+    let reply = conn.call_sync(
+        Some(DESTINATION),
+        PATH,
+        INTERFACE,
+        method,
+        Some(&(NAMESPACE, KEY).to_variant()),
+        glib::VariantTy::new("(v)").ok(),
+        gio::DBusCallFlags::NONE,
+        TIMEOUT_MS,
+        gio::Cancellable::NONE,
+    )?;
+    reply.child_value(0).as_variant().ok_or_else(|| anyhow!("malformed portal reply"))
+}
+
+pub fn read_color_scheme(conn: &gio::DBusConnection) -> Result<ColorScheme> {
+    // ReadOne needs version 2 of the interface; the older Read wraps the
+    // value in a second variant.
+    let value = match call(conn, "ReadOne") {
+        Ok(v) => v,
+        Err(_) => call(conn, "Read")?.as_variant().ok_or_else(|| anyhow!("malformed portal reply"))?,
+    };
+    value
+        .get::<u32>()
+        .map(ColorScheme::from_portal)
+        .ok_or_else(|| anyhow!("unexpected color-scheme type {}", value.type_()))
+}
+
+/// Calls `f` whenever the portal reports a new colour scheme.
+pub fn watch_color_scheme(conn: &gio::DBusConnection, f: impl Fn(ColorScheme) + 'static) -> gio::SignalSubscriptionId {
+    conn.signal_subscribe(
+        Some(DESTINATION),
+        Some(INTERFACE),
+        Some("SettingChanged"),
+        Some(PATH),
+        Some(NAMESPACE),
+        gio::DBusSignalFlags::NONE,
+        move |_, _, _, _, _, params| {
+            let Some((namespace, key, value)) = params.get::<(String, String, glib::Variant)>() else { return };
+            if namespace == NAMESPACE && key == KEY {
+                if let Some(v) = value.get::<u32>() {
+                    f(ColorScheme::from_portal(v));
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::sync::mpsc;
+
+    const PORTAL_XML: &str = r#"
+        <node>
+          <interface name="org.freedesktop.portal.Settings">
+            <method name="ReadOne">
+              <arg type="s" name="namespace" direction="in"/>
+              <arg type="s" name="key" direction="in"/>
+              <arg type="v" name="value" direction="out"/>
+            </method>
+            <signal name="SettingChanged">
+              <arg type="s" name="namespace"/>
+              <arg type="s" name="key"/>
+              <arg type="v" name="value"/>
+            </signal>
+          </interface>
+        </node>"#;
+
+    fn connect(address: &str) -> gio::DBusConnection {
+        gio::DBusConnection::for_address_sync(
+            address,
+            gio::DBusConnectionFlags::AUTHENTICATION_CLIENT | gio::DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+            None,
+            gio::Cancellable::NONE,
+        )
+        .unwrap()
+    }
+
+    // Serves a fixed colour scheme from its own thread, then emits one
+    // SettingChanged switching to light.
+    fn spawn_portal(address: String, scheme: u32) -> mpsc::Receiver<()> {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let ctx = glib::MainContext::new();
+            ctx.with_thread_default(|| {
+                let conn = connect(&address);
+                let node = gio::DBusNodeInfo::for_xml(PORTAL_XML).unwrap();
+                let iface = node.lookup_interface(INTERFACE).unwrap();
+                let _id = conn
+                    .register_object(
+                        PATH,
+                        &iface,
+                        move |_, _, _, _, _, _, invocation| invocation.return_value(Some(&(scheme.to_variant(),).to_variant())),
+                        |_, _, _, _, _| ().to_variant(),
+                        |_, _, _, _, _, _| false,
+                    )
+                    .unwrap();
+                conn.call_sync(
+                    Some("org.freedesktop.DBus"),
+                    "/org/freedesktop/DBus",
+                    "org.freedesktop.DBus",
+                    "RequestName",
+                    Some(&(DESTINATION, 0u32).to_variant()),
+                    None,
+                    gio::DBusCallFlags::NONE,
+                    -1,
+                    gio::Cancellable::NONE,
+                )
+                .unwrap();
+                ready_tx.send(()).unwrap();
+                let main_loop = glib::MainLoop::new(Some(&ctx), false);
+                let emitter = conn.clone();
+                glib::timeout_add_local_once(std::time::Duration::from_millis(200), move || {
+                    let args = (NAMESPACE, KEY, 2u32.to_variant()).to_variant();
+                    emitter.emit_signal(None, PATH, INTERFACE, "SettingChanged", Some(&args)).unwrap();
+                });
+                main_loop.run();
+            })
+            .unwrap();
+        });
+        ready_rx
+    }
+
+    // gio 0.18 has no safe binding for GTestDBus.
+    struct TestBus(*mut gio::ffi::GTestDBus);
+
+    impl TestBus {
+        fn up() -> Self {
+            // SAFETY: the bus is owned by this wrapper and released in Drop.
+            unsafe {
+                let bus = gio::ffi::g_test_dbus_new(gio::ffi::G_TEST_DBUS_NONE);
+                gio::ffi::g_test_dbus_up(bus);
+                Self(bus)
+            }
+        }
+
+        fn address(&self) -> String {
+            // SAFETY: the address is owned by the running bus and copied here.
+            unsafe { std::ffi::CStr::from_ptr(gio::ffi::g_test_dbus_get_bus_address(self.0)).to_string_lossy().into_owned() }
+        }
+    }
+
+    impl Drop for TestBus {
+        fn drop(&mut self) {
+            // SAFETY: self.0 came from g_test_dbus_new and is dropped once.
+            unsafe {
+                gio::ffi::g_test_dbus_down(self.0);
+                glib::gobject_ffi::g_object_unref(self.0 as *mut _);
+            }
+        }
+    }
+
+    #[test]
+    fn reads_and_watches_stand_in_portal() {
+        let bus = TestBus::up();
+        let address = bus.address();
+        let ctx = glib::MainContext::default();
+        let conn = connect(&address);
+        let seen = Rc::new(Cell::new(None));
+        let _sub = watch_color_scheme(&conn, {
+            let seen = seen.clone();
+            move |s| seen.set(Some(s))
+        });
+
+        spawn_portal(address, 1).recv().unwrap();
+        assert_eq!(read_color_scheme(&conn).unwrap(), ColorScheme::PreferDark);
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while seen.get().is_none() && std::time::Instant::now() < deadline {
+            ctx.iteration(false);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(seen.get(), Some(ColorScheme::PreferLight));
+    }
+}
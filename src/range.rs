@@ -0,0 +1,209 @@
+use crate::state::AyahRef;
+use crate::surah_index::{ayah_count, juz_of, juz_start};
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// An inclusive run of ayat in mushaf order, possibly spanning several surahs.
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct AyahRange {
+    pub start: AyahRef,
+    pub end: AyahRef,
+}
+
+/// The ayah after `r` in mushaf order.
+pub fn next_ayah(r: &AyahRef) -> Option<AyahRef> {
+    if r.ayah_index < ayah_count(r.surah_id)? {
+        return Some(AyahRef { surah_id: r.surah_id, ayah_index: r.ayah_index + 1 });
+    }
+    ayah_count(r.surah_id + 1).map(|_| AyahRef { surah_id: r.surah_id + 1, ayah_index: 1 })
+}
+
+/// The ayah before `r` in mushaf order.
+pub fn previous_ayah(r: &AyahRef) -> Option<AyahRef> {
+    if r.ayah_index > 1 {
+        return Some(AyahRef { surah_id: r.surah_id, ayah_index: r.ayah_index - 1 });
+    }
+    let surah_id = r.surah_id.checked_sub(1)?;
+    Some(AyahRef { surah_id, ayah_index: ayah_count(surah_id)? })
+}
+
+fn exists(r: &AyahRef) -> bool {
+    ayah_count(r.surah_id).is_some_and(|n| (1..=n).contains(&r.ayah_index))
+}
+
+impl AyahRange {
+    pub fn new(start: AyahRef, end: AyahRef) -> Result<Self> {
+        for r in [&start, &end] {
+            if !exists(r) {
+                bail!("{}:{} is not an ayah", r.surah_id, r.ayah_index);
+            }
+        }
+        if end < start {
+            bail!("range {}:{}-{}:{} ends before it starts", start.surah_id, start.ayah_index, end.surah_id, end.ayah_index);
+        }
+        Ok(Self { start, end })
+    }
+
+    pub fn single(at: AyahRef) -> Self {
+        Self { start: at.clone(), end: at }
+    }
+
+    pub fn surah(surah_id: u16) -> Option<Self> {
+        let count = ayah_count(surah_id)?;
+        Some(Self { start: AyahRef { surah_id, ayah_index: 1 }, end: AyahRef { surah_id, ayah_index: count } })
+    }
+
+    pub fn juz(juz: u8) -> Option<Self> {
+        let start = juz_start(juz)?;
+        let end = match juz_start(juz + 1) {
+            Some(next) => previous_ayah(&next)?,
+            None => Self::surah(114)?.end,
+        };
+        Some(Self { start, end })
+    }
+
+    pub fn contains(&self, r: &AyahRef) -> bool {
+        &self.start <= r && r <= &self.end
+    }
+
+    /// Number of ayat in the range.
+    pub fn len(&self) -> usize {
+        if self.is_empty() {
+            return 0;
+        }
+        if self.start.surah_id == self.end.surah_id {
+            return (self.end.ayah_index - self.start.ayah_index) as usize + 1;
+        }
+        let head = ayah_count(self.start.surah_id).unwrap_or(0).saturating_sub(self.start.ayah_index) as usize + 1;
+        let middle: usize = (self.start.surah_id + 1..self.end.surah_id).filter_map(ayah_count).map(usize::from).sum();
+        head + middle + self.end.ayah_index as usize
+    }
+
+    /// Only true for a hand-built range whose end precedes its start.
+    pub fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = AyahRef> + '_ {
+        std::iter::successors((!self.is_empty()).then(|| self.start.clone()), next_ayah).take_while(|r| r <= &self.end)
+    }
+
+    /// Splits the range wherever `key` changes between neighbouring ayat.
+    pub fn chunks_by<K: PartialEq>(&self, key: impl Fn(&AyahRef) -> K) -> Vec<AyahRange> {
+        let mut out: Vec<(K, AyahRange)> = Vec::new();
+        for r in self.iter() {
+            let k = key(&r);
+            match out.last_mut() {
+                Some((last, range)) if *last == k => range.end = r,
+                _ => out.push((k, AyahRange::single(r))),
+            }
+        }
+        out.into_iter().map(|(_, r)| r).collect()
+    }
+
+    pub fn surah_chunks(&self) -> Vec<AyahRange> {
+        self.chunks_by(|r| r.surah_id)
+    }
+
+    pub fn juz_chunks(&self) -> Vec<AyahRange> {
+        self.chunks_by(juz_of)
+    }
+
+    /// Splits at the page breaks of the imported mushaf layout. Without a
+    /// layout the range stays whole.
+    #[cfg(feature = "sqlite")]
+    pub fn page_chunks(&self, conn: &rusqlite::Connection) -> Vec<AyahRange> {
+        self.chunks_by(|r| crate::db::get_page_for_ayah(conn, r.surah_id, r.ayah_index).ok().flatten())
+    }
+}
+
+impl fmt::Display for AyahRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (s, e) = (&self.start, &self.end);
+        if s == e {
+            write!(f, "{}:{}", s.surah_id, s.ayah_index)
+        } else if s.surah_id == e.surah_id {
+            write!(f, "{}:{}-{}", s.surah_id, s.ayah_index, e.ayah_index)
+        } else {
+            write!(f, "{}:{}-{}:{}", s.surah_id, s.ayah_index, e.surah_id, e.ayah_index)
+        }
+    }
+}
+
+impl std::str::FromStr for AyahRange {
+    type Err = anyhow::Error;
+
+    /// Accepts anything the reference parser does.
+    fn from_str(s: &str) -> Result<Self> {
+        let r = crate::reference::parse(s)?;
+        r.range().ok_or_else(|| anyhow!("{} needs an imported mushaf layout", r))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: u16, a: u16) -> AyahRef {
+        AyahRef { surah_id: s, ayah_index: a }
+    }
+
+    #[test]
+    fn iterates_across_surah_boundaries() {
+        let r = AyahRange::new(at(1, 6), at(2, 2)).unwrap();
+        assert_eq!(r.iter().collect::<Vec<_>>(), vec![at(1, 6), at(1, 7), at(2, 1), at(2, 2)]);
+        assert_eq!(r.len(), 4);
+        assert!(r.contains(&at(1, 7)) && !r.contains(&at(2, 3)) && !r.contains(&at(1, 5)));
+        assert_eq!(r.to_string(), "1:6-2:2");
+        assert_eq!(AyahRange::new(at(2, 1), at(4, 1)).unwrap().len(), 286 + 200 + 1);
+        assert_eq!(AyahRange::new(at(1, 1), at(114, 6)).unwrap().len(), 6236);
+        assert_eq!(AyahRange::new(at(114, 6), at(114, 6)).unwrap().iter().count(), 1);
+        assert!(AyahRange::new(at(2, 3), at(2, 1)).is_err());
+        assert!(AyahRange::new(at(1, 8), at(2, 1)).is_err());
+    }
+
+    #[test]
+    fn splits_into_juz_and_surah_chunks() {
+        let juz30 = AyahRange::juz(30).unwrap();
+        assert_eq!(juz30, AyahRange::new(at(78, 1), at(114, 6)).unwrap());
+        assert_eq!(AyahRange::juz(1).unwrap().end, at(2, 141));
+        assert_eq!(juz30.surah_chunks().len(), 37);
+        let r = AyahRange::new(at(2, 140), at(2, 255)).unwrap();
+        let chunks: Vec<String> = r.juz_chunks().iter().map(|c| c.to_string()).collect();
+        assert_eq!(chunks, vec!["2:140-141", "2:142-252", "2:253-255"]);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn splits_into_page_chunks() {
+        use crate::mushaf::{LineKind, LineSpec, WordRef};
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::db::init_schema(&conn).unwrap();
+        let r = AyahRange::new(at(1, 6), at(2, 6)).unwrap();
+        assert_eq!(r.page_chunks(&conn), vec![r.clone()]);
+        let line = |page, start: AyahRef, end: AyahRef| LineSpec {
+            page,
+            line: 1,
+            kind: LineKind::Ayah,
+            surah_id: start.surah_id,
+            start: WordRef { ayah: start, word: 1 },
+            end: WordRef { ayah: end, word: 1 },
+        };
+        crate::db::upsert_mushaf_line(&conn, &line(1, at(1, 1), at(1, 7))).unwrap();
+        crate::db::upsert_mushaf_line(&conn, &line(2, at(2, 1), at(2, 5))).unwrap();
+        crate::db::upsert_mushaf_line(&conn, &line(3, at(2, 6), at(2, 16))).unwrap();
+        let chunks: Vec<String> = r.page_chunks(&conn).iter().map(|c| c.to_string()).collect();
+        assert_eq!(chunks, vec!["1:6-7", "2:1-5", "2:6"]);
+    }
+
+    #[test]
+    fn round_trips_through_serde_and_text() {
+        let r: AyahRange = "2:255-257".parse().unwrap();
+        let json = serde_json::to_string(&r).unwrap();
+        assert_eq!(serde_json::from_str::<AyahRange>(&json).unwrap(), r);
+        assert_eq!("Al-Kahf".parse::<AyahRange>().unwrap(), AyahRange::surah(18).unwrap());
+        assert_eq!("juz 30".parse::<AyahRange>().unwrap().start, at(78, 1));
+    }
+}
@@ -0,0 +1,312 @@
+use crate::range::AyahRange;
+use crate::state::{AyahRef, Surah};
+use crate::surah_index::default_surahs;
+use anyhow::{anyhow, bail, Result};
+use std::fmt;
+
+// Verse references as people type them: "2:255", "2:255-257", "Al-Baqarah
+// 255", "البقرة ٢٥٥", "baqara 255", "juz 30", "page 50". Everything is checked
+// against the surah table before it is returned.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reference {
+    Ayah(AyahRef),
+    Range(AyahRange),
+    Surah(u16),
+    Juz(u8),
+    Page(u16),
+}
+
+impl Reference {
+    /// The ayah to open for this reference. Pages need an imported mushaf
+    /// layout.
+    pub fn first_ayah(&self) -> Option<AyahRef> {
+        match self {
+            Reference::Ayah(r) => Some(r.clone()),
+            Reference::Range(r) => Some(r.start.clone()),
+            Reference::Surah(s) => Some(AyahRef { surah_id: *s, ayah_index: 1 }),
+            Reference::Juz(j) => crate::surah_index::juz_start(*j),
+            Reference::Page(p) => crate::data::load_mushaf_page(*p).ok()?.first_ayah(),
+        }
+    }
+
+    /// Every ayah the reference covers. A page includes the ayat that start
+    /// or end on it.
+    pub fn range(&self) -> Option<AyahRange> {
+        match self {
+            Reference::Ayah(r) => Some(AyahRange::single(r.clone())),
+            Reference::Range(r) => Some(r.clone()),
+            Reference::Surah(s) => AyahRange::surah(*s),
+            Reference::Juz(j) => AyahRange::juz(*j),
+            Reference::Page(p) => {
+                let page = crate::data::load_mushaf_page(*p).ok()?;
+                AyahRange::new(page.first_ayah()?, page.last_ayah()?).ok()
+            }
+        }
+    }
+}
+
+impl fmt::Display for Reference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reference::Ayah(r) => write!(f, "{}:{}", r.surah_id, r.ayah_index),
+            Reference::Range(r) => fmt::Display::fmt(r, f),
+            Reference::Surah(s) => write!(f, "Surah {}", s),
+            Reference::Juz(j) => write!(f, "Juz {}", j),
+            Reference::Page(p) => write!(f, "Page {}", p),
+        }
+    }
+}
+
+const JUZ_WORDS: [&str; 6] = ["juz'", "juz", "juzʼ", "para", "الجزء", "جزء"];
+const PAGE_WORDS: [&str; 5] = ["page", "pg", "p.", "الصفحة", "صفحة"];
+const SURAH_WORDS: [&str; 5] = ["surah", "surat", "sura", "سورة", "سوره"];
+
+pub fn parse(input: &str) -> Result<Reference> {
+    let text: String = input.trim().chars().map(ascii_digit).collect();
+    let lower = text.to_lowercase();
+    if lower.is_empty() {
+        bail!("empty reference");
+    }
+    if let Some(n) = keyword_number(&lower, &JUZ_WORDS) {
+        return match n {
+            1..=30 => Ok(Reference::Juz(n as u8)),
+            _ => Err(anyhow!("there is no juz {}; juz run from 1 to 30", n)),
+        };
+    }
+    if let Some(n) = keyword_number(&lower, &PAGE_WORDS) {
+        return match n {
+            1..=crate::mushaf::PAGE_COUNT => Ok(Reference::Page(n)),
+            _ => Err(anyhow!("there is no page {}; the mushaf has {} pages", n, crate::mushaf::PAGE_COUNT)),
+        };
+    }
+    let mut rest = lower.as_str();
+    for w in SURAH_WORDS {
+        if let Some(r) = rest.strip_prefix(w) {
+            rest = r.trim_start();
+            break;
+        }
+    }
+    let surahs = default_surahs();
+    let split = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+    let (name, numbers) = rest.split_at(split);
+    let name = name.trim().trim_end_matches([':', ',', '.', '#']).trim();
+    if name.is_empty() {
+        return numeric(numbers, &surahs);
+    }
+    let surah = find_surah(name, &surahs)?;
+    match numbers.trim() {
+        "" => Ok(Reference::Surah(surah)),
+        n => numeric(&format!("{}:{}", surah, n), &surahs),
+    }
+}
+
+/// Maps Arabic-Indic and Persian digits to ASCII.
+fn ascii_digit(c: char) -> char {
+    match c {
+        '\u{0660}'..='\u{0669}' => char::from(b'0' + (c as u32 - 0x0660) as u8),
+        '\u{06F0}'..='\u{06F9}' => char::from(b'0' + (c as u32 - 0x06F0) as u8),
+        '–' | '—' | '~' => '-',
+        _ => c,
+    }
+}
+
+fn keyword_number(s: &str, words: &[&str]) -> Option<u16> {
+    let rest = words.iter().find_map(|w| s.strip_prefix(w))?;
+    rest.trim_start_matches([' ', '.', ':', '#']).trim().parse().ok()
+}
+
+// "S", "S:A", "S.A", "S A", "S:A-B" or "S:A-T:B".
+fn numeric(s: &str, surahs: &[Surah]) -> Result<Reference> {
+    let s = s.trim();
+    let (left, right) = match s.split_once('-') {
+        Some((l, r)) => (l.trim(), Some(r.trim())),
+        None => (s, None),
+    };
+    let pair = |p: &str| -> Result<(u16, Option<u16>)> {
+        let mut parts = p.split([':', '.', ' ', ',']).filter(|x| !x.is_empty());
+        let a = parts.next().ok_or_else(|| anyhow!("missing number in {:?}", s))?;
+        let a = a.parse::<u16>().map_err(|_| anyhow!("not a reference: {:?}", s))?;
+        let b = parts.next().map(|b| b.parse::<u16>().map_err(|_| anyhow!("not a reference: {:?}", s))).transpose()?;
+        if parts.next().is_some() {
+            bail!("not a reference: {:?}", s);
+        }
+        Ok((a, b))
+    };
+    let start = match pair(left)? {
+        (surah, None) if right.is_none() => {
+            check(surahs, surah, None)?;
+            return Ok(Reference::Surah(surah));
+        }
+        (_, None) => bail!("a range needs a starting ayah, as in 2:255-257"),
+        (surah, Some(ayah)) => check(surahs, surah, Some(ayah))?,
+    };
+    let Some(right) = right else { return Ok(Reference::Ayah(start)) };
+    let end = match pair(right)? {
+        (ayah, None) => check(surahs, start.surah_id, Some(ayah))?,
+        (surah, Some(ayah)) => check(surahs, surah, Some(ayah))?,
+    };
+    if end < start {
+        bail!("range {} ends before it starts", s);
+    }
+    Ok(if end == start { Reference::Ayah(start) } else { Reference::Range(AyahRange::new(start, end)?) })
+}
+
+fn check(surahs: &[Surah], surah: u16, ayah: Option<u16>) -> Result<AyahRef> {
+    let s = surahs
+        .iter()
+        .find(|x| x.id == surah)
+        .ok_or_else(|| anyhow!("there is no surah {}; surahs run from 1 to 114", surah))?;
+    let ayah = ayah.unwrap_or(1);
+    if ayah == 0 || ayah > s.ayah_count {
+        bail!("{} ({}) has {} ayat, so there is no ayah {}", s.name_en, s.id, s.ayah_count, ayah);
+    }
+    Ok(AyahRef { surah_id: surah, ayah_index: ayah })
+}
+
+fn is_arabic(s: &str) -> bool {
+    s.chars().any(|c| ('\u{0600}'..='\u{06FF}').contains(&c))
+}
+
+// Spelling-insensitive forms of a name: as written and without its article.
+fn name_keys(s: &str) -> [String; 2] {
+    if is_arabic(s) {
+        let key: String = s
+            .chars()
+            .filter_map(|c| match c {
+                '\u{064B}'..='\u{065F}' | '\u{0670}' | '\u{0640}' => None,
+                'أ' | 'إ' | 'آ' | 'ٱ' => Some('ا'),
+                'ة' => Some('ه'),
+                'ى' => Some('ي'),
+                c if c.is_whitespace() => None,
+                c => Some(c),
+            })
+            .collect();
+        let bare = key.strip_prefix("ال").filter(|b| b.chars().count() > 1).unwrap_or(&key).to_string();
+        return [key, bare];
+    }
+    let mut key: String = s.chars().filter(|c| c.is_ascii_alphabetic()).map(|c| c.to_ascii_lowercase()).collect();
+    for (from, to) in [("ee", "i"), ("ii", "i"), ("oo", "u"), ("uu", "u"), ("ou", "u"), ("aa", "a")] {
+        key = key.replace(from, to);
+    }
+    if key.len() > 3 && key.ends_with('h') {
+        key.pop();
+    }
+    let bare = ["ash", "adh", "ath", "al", "an", "ar", "as", "at", "ad", "az"]
+        .iter()
+        .find_map(|a| key.strip_prefix(a).filter(|b| b.len() > 2))
+        .unwrap_or(&key)
+        .to_string();
+    [key, bare]
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = (prev + usize::from(ca != *cb)).min(row[j] + 1).min(cur + 1);
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+/// Resolves a surah name, exactly, by unique prefix, or by the closest
+/// spelling within a small edit distance.
+pub fn find_surah(name: &str, surahs: &[Surah]) -> Result<u16> {
+    let input = name_keys(name);
+    let arabic = is_arabic(name);
+    let candidates: Vec<(u16, [String; 2])> = surahs
+        .iter()
+        .map(|s| (s.id, name_keys(if arabic { &s.name_ar } else { &s.name_en })))
+        .collect();
+    let pick = |ids: Vec<u16>| -> Option<Result<u16>> {
+        match ids.as_slice() {
+            [] => None,
+            [id] => Some(Ok(*id)),
+            _ => Some(Err(anyhow!("{:?} could be {}", name, describe(&ids, surahs)))),
+        }
+    };
+    let exact: Vec<u16> = candidates.iter().filter(|(_, keys)| keys.iter().any(|k| input.contains(k))).map(|(id, _)| *id).collect();
+    if let Some(r) = pick(exact) {
+        return r;
+    }
+    if input[1].chars().count() >= 3 {
+        let prefixed: Vec<u16> = candidates
+            .iter()
+            .filter(|(_, keys)| keys.iter().any(|k| input.iter().any(|i| k.starts_with(i.as_str()))))
+            .map(|(id, _)| *id)
+            .collect();
+        if let Some(r) = pick(prefixed) {
+            return r;
+        }
+    }
+    let limit = (input[1].chars().count() / 4).max(1);
+    let scored: Vec<(u16, usize)> = candidates
+        .iter()
+        .map(|(id, keys)| (*id, keys.iter().flat_map(|k| input.iter().map(move |i| edit_distance(i, k))).min().unwrap_or(usize::MAX)))
+        .filter(|(_, d)| *d <= limit)
+        .collect();
+    let best = scored.iter().map(|(_, d)| *d).min();
+    let closest = scored.iter().filter(|(_, d)| Some(*d) == best).map(|(id, _)| *id).collect();
+    pick(closest).unwrap_or_else(|| Err(anyhow!("no surah called {:?}", name)))
+}
+
+fn describe(ids: &[u16], surahs: &[Surah]) -> String {
+    ids.iter()
+        .filter_map(|id| surahs.iter().find(|s| s.id == *id))
+        .map(|s| format!("{} ({})", s.name_en, s.id))
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: u16, a: u16) -> AyahRef {
+        AyahRef { surah_id: s, ayah_index: a }
+    }
+
+    #[test]
+    fn parses_numeric_and_keyword_forms() {
+        assert_eq!(parse("2:255").unwrap(), Reference::Ayah(at(2, 255)));
+        assert_eq!(parse(" 2.255 ").unwrap(), Reference::Ayah(at(2, 255)));
+        assert_eq!(parse("2:255-257").unwrap(), Reference::Range(AyahRange::new(at(2, 255), at(2, 257)).unwrap()));
+        assert_eq!(parse("2:285 – 3:2").unwrap().range().unwrap().len(), 4);
+        assert_eq!(parse("18").unwrap(), Reference::Surah(18));
+        assert_eq!(parse("Juz 30").unwrap(), Reference::Juz(30));
+        assert_eq!(parse("juz' 1").unwrap().first_ayah(), Some(at(1, 1)));
+        assert_eq!(parse("page 50").unwrap(), Reference::Page(50));
+        assert_eq!(parse("٢:٢٥٥").unwrap(), Reference::Ayah(at(2, 255)));
+        assert_eq!(parse("2:255-257").unwrap().to_string(), "2:255-257");
+    }
+
+    #[test]
+    fn resolves_surah_names() {
+        assert_eq!(parse("Al-Baqarah 255").unwrap(), Reference::Ayah(at(2, 255)));
+        assert_eq!(parse("البقرة ٢٥٥").unwrap(), Reference::Ayah(at(2, 255)));
+        assert_eq!(parse("سورة الكهف").unwrap(), Reference::Surah(18));
+        assert_eq!(parse("baqara 255").unwrap(), Reference::Ayah(at(2, 255)));
+        assert_eq!(parse("surah bakara 1-5").unwrap(), Reference::Range(AyahRange::new(at(2, 1), at(2, 5)).unwrap()));
+        assert_eq!(parse("yaseen").unwrap(), Reference::Surah(36));
+        assert_eq!(parse("an'am 1").unwrap(), Reference::Ayah(at(6, 1)));
+        assert_eq!(parse("An-Nas").unwrap(), Reference::Surah(114));
+        assert_eq!(parse("kahf: 10").unwrap(), Reference::Ayah(at(18, 10)));
+    }
+
+    #[test]
+    fn rejects_out_of_range_references() {
+        assert!(parse("115:1").is_err());
+        assert!(parse("1:8").unwrap_err().to_string().contains("has 7 ayat"));
+        assert!(parse("2:257-255").is_err());
+        assert!(parse("juz 31").is_err());
+        assert!(parse("page 605").is_err());
+        assert!(parse("zzzzqqq 1").is_err());
+        assert!(parse("").is_err());
+    }
+}
@@ -1,7 +1,12 @@
 use std::collections::HashMap;
+use crate::card::CardOptions;
+use crate::citation::CopySettings;
 use crate::surah_index::default_surahs;
+use crate::theme::{self, Theme};
+use crate::transliteration::Scheme;
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+/// Ordered by surah, then ayah, which is mushaf order.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
 pub struct AyahRef {
     pub surah_id: u16,
     pub ayah_index: u16,
@@ -11,6 +16,103 @@ impl Default for AyahRef {
     fn default() -> Self { Self { surah_id: 1, ayah_index: 1 } }
 }
 
+/// How several translations of one ayah are arranged.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TranslationLayout {
+    #[default]
+    Stacked,
+    Columns,
+}
+
+/// Font families (`None` uses the default Quranic font or the theme font)
+/// and point sizes for Arabic and translation text.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FontSettings {
+    pub arabic_family: Option<String>,
+    pub arabic_size: f64,
+    pub translation_family: Option<String>,
+    pub translation_size: f64,
+}
+
+impl FontSettings {
+    pub const DEFAULT_ARABIC_SIZE: f64 = 20.0;
+    pub const DEFAULT_TRANSLATION_SIZE: f64 = 12.0;
+    const MIN_SIZE: f64 = 6.0;
+    const MAX_SIZE: f64 = 96.0;
+
+    /// Scales both sizes by 10% per step, keeping their ratio.
+    pub fn zoom(&mut self, steps: i32) {
+        let factor = 1.1f64.powi(steps);
+        let scale = |size: f64| ((size * factor * 2.0).round() / 2.0).clamp(Self::MIN_SIZE, Self::MAX_SIZE);
+        self.arabic_size = scale(self.arabic_size);
+        self.translation_size = scale(self.translation_size);
+    }
+
+    pub fn reset_sizes(&mut self) {
+        self.arabic_size = Self::DEFAULT_ARABIC_SIZE;
+        self.translation_size = Self::DEFAULT_TRANSLATION_SIZE;
+    }
+}
+
+impl Default for FontSettings {
+    fn default() -> Self {
+        Self {
+            arabic_family: None,
+            arabic_size: Self::DEFAULT_ARABIC_SIZE,
+            translation_family: None,
+            translation_size: Self::DEFAULT_TRANSLATION_SIZE,
+        }
+    }
+}
+
+/// Positions left by jumps (surah changes, bookmarks, search, "Go to"),
+/// plus the last ayah read in each recently visited surah.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct History {
+    pub back: Vec<AyahRef>,
+    pub forward: Vec<AyahRef>,
+    /// Most recent first, one entry per surah.
+    pub recent: Vec<AyahRef>,
+}
+
+impl History {
+    pub const MAX_ENTRIES: usize = 100;
+    pub const MAX_RECENT: usize = 12;
+
+    /// Records leaving `from` for somewhere new, which drops the forward stack.
+    pub fn push(&mut self, from: AyahRef) {
+        if self.back.last() != Some(&from) {
+            self.back.push(from);
+        }
+        if self.back.len() > Self::MAX_ENTRIES {
+            self.back.remove(0);
+        }
+        self.forward.clear();
+    }
+
+    pub fn back(&mut self, current: AyahRef) -> Option<AyahRef> {
+        let to = self.back.pop()?;
+        self.forward.push(current);
+        Some(to)
+    }
+
+    pub fn forward(&mut self, current: AyahRef) -> Option<AyahRef> {
+        let to = self.forward.pop()?;
+        self.back.push(current);
+        Some(to)
+    }
+
+    /// Notes `at` as the latest position read in its surah.
+    pub fn read(&mut self, at: &AyahRef) {
+        self.recent.retain(|r| r.surah_id != at.surah_id);
+        self.recent.insert(0, at.clone());
+        self.recent.truncate(Self::MAX_RECENT);
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Surah {
     pub id: u16,
@@ -24,12 +126,29 @@ pub struct AppState {
     pub surahs: Vec<Surah>,
     pub current: AyahRef,
     pub translation_lang: Option<String>,
+    pub compare_langs: Vec<String>,
+    pub translation_layout: TranslationLayout,
+    pub transliteration_lang: Option<String>,
+    pub show_transliteration: bool,
+    pub transliteration_scheme: Scheme,
     pub translations: HashMap<(u16, u16, String), String>,
     pub current_ayat: Vec<String>,
     pub search_query: String,
     pub search_results: Vec<u16>,
     pub bookmarks: Vec<AyahRef>,
     pub prefer_dark: bool,
+    pub follow_system_scheme: bool,
+    pub tafsir_id: Option<i64>,
+    pub show_tafsir: bool,
+    pub show_tajweed: bool,
+    pub focus_mode: bool,
+    pub mushaf_mode: bool,
+    pub fonts: FontSettings,
+    pub theme: Theme,
+    pub opacity: f64,
+    pub history: History,
+    pub copy: CopySettings,
+    pub card: CardOptions,
 }
 
 impl AppState {
@@ -39,12 +158,51 @@ impl AppState {
             surahs,
             current: AyahRef { surah_id: 1, ayah_index: 1 },
             translation_lang: None,
+            compare_langs: Vec::new(),
+            translation_layout: TranslationLayout::default(),
+            transliteration_lang: None,
+            show_transliteration: false,
+            transliteration_scheme: Scheme::default(),
             translations: HashMap::new(),
             current_ayat: Vec::new(),
             search_query: String::new(),
             search_results: Vec::new(),
             bookmarks: Vec::new(),
             prefer_dark: false,
+            follow_system_scheme: false,
+            tafsir_id: None,
+            show_tafsir: false,
+            show_tajweed: false,
+            focus_mode: false,
+            mushaf_mode: false,
+            fonts: FontSettings::default(),
+            theme: Theme::default(),
+            opacity: theme::DEFAULT_OPACITY,
+            history: History::default(),
+            copy: CopySettings::default(),
+            card: CardOptions::default(),
+        }
+    }
+
+    /// Translations to show, in order: the primary one followed by the
+    /// comparison list. Empty while translations are hidden.
+    pub fn translation_langs(&self) -> Vec<String> {
+        let Some(primary) = &self.translation_lang else { return Vec::new() };
+        let mut out = vec![primary.clone()];
+        for l in &self.compare_langs {
+            if !out.contains(l) {
+                out.push(l.clone());
+            }
+        }
+        out
+    }
+
+    /// Moves `lang` one place earlier in the comparison list.
+    pub fn raise_compare_lang(&mut self, lang: &str) {
+        if let Some(i) = self.compare_langs.iter().position(|l| l == lang) {
+            if i > 0 {
+                self.compare_langs.swap(i - 1, i);
+            }
         }
     }
 
@@ -85,3 +243,61 @@ impl AppState {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translation_langs_follow_primary_then_comparison_order() {
+        let mut st = AppState::new();
+        st.compare_langs = vec!["ur".into(), "en".into(), "id".into()];
+        assert!(st.translation_langs().is_empty());
+        st.translation_lang = Some("en".into());
+        assert_eq!(st.translation_langs(), vec!["en", "ur", "id"]);
+        st.raise_compare_lang("id");
+        st.raise_compare_lang("id");
+        st.raise_compare_lang("id");
+        assert_eq!(st.compare_langs, vec!["id", "ur", "en"]);
+        assert!(crate::data::is_rtl_language("ur") && crate::data::is_rtl_language("fa-IR"));
+        assert!(!crate::data::is_rtl_language("en"));
+    }
+
+    #[test]
+    fn history_steps_back_and_forward() {
+        let at = |s, a| AyahRef { surah_id: s, ayah_index: a };
+        let mut h = History::default();
+        h.push(at(1, 1));
+        h.push(at(2, 255));
+        h.push(at(2, 255));
+        assert_eq!(h.back, vec![at(1, 1), at(2, 255)]);
+        assert_eq!(h.back(at(18, 10)), Some(at(2, 255)));
+        assert_eq!(h.back(at(2, 255)), Some(at(1, 1)));
+        assert_eq!(h.back(at(1, 1)), None);
+        assert_eq!(h.forward(at(1, 1)), Some(at(2, 255)));
+        h.push(at(2, 255));
+        assert!(h.forward.is_empty());
+        for i in 0..150 {
+            h.push(at(3, i));
+        }
+        assert_eq!(h.back.len(), History::MAX_ENTRIES);
+
+        h.read(&at(2, 5));
+        h.read(&at(18, 1));
+        h.read(&at(2, 9));
+        assert_eq!(h.recent, vec![at(2, 9), at(18, 1)]);
+    }
+
+    #[test]
+    fn zoom_scales_both_sizes_within_bounds() {
+        let mut f = FontSettings::default();
+        f.zoom(1);
+        assert_eq!((f.arabic_size, f.translation_size), (22.0, 13.0));
+        f.zoom(-100);
+        assert_eq!((f.arabic_size, f.translation_size), (6.0, 6.0));
+        f.zoom(100);
+        assert_eq!(f.arabic_size, 96.0);
+        f.reset_sizes();
+        assert_eq!(f, FontSettings::default());
+    }
+}
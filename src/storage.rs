@@ -1,16 +1,144 @@
-use crate::state::AyahRef;
+use crate::card::CardOptions;
+use crate::citation::CopySettings;
+use crate::state::{AyahRef, FontSettings, History, TranslationLayout};
+use crate::theme::Theme;
+use crate::transliteration::Scheme;
 use anyhow::{Context, Result};
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+/// A saved ayah. Older state files stored bare `{surah_id, ayah_index}`
+/// objects, which still deserialize with the remaining fields empty.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    #[serde(flatten)]
+    pub ayah: AyahRef,
+    /// Unix seconds.
+    #[serde(default)]
+    pub created: u64,
+    #[serde(default)]
+    pub note: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub collection: Option<String>,
+}
+
+impl Bookmark {
+    pub fn new(ayah: AyahRef) -> Self {
+        let created = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self { ayah, created, note: String::new(), tags: Vec::new(), collection: None }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BookmarkFilter {
+    pub text: String,
+    pub tag: Option<String>,
+    pub collection: Option<String>,
+}
+
+impl BookmarkFilter {
+    /// Free text matches the surah name, the `s:a` reference, the note and tags.
+    pub fn matches(&self, b: &Bookmark, surah_name: &str) -> bool {
+        if self.tag.as_ref().is_some_and(|t| !b.tags.contains(t)) {
+            return false;
+        }
+        if self.collection.is_some() && self.collection != b.collection {
+            return false;
+        }
+        let q = self.text.trim().to_lowercase();
+        if q.is_empty() {
+            return true;
+        }
+        let reference = format!("{}:{}", b.ayah.surah_id, b.ayah.ayah_index);
+        surah_name.to_lowercase().contains(&q)
+            || reference.starts_with(&q)
+            || b.note.to_lowercase().contains(&q)
+            || b.tags.iter().any(|t| t.to_lowercase().contains(&q))
+    }
+}
+
+/// Splits user input like `#dua, memorise  tafsir` into distinct tags.
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for t in input.split(|c: char| c == ',' || c.is_whitespace()) {
+        let t = t.trim().trim_start_matches('#');
+        if !t.is_empty() && !out.iter().any(|x| x == t) {
+            out.push(t.to_string());
+        }
+    }
+    out
+}
+
+/// Every tag in use, sorted.
+pub fn all_tags(bookmarks: &[Bookmark]) -> Vec<String> {
+    let mut tags: Vec<String> = bookmarks.iter().flat_map(|b| b.tags.iter().cloned()).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Reads one bookmark per line as `reference` or `reference | note`, for
+/// example `Al-Kahf 10 | read on Fridays`. Blank lines and `#` comments are
+/// skipped; lines that do not parse are returned with their line number.
+pub fn parse_bookmark_lines(text: &str) -> (Vec<Bookmark>, Vec<(usize, String)>) {
+    let mut out = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (target, note) = match line.split_once(['|', '\t']) {
+            Some((t, n)) => (t.trim(), n.trim()),
+            None => (line, ""),
+        };
+        match crate::reference::parse(target).and_then(|r| r.first_ayah().ok_or_else(|| anyhow::anyhow!("{} needs an imported mushaf layout", r))) {
+            Ok(ayah) => {
+                let mut b = Bookmark::new(ayah);
+                b.note = note.to_string();
+                out.push(b);
+            }
+            Err(e) => errors.push((i + 1, format!("{:#}", e))),
+        }
+    }
+    (out, errors)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct Persisted {
     pub last: AyahRef,
-    pub bookmarks: Vec<AyahRef>,
+    pub bookmarks: Vec<Bookmark>,
+    /// Named bookmark collections, kept even while empty.
+    pub collections: Vec<String>,
     pub translation_lang: Option<String>,
+    pub compare_langs: Vec<String>,
+    pub translation_layout: TranslationLayout,
     pub prefer_dark: bool,
+    pub follow_system_scheme: bool,
+    pub tafsir_id: Option<i64>,
+    pub show_tafsir: bool,
+    pub show_transliteration: bool,
+    pub transliteration_scheme: Scheme,
+    pub show_tajweed: bool,
+    pub focus_mode: bool,
+    pub mushaf_mode: bool,
+    pub fonts: FontSettings,
+    pub theme: Theme,
+    /// Window opacity; `None` keeps the built-in default.
+    pub opacity: Option<f64>,
+    pub history: History,
+    /// Last choices in the copy dialog.
+    pub copy: CopySettings,
+    /// Last choices in the verse card dialog.
+    pub card: CardOptions,
 }
 
 fn data_dir() -> Result<PathBuf> {
@@ -39,8 +167,59 @@ pub fn save(p: &Persisted) -> Result<()> {
 
 pub fn add_bookmark(b: AyahRef) -> Result<()> {
     let mut p = load().unwrap_or_default();
-    if !p.bookmarks.iter().any(|x| x == &b) {
-        p.bookmarks.push(b);
+    if !p.bookmarks.iter().any(|x| x.ayah == b) {
+        p.bookmarks.push(Bookmark::new(b));
     }
     save(&p)
 }
+
+/// Replaces the stored bookmarks and collections, keeping their order.
+pub fn save_bookmarks(bookmarks: &[Bookmark], collections: &[String]) -> Result<()> {
+    let mut p = load().unwrap_or_default();
+    p.bookmarks = bookmarks.to_vec();
+    p.collections = collections.to_vec();
+    save(&p)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_bare_bookmarks_from_older_state() {
+        let p: Persisted = serde_json::from_str(r#"{"bookmarks":[{"surah_id":2,"ayah_index":255}]}"#).unwrap();
+        assert_eq!(p.bookmarks[0].ayah, AyahRef { surah_id: 2, ayah_index: 255 });
+        assert!(p.bookmarks[0].tags.is_empty() && p.bookmarks[0].collection.is_none());
+    }
+
+    #[test]
+    fn filters_by_text_tag_and_collection() {
+        let mut b = Bookmark::new(AyahRef { surah_id: 2, ayah_index: 255 });
+        b.note = "Ayat al-Kursi".into();
+        b.tags = parse_tags("#protection, memorise memorise");
+        b.collection = Some("Daily".into());
+        assert_eq!(b.tags, vec!["protection", "memorise"]);
+        let f = |text: &str, tag: Option<&str>, collection: Option<&str>| BookmarkFilter {
+            text: text.into(),
+            tag: tag.map(Into::into),
+            collection: collection.map(Into::into),
+        };
+        assert!(f("", None, None).matches(&b, "Al-Baqarah"));
+        assert!(f("kursi", None, None).matches(&b, "Al-Baqarah"));
+        assert!(f("baqarah", Some("memorise"), Some("Daily")).matches(&b, "Al-Baqarah"));
+        assert!(f("2:25", None, None).matches(&b, "Al-Baqarah"));
+        assert!(!f("", Some("dua"), None).matches(&b, "Al-Baqarah"));
+        assert!(!f("", None, Some("Weekly")).matches(&b, "Al-Baqarah"));
+        assert_eq!(all_tags(&[b.clone(), b]), vec!["memorise", "protection"]);
+    }
+
+    #[test]
+    fn imports_bookmarks_from_reference_lines() {
+        let (bookmarks, errors) = parse_bookmark_lines("# reading list\n2:255\nAl-Kahf 10 | Fridays\n\njuz 30\n1:9\n");
+        let refs: Vec<(u16, u16)> = bookmarks.iter().map(|b| (b.ayah.surah_id, b.ayah.ayah_index)).collect();
+        assert_eq!(refs, vec![(2, 255), (18, 10), (78, 1)]);
+        assert_eq!(bookmarks[1].note, "Fridays");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, 6);
+    }
+}
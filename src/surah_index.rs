@@ -1,4 +1,4 @@
-use crate::state::Surah;
+use crate::state::{AyahRef, Surah};
 
 pub fn default_surahs() -> Vec<Surah> {
     let names_en = [
@@ -7,15 +7,40 @@ pub fn default_surahs() -> Vec<Surah> {
     let names_ar = [
         "الفاتحة","البقرة","آل عمران","النساء","المائدة","الأنعام","الأعراف","الأنفال","التوبة","يونس","هود","يوسف","الرعد","إبراهيم","الحجر","النحل","الإسراء","الكهف","مريم","طه","الأنبياء","الحج","المؤمنون","النور","الفرقان","الشعراء","النمل","القصص","العنكبوت","الروم","لقمان","السجدة","الأحزاب","سبإ","فاطر","يس","الصافات","ص","الزمر","غافر","فصلت","الشورى","الزخرف","الدخان","الجاثية","الأحقاف","محمد","الفتح","الحجرات","ق","الذاريات","الطور","النجم","القمر","الرحمن","الواقعة","الحديد","المجادلة","الحشر","الممتحنة","الصف","الجمعة","المنافقون","التغابن","الطلاق","التحريم","الملك","القلم","الحاقة","المعارج","نوح","الجن","المزمل","المدثر","القيامة","الإنسان","المرسلات","النبإ","النازعات","عبس","التكوير","الإنفطار","المطففين","الإنشقاق","البروج","الطارق","الأعلى","الغاشية","الفجر","البلد","الشمس","الليل","الضحى","الشرح","التين","العلق","القدر","البينة","الزلزلة","العاديات","القارعة","التكاثر","العصر","الهمزة","الفيل","قريش","الماعون","الكوثر","الكافرون","النصر","المسد","الإخلاص","الفلق","الناس"
     ];
-    let counts = [
-        7,286,200,176,120,165,206,75,129,109,123,111,43,52,99,128,111,110,98,135,112,78,118,64,77,227,93,88,69,60,34,30,73,54,45,83,182,88,75,85,54,53,89,59,37,35,38,29,18,45,60,49,62,55,78,96,29,22,24,13,14,11,11,18,12,12,30,52,52,44,28,28,20,56,40,31,50,40,46,42,29,19,36,25,22,17,19,26,30,20,15,21,11,8,8,19,5,8,8,11,11,8,3,9,5,4,7,3,6,3,5,4,5,6
-    ];
     (1..=114)
         .map(|i| Surah {
             id: i,
             name_ar: names_ar[(i - 1) as usize].to_string(),
             name_en: names_en[(i - 1) as usize].to_string(),
-            ayah_count: counts[(i - 1) as usize] as u16,
+            ayah_count: AYAH_COUNTS[(i - 1) as usize],
         })
         .collect()
 }
+
+const AYAH_COUNTS: [u16; 114] = [
+    7,286,200,176,120,165,206,75,129,109,123,111,43,52,99,128,111,110,98,135,112,78,118,64,77,227,93,88,69,60,34,30,73,54,45,83,182,88,75,85,54,53,89,59,37,35,38,29,18,45,60,49,62,55,78,96,29,22,24,13,14,11,11,18,12,12,30,52,52,44,28,28,20,56,40,31,50,40,46,42,29,19,36,25,22,17,19,26,30,20,15,21,11,8,8,19,5,8,8,11,11,8,3,9,5,4,7,3,6,3,5,4,5,6,
+];
+
+pub fn ayah_count(surah: u16) -> Option<u16> {
+    AYAH_COUNTS.get((surah as usize).checked_sub(1)?).copied()
+}
+
+// First ayah of each juz, as (surah, ayah).
+const JUZ_STARTS: [(u16, u16); 30] = [
+    (1, 1), (2, 142), (2, 253), (3, 93), (4, 24), (4, 148), (5, 82), (6, 111), (7, 88), (8, 41),
+    (9, 93), (11, 6), (12, 53), (15, 1), (17, 1), (18, 75), (21, 1), (23, 1), (25, 21), (27, 56),
+    (29, 46), (33, 31), (36, 28), (39, 32), (41, 47), (46, 1), (51, 31), (58, 1), (67, 1), (78, 1),
+];
+
+pub fn juz_start(juz: u8) -> Option<AyahRef> {
+    let (surah_id, ayah_index) = *JUZ_STARTS.get((juz as usize).checked_sub(1)?)?;
+    Some(AyahRef { surah_id, ayah_index })
+}
+
+pub fn juz_of(r: &AyahRef) -> u8 {
+    JUZ_STARTS
+        .iter()
+        .rposition(|&(s, a)| (s, a) <= (r.surah_id, r.ayah_index))
+        .map(|i| i as u8 + 1)
+        .unwrap_or(1)
+}
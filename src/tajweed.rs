@@ -0,0 +1,249 @@
+// Tajweed annotation of vowelized Uthmani text. Spans are byte ranges into
+// the analysed string, so they can be used directly as Pango attribute
+// indices.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    Ghunnah,
+    Idgham,
+    Ikhfa,
+    Iqlab,
+    Qalqalah,
+    Madd,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 6] = [Rule::Ghunnah, Rule::Idgham, Rule::Ikhfa, Rule::Iqlab, Rule::Qalqalah, Rule::Madd];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::Ghunnah => "Ghunnah",
+            Rule::Idgham => "Idgham",
+            Rule::Ikhfa => "Ikhfa",
+            Rule::Iqlab => "Iqlab",
+            Rule::Qalqalah => "Qalqalah",
+            Rule::Madd => "Madd",
+        }
+    }
+
+    /// Conventional colour-coded mushaf palette as `#rrggbb`.
+    pub fn colour(&self) -> &'static str {
+        match self {
+            Rule::Ghunnah => "#ff7e1e",
+            Rule::Idgham => "#169777",
+            Rule::Ikhfa => "#9400a8",
+            Rule::Iqlab => "#26bffd",
+            Rule::Qalqalah => "#dd0008",
+            Rule::Madd => "#d500b7",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub rule: Rule,
+}
+
+const IDGHAM: &[char] = &['ي', 'ر', 'م', 'ل', 'و', 'ن'];
+const IKHFA: &[char] = &['ت', 'ث', 'ج', 'د', 'ذ', 'ز', 'س', 'ش', 'ص', 'ض', 'ط', 'ظ', 'ف', 'ق', 'ك'];
+const QALQALAH: &[char] = &['ق', 'ط', 'ب', 'ج', 'د'];
+const HAMZA: &[char] = &['ء', 'أ', 'إ', 'ؤ', 'ئ'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Vowel {
+    A,
+    I,
+    U,
+}
+
+#[derive(Debug, Clone)]
+struct Letter {
+    ch: char,
+    start: usize,
+    end: usize,
+    word: usize,
+    vowel: Option<Vowel>,
+    tanween: bool,
+    shadda: bool,
+    sukun: bool,
+    maddah: bool,
+}
+
+impl Letter {
+    fn bare(&self) -> bool {
+        self.vowel.is_none() && !self.tanween && !self.shadda
+    }
+}
+
+fn letters(text: &str) -> Vec<Letter> {
+    let mut out: Vec<Letter> = Vec::new();
+    let mut word = 0;
+    let mut in_space = false;
+    for (i, c) in text.char_indices() {
+        if c.is_whitespace() {
+            if !in_space {
+                word += 1;
+            }
+            in_space = true;
+            continue;
+        }
+        in_space = false;
+        if c == 'ٱ' || (('\u{0621}'..='\u{064A}').contains(&c) && c != '\u{0640}') {
+            out.push(Letter { ch: c, start: i, end: i + c.len_utf8(), word, vowel: None, tanween: false, shadda: false, sukun: false, maddah: false });
+            continue;
+        }
+        let Some(l) = out.last_mut() else { continue };
+        if l.word != word {
+            continue;
+        }
+        match c {
+            '\u{064E}' => l.vowel = Some(Vowel::A),
+            '\u{0650}' => l.vowel = Some(Vowel::I),
+            '\u{064F}' => l.vowel = Some(Vowel::U),
+            '\u{064B}'..='\u{064D}' | '\u{08F0}'..='\u{08F2}' => l.tanween = true,
+            '\u{0651}' => l.shadda = true,
+            '\u{0652}' | '\u{06E1}' => l.sukun = true,
+            '\u{0653}' => l.maddah = true,
+            _ => {}
+        }
+        l.end = i + c.len_utf8();
+    }
+    out
+}
+
+fn is_madd_seat(ls: &[Letter], i: usize) -> bool {
+    let l = &ls[i];
+    if !l.bare() || i == 0 || ls[i - 1].word != l.word {
+        return false;
+    }
+    matches!(
+        (ls[i - 1].vowel, l.ch),
+        (Some(Vowel::A), 'ا' | 'ى') | (Some(Vowel::I), 'ي') | (Some(Vowel::U), 'و')
+    )
+}
+
+pub fn annotate(text: &str) -> Vec<Span> {
+    let ls = letters(text);
+    let mut spans = Vec::new();
+    for (i, l) in ls.iter().enumerate() {
+        let last = i + 1 == ls.len();
+
+        if matches!(l.ch, 'ن' | 'م') && l.shadda {
+            spans.push(Span { start: l.start, end: l.end, rule: Rule::Ghunnah });
+        }
+
+        let noon_sakinah = l.ch == 'ن' && l.bare() && !last;
+        if (noon_sakinah || l.tanween) && !last {
+            // The alef or alef maqsura carrying fathatan is silent
+            let j = (i + 1..ls.len()).find(|&j| !(matches!(ls[j].ch, 'ا' | 'ى') && ls[j].bare() && ls[j].word == l.word));
+            if let Some(j) = j {
+                let n = &ls[j];
+                if IDGHAM.contains(&n.ch) && (l.tanween || n.word != l.word) {
+                    spans.push(Span { start: l.start, end: n.end, rule: Rule::Idgham });
+                } else if n.ch == 'ب' {
+                    spans.push(Span { start: l.start, end: l.end, rule: Rule::Iqlab });
+                } else if IKHFA.contains(&n.ch) {
+                    spans.push(Span { start: l.start, end: l.end, rule: Rule::Ikhfa });
+                }
+            }
+        }
+
+        if l.ch == 'م' && l.bare() && !last {
+            let n = &ls[i + 1];
+            if n.ch == 'ب' {
+                spans.push(Span { start: l.start, end: l.end, rule: Rule::Ikhfa });
+            } else if n.ch == 'م' {
+                spans.push(Span { start: l.start, end: n.end, rule: Rule::Idgham });
+            }
+        }
+
+        if QALQALAH.contains(&l.ch) && (l.sukun || last) {
+            spans.push(Span { start: l.start, end: l.end, rule: Rule::Qalqalah });
+        }
+
+        let extended = is_madd_seat(&ls, i)
+            && ls.get(i + 1).is_some_and(|n| HAMZA.contains(&n.ch) || (n.shadda && n.word == l.word));
+        if l.maddah || l.ch == 'آ' || extended {
+            spans.push(Span { start: l.start, end: l.end, rule: Rule::Madd });
+        }
+    }
+    spans.sort_by_key(|s| (s.start, s.end));
+    spans
+}
+
+/// Annotates consecutive words of one ayah together, so that rules spanning
+/// a word boundary still apply, and returns each word's spans relative to it.
+pub fn annotate_words(words: &[&str]) -> Vec<Vec<Span>> {
+    let mut starts = Vec::with_capacity(words.len());
+    let mut text = String::new();
+    for w in words {
+        if !text.is_empty() {
+            text.push(' ');
+        }
+        starts.push(text.len());
+        text.push_str(w);
+    }
+    let mut out = vec![Vec::new(); words.len()];
+    for span in annotate(&text) {
+        for (k, (w, &at)) in words.iter().zip(&starts).enumerate() {
+            let (start, end) = (span.start.max(at), span.end.min(at + w.len()));
+            if start < end {
+                out[k].push(Span { start: start - at, end: end - at, rule: span.rule });
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str) -> Vec<(Rule, &str)> {
+        annotate(text).into_iter().map(|s| (s.rule, &text[s.start..s.end])).collect()
+    }
+
+    #[test]
+    fn fatiha() {
+        let sf = crate::data::load_surah_text_fatiha().unwrap();
+        let expected: [&[(Rule, &str)]; 7] = [
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[],
+            &[(Rule::Madd, "ا")],
+        ];
+        for (ayah, want) in sf.ayat.iter().zip(expected) {
+            assert_eq!(found(ayah), want, "{}", ayah);
+        }
+    }
+
+    #[test]
+    fn known_ayat() {
+        let cases: &[(&str, &[(Rule, &str)])] = &[
+            ("قُلْ هُوَ اللَّهُ أَحَدٌ", &[(Rule::Qalqalah, "دٌ")]),
+            ("لَمْ يَلِدْ وَلَمْ يُولَدْ", &[(Rule::Qalqalah, "دْ"), (Rule::Qalqalah, "دْ")]),
+            ("وَلَمْ يَكُن لَّهُ كُفُوًا أَحَدٌ", &[(Rule::Idgham, "ن لَّ"), (Rule::Qalqalah, "دٌ")]),
+            ("مِن شَرِّ مَا خَلَقَ", &[(Rule::Ikhfa, "ن"), (Rule::Qalqalah, "قَ")]),
+            ("إِنَّ اللَّهَ عَلِيمٌ بِذَاتِ الصُّدُورِ", &[(Rule::Ghunnah, "نَّ"), (Rule::Iqlab, "مٌ")]),
+            ("تَرْمِيهِم بِحِجَارَةٍ", &[(Rule::Ikhfa, "م")]),
+            ("إِذَا جَاءَ نَصْرُ اللَّهِ", &[(Rule::Madd, "ا")]),
+        ];
+        for (ayah, want) in cases {
+            assert_eq!(found(ayah), *want, "{}", ayah);
+        }
+    }
+
+    #[test]
+    fn annotates_words_in_context() {
+        let words = ["وَلَمْ", "يَكُن", "لَّهُۥ", "كُفُوًا", "أَحَدٌۢ"];
+        let spans = annotate_words(&words);
+        let found: Vec<Vec<(Rule, &str)>> = words.iter().zip(&spans).map(|(w, s)| s.iter().map(|s| (s.rule, &w[s.start..s.end])).collect()).collect();
+        assert_eq!(found, vec![vec![], vec![(Rule::Idgham, "ن")], vec![(Rule::Idgham, "لَّ")], vec![], vec![(Rule::Qalqalah, "دٌۢ")]]);
+        assert_eq!(annotate_words(&["خَلَقَ", "ٱلْإِنسَٰنَ"])[0], vec![]);
+    }
+}
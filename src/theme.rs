@@ -0,0 +1,115 @@
+use anyhow::{Context, Result};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+// Named colour schemes bundled as CSS in `assets/themes`. The user's own
+// `style.css` in the config directory is applied on top of whichever theme
+// is selected.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    Default,
+    Sepia,
+    HighContrast,
+    Oled,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 4] = [Theme::Default, Theme::Sepia, Theme::HighContrast, Theme::Oled];
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            Theme::Default => "default",
+            Theme::Sepia => "sepia",
+            Theme::HighContrast => "high-contrast",
+            Theme::Oled => "oled",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Theme::Default => "Default",
+            Theme::Sepia => "Sepia",
+            Theme::HighContrast => "High contrast",
+            Theme::Oled => "OLED black",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|t| t.id() == id)
+    }
+
+    /// CSS for the theme; the default theme adds nothing to the GTK theme.
+    pub fn css(&self) -> Result<String> {
+        if *self == Theme::Default {
+            return Ok(String::new());
+        }
+        let path = crate::data::assets_dir().join("themes").join(format!("{}.css", self.id()));
+        fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))
+    }
+}
+
+pub const DEFAULT_OPACITY: f64 = 0.92;
+
+/// The desktop-wide preference published by the settings portal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorScheme {
+    NoPreference,
+    PreferDark,
+    PreferLight,
+}
+
+impl ColorScheme {
+    pub fn from_portal(value: u32) -> Self {
+        match value {
+            1 => ColorScheme::PreferDark,
+            2 => ColorScheme::PreferLight,
+            _ => ColorScheme::NoPreference,
+        }
+    }
+}
+
+/// Whether to use the dark variant: the system preference when following
+/// it and one is known, otherwise the manual toggle.
+pub fn use_dark(follow_system: bool, system: Option<ColorScheme>, prefer_dark: bool) -> bool {
+    match (follow_system, system) {
+        (true, Some(ColorScheme::PreferDark)) => true,
+        (true, Some(ColorScheme::PreferLight)) => false,
+        _ => prefer_dark,
+    }
+}
+
+/// `~/.config/hyprquran/style.css` on Linux.
+pub fn user_css_path() -> Option<PathBuf> {
+    let dirs = ProjectDirs::from("org", "hyprquran", "HyprQuran")?;
+    Some(dirs.config_dir().join("style.css"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_themes_load() {
+        for theme in Theme::ALL {
+            let css = theme.css().unwrap();
+            assert_eq!(css.is_empty(), theme == Theme::Default, "{}", theme.id());
+            assert_eq!(Theme::from_id(theme.id()), Some(theme));
+        }
+    }
+
+    #[test]
+    fn follows_portal_scheme_when_known() {
+        assert_eq!(ColorScheme::from_portal(1), ColorScheme::PreferDark);
+        assert_eq!(ColorScheme::from_portal(7), ColorScheme::NoPreference);
+        assert!(use_dark(true, Some(ColorScheme::PreferDark), false));
+        assert!(!use_dark(true, Some(ColorScheme::PreferLight), true));
+        assert!(use_dark(true, Some(ColorScheme::NoPreference), true));
+        assert!(use_dark(true, None, true));
+        assert!(!use_dark(false, Some(ColorScheme::PreferDark), false));
+    }
+}
@@ -0,0 +1,339 @@
+use serde::{Deserialize, Serialize};
+
+// Rule-based romanization of vowelized Uthmani text. `Simple` follows
+// connected recitation (hamzat al-wasl elided after a vowel, sun letters
+// assimilated); `AlaLc` and `Iso233` romanize each word as written.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scheme {
+    #[default]
+    Simple,
+    AlaLc,
+    Iso233,
+}
+
+impl Scheme {
+    fn phonetic(self) -> bool {
+        self == Scheme::Simple
+    }
+
+    fn col(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Vowel {
+    A,
+    I,
+    U,
+}
+
+const SHORT: [&str; 3] = ["a", "i", "u"];
+const LONG: [[&str; 3]; 3] = [["a", "ā", "ā"], ["i", "ī", "ī"], ["u", "ū", "ū"]];
+const HAMZA: [&str; 3] = ["'", "ʼ", "ʾ"];
+
+const LETTERS: &[(char, [&str; 3])] = &[
+    ('ب', ["b", "b", "b"]),
+    ('ت', ["t", "t", "t"]),
+    ('ث', ["th", "th", "ṯ"]),
+    ('ج', ["j", "j", "ǧ"]),
+    ('ح', ["h", "ḥ", "ḥ"]),
+    ('خ', ["kh", "kh", "ḫ"]),
+    ('د', ["d", "d", "d"]),
+    ('ذ', ["dh", "dh", "ḏ"]),
+    ('ر', ["r", "r", "r"]),
+    ('ز', ["z", "z", "z"]),
+    ('س', ["s", "s", "s"]),
+    ('ش', ["sh", "sh", "š"]),
+    ('ص', ["s", "ṣ", "ṣ"]),
+    ('ض', ["d", "ḍ", "ḍ"]),
+    ('ط', ["t", "ṭ", "ṭ"]),
+    ('ظ', ["z", "ẓ", "ẓ"]),
+    ('ع', ["'", "ʻ", "ʿ"]),
+    ('غ', ["gh", "gh", "ġ"]),
+    ('ف', ["f", "f", "f"]),
+    ('ق', ["q", "q", "q"]),
+    ('ك', ["k", "k", "k"]),
+    ('ل', ["l", "l", "l"]),
+    ('م', ["m", "m", "m"]),
+    ('ن', ["n", "n", "n"]),
+    ('ه', ["h", "h", "h"]),
+    ('و', ["w", "w", "w"]),
+    ('ي', ["y", "y", "y"]),
+    ('ى', ["y", "y", "y"]),
+];
+
+#[derive(Debug, Clone, Default)]
+struct Unit {
+    ch: char,
+    vowel: Option<Vowel>,
+    tanween: Option<Vowel>,
+    shadda: bool,
+    sukun: bool,
+    dagger: bool,
+    small_waw: bool,
+    small_yeh: bool,
+    silent: bool,
+}
+
+impl Unit {
+    // A letter carrying no vowel of its own, i.e. a possible madd seat.
+    fn seat(&self) -> bool {
+        self.vowel.is_none() && self.tanween.is_none() && !self.shadda
+    }
+}
+
+pub fn transliterate(text: &str, scheme: Scheme) -> String {
+    let words: Vec<Vec<Unit>> = text.split_whitespace().map(units).filter(|u| !u.is_empty()).collect();
+    let mut out: Vec<String> = Vec::new();
+    for (i, w) in words.iter().enumerate() {
+        let after_vowel = out
+            .last()
+            .and_then(|p| p.chars().last())
+            .is_some_and(|c| "aiuāīū".contains(c));
+        let ctx = Context { initial: i == 0, after_vowel, pause: i + 1 == words.len() };
+        let r = render(w, scheme, ctx);
+        if !r.is_empty() {
+            out.push(r);
+        }
+    }
+    out.join(" ")
+}
+
+fn units(word: &str) -> Vec<Unit> {
+    let mut out: Vec<Unit> = Vec::new();
+    for c in word.chars() {
+        if c == 'ٱ' || (('\u{0621}'..='\u{064A}').contains(&c) && c != '\u{0640}') {
+            out.push(Unit { ch: c, ..Default::default() });
+            continue;
+        }
+        let Some(u) = out.last_mut() else { continue };
+        match c {
+            '\u{064E}' | '\u{0618}' => u.vowel = Some(Vowel::A),
+            '\u{0650}' | '\u{061A}' => u.vowel = Some(Vowel::I),
+            '\u{064F}' | '\u{0619}' => u.vowel = Some(Vowel::U),
+            '\u{064B}' | '\u{08F0}' => u.tanween = Some(Vowel::A),
+            '\u{064D}' | '\u{08F2}' => u.tanween = Some(Vowel::I),
+            '\u{064C}' | '\u{08F1}' => u.tanween = Some(Vowel::U),
+            '\u{0651}' => u.shadda = true,
+            '\u{0652}' | '\u{06E1}' => u.sukun = true,
+            '\u{0670}' => u.dagger = true,
+            '\u{06E5}' => u.small_waw = true,
+            '\u{06E6}' => u.small_yeh = true,
+            '\u{06DF}' | '\u{06E0}' => u.silent = true,
+            _ => {}
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Context {
+    initial: bool,
+    after_vowel: bool,
+    pause: bool,
+}
+
+fn is_wasl(u: &[Unit], i: usize) -> bool {
+    let c = &u[i];
+    if c.ch == 'ٱ' {
+        return true;
+    }
+    if c.ch != 'ا' || !c.seat() || c.dagger {
+        return false;
+    }
+    // A long ā is never word-initial nor followed by a consonant with
+    // sukun, so a bare alef in those positions (or before the article's
+    // lam) is a wasl.
+    if i == 0 {
+        return u.len() > 1;
+    }
+    match u.get(i + 1) {
+        Some(n) if n.sukun => true,
+        Some(n) => n.ch == 'ل' && n.seat(),
+        None => false,
+    }
+}
+
+fn is_article_lam(u: &[Unit], i: usize) -> bool {
+    let Some(c) = u.get(i) else { return false };
+    if c.ch != 'ل' || !c.seat() || i == 0 {
+        return false;
+    }
+    is_wasl(u, i - 1) || (i == 1 && u[0].ch == 'ل' && u[0].vowel == Some(Vowel::I))
+}
+
+fn render(u: &[Unit], scheme: Scheme, ctx: Context) -> String {
+    let col = scheme.col();
+    let mut out = String::new();
+    let mut consumed = vec![false; u.len()];
+    let mut assimilated = false;
+    for i in 0..u.len() {
+        if consumed[i] || u[i].silent {
+            continue;
+        }
+        let cur = &u[i];
+        let next = u.get(i + 1);
+        let last = (i + 1..u.len()).all(|j| u[j].silent || (matches!(u[j].ch, 'ا' | 'ى') && u[j].seat()));
+        let pausal = last && ctx.pause;
+
+        if is_wasl(u, i) {
+            let elide = i > 0 || (scheme.phonetic() && !ctx.initial && ctx.after_vowel);
+            if !elide {
+                let v = if is_article_lam(u, i + 1) || u.get(i + 1).is_some_and(|n| n.ch == 'ل' && n.shadda) {
+                    Vowel::A
+                } else if u.get(i + 2).and_then(|n| n.vowel) == Some(Vowel::U) {
+                    Vowel::U
+                } else {
+                    Vowel::I
+                };
+                out.push_str(SHORT[v as usize]);
+            }
+            continue;
+        }
+
+        if is_article_lam(u, i) {
+            match next {
+                Some(n) if n.shadda => {
+                    assimilated = true;
+                    if !scheme.phonetic() {
+                        out.push('l');
+                        if n.ch != 'ل' {
+                            out.push('-');
+                        }
+                    }
+                }
+                _ => out.push_str("l-"),
+            }
+            continue;
+        }
+
+        let hamza = if i == 0 && scheme != Scheme::Iso233 { "" } else { HAMZA[col] };
+        let cons = match cur.ch {
+            'ا' | 'ى' if cur.seat() => {
+                if cur.dagger {
+                    out.push_str(LONG[Vowel::A as usize][col]);
+                }
+                continue;
+            }
+            'آ' => {
+                out.push_str(hamza);
+                out.push_str(LONG[Vowel::A as usize][col]);
+                continue;
+            }
+            'ا' | 'أ' | 'إ' | 'ؤ' | 'ئ' | 'ء' => hamza,
+            'ة' if pausal => "h",
+            'ة' => "t",
+            c => match LETTERS.iter().find(|(l, _)| *l == c) {
+                Some((_, r)) => r[col],
+                None => continue,
+            },
+        };
+        if cur.shadda && assimilated {
+            out.push_str(cons);
+            if scheme.phonetic() {
+                if cur.ch != 'ل' {
+                    out.push('-');
+                }
+                out.push_str(cons);
+            }
+        } else if cur.shadda {
+            out.push_str(cons);
+            out.push_str(cons);
+        } else {
+            out.push_str(cons);
+        }
+        assimilated = false;
+
+        let vowel = match (cur.vowel, cur.ch) {
+            (None, 'إ') if cur.tanween.is_none() => Some(Vowel::I),
+            (v, _) => v,
+        };
+        if let Some(t) = cur.tanween {
+            if next.is_some_and(|n| matches!(n.ch, 'ا' | 'ى') && n.seat()) {
+                consumed[i + 1] = true;
+            }
+            if pausal {
+                if t == Vowel::A {
+                    out.push_str(LONG[Vowel::A as usize][col]);
+                }
+            } else {
+                out.push_str(SHORT[t as usize]);
+                out.push('n');
+            }
+            continue;
+        }
+        let Some(v) = vowel else {
+            if cur.dagger {
+                out.push_str(LONG[Vowel::A as usize][col]);
+            }
+            continue;
+        };
+        let seat = match v {
+            Vowel::A => ['ا', 'ى'],
+            Vowel::I => ['ي', 'ي'],
+            Vowel::U => ['و', 'و'],
+        };
+        let madd_letter = next.is_some_and(|n| seat.contains(&n.ch) && n.seat() && !(v == Vowel::A && n.sukun));
+        let lafz_jalala = v == Vowel::A && cur.ch == 'ل' && cur.shadda && next.is_some_and(|n| n.ch == 'ه') && i + 2 == u.len();
+        let long = match v {
+            Vowel::A => cur.dagger || madd_letter || lafz_jalala,
+            Vowel::I => cur.small_yeh || madd_letter,
+            Vowel::U => cur.small_waw || madd_letter,
+        };
+        if madd_letter {
+            consumed[i + 1] = true;
+        }
+        if long {
+            out.push_str(LONG[v as usize][col]);
+        } else if !pausal {
+            out.push_str(SHORT[v as usize]);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fatiha() {
+        let cases: [[&str; 3]; 7] = [
+            ["bismi llahi r-rahmani r-rahim", "bismi allāhi al-raḥmāni al-raḥīm", "bismi allāhi al-raḥmāni al-raḥīm"],
+            ["al-hamdu lillahi rabbi l-'alamin", "al-ḥamdu lillāhi rabbi al-ʻālamīn", "al-ḥamdu lillāhi rabbi al-ʿālamīn"],
+            ["ar-rahmani r-rahim", "al-raḥmāni al-raḥīm", "al-raḥmāni al-raḥīm"],
+            ["maliki yawmi d-din", "māliki yawmi al-dīn", "māliki yawmi al-dīn"],
+            ["iyyaka na'budu wa'iyyaka nasta'in", "iyyāka naʻbudu waʼiyyāka nastaʻīn", "ʾiyyāka naʿbudu waʾiyyāka nastaʿīn"],
+            ["ihdina s-sirata l-mustaqim", "ihdinā al-ṣirāṭa al-mustaqīm", "ihdinā al-ṣirāṭa al-mustaqīm"],
+            [
+                "sirata lladhina an'amta 'alayhim ghayri l-maghdubi 'alayhim wala d-dallin",
+                "ṣirāṭa alladhīna anʻamta ʻalayhim ghayri al-maghḍūbi ʻalayhim walā al-ḍāllīn",
+                "ṣirāṭa allaḏīna ʾanʿamta ʿalayhim ġayri al-maġḍūbi ʿalayhim walā al-ḍāllīn",
+            ],
+        ];
+        let sf = crate::data::load_surah_text_fatiha().unwrap();
+        for (ayah, expected) in sf.ayat.iter().zip(cases.iter()) {
+            for (scheme, want) in [Scheme::Simple, Scheme::AlaLc, Scheme::Iso233].into_iter().zip(expected) {
+                assert_eq!(transliterate(ayah, scheme), *want, "{:?}", scheme);
+            }
+        }
+    }
+
+    #[test]
+    fn tanween_and_wasl() {
+        let cases = [
+            ("كِتَابًا", "kitābā"),
+            ("كِتَابًا مُبِينًا", "kitāban mubīnā"),
+            ("قَالُوا", "qālū"),
+            ("رَحْمَةٌ", "raḥmah"),
+            ("وَٱلْعَصْرِ", "wal-ʻaṣr"),
+            ("عَلَىٰ", "ʻalā"),
+        ];
+        for (arabic, want) in cases {
+            assert_eq!(transliterate(arabic, Scheme::AlaLc), want);
+        }
+    }
+}
@@ -1,25 +1,59 @@
+use crate::card::{self, AspectRatio, Card, CardFormat, CardOptions, CardTemplate};
+use crate::citation::{self, ayah_marker, arabic_digits, CopyContent, CopyFormat, CopySettings};
 use crate::data;
-use crate::state::{AppState, AyahRef};
-use crate::storage;
+use crate::export::{self, Document, ExportFormat};
+use crate::fonts;
+use crate::footnotes::{self, Footnote, Segment};
+use crate::mushaf::{self, LineKind, MushafPage};
+use crate::notes::{self, Highlight, Note};
+use crate::reference::{self, Reference};
+use crate::state::{AppState, AyahRef, FontSettings, Surah, TranslationLayout};
+use crate::storage::{self, Bookmark, BookmarkFilter};
+use crate::tajweed::{self, Rule};
+use crate::portal;
+use crate::range::AyahRange;
+use crate::theme::{self, ColorScheme, Theme};
+use crate::transliteration::transliterate;
 use anyhow::Result;
 use gio::prelude::*;
 use glib::clone;
 use gtk4 as gtk;
+use gtk::gdk;
 use gtk::prelude::*;
 use gtk4::prelude::ActionMapExt;
-use pango::{AttrList, AttrSize, AttrString};
-use std::cell::RefCell;
+use pango::{AttrColor, AttrList, AttrSize, AttrString};
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
+// Everything the surah list rows depend on; the rows are rebuilt when it changes.
+type SurahKey = (u16, Vec<String>, bool, TranslationLayout, FontSettings, Vec<Note>);
+
 pub fn build_ui_with_init(app: &gtk::Application, init: Option<AyahRef>) -> Result<()> {
     let state = Rc::new(RefCell::new(AppState::new()));
+    let registered = fonts::register_bundled(&data::assets_dir().join("fonts"));
     let window = gtk::ApplicationWindow::builder()
         .application(app)
         .title("HyprQur'an")
         .default_width(960)
         .default_height(720)
         .build();
-    window.set_opacity(0.92);
+    window.set_opacity(theme::DEFAULT_OPACITY);
+    let themer = Themer::new(&WidgetExt::display(&window));
+    let pango_context = window.pango_context();
+    // Let Pango pick up the application fonts added above.
+    if !registered.is_empty() {
+        if let Some(font_map) = pango_context.font_map() {
+            font_map.changed();
+        }
+    }
+    let families: Vec<String> = pango_context.list_families().iter().map(|f| f.name().to_string()).collect();
+    fonts::set_default_family(fonts::pick_family(&families));
+    let font_warning = gtk::InfoBar::new();
+    font_warning.set_message_type(gtk::MessageType::Warning);
+    font_warning.set_show_close_button(true);
+    font_warning.add_child(&gtk::Label::new(Some("No Quranic font found. Install Amiri Quran or place it in assets/fonts; Arabic text uses the default font.")));
+    font_warning.set_revealed(fonts::default_family().is_none());
+    font_warning.connect_response(|bar, _| bar.set_revealed(false));
 
     let header = gtk::HeaderBar::builder().title_widget(&gtk::Label::new(Some("HyprQur'an"))).build();
     let surah_combo = gtk::ComboBoxText::new();
@@ -41,7 +75,100 @@ pub fn build_ui_with_init(app: &gtk::Application, init: Option<AyahRef>) -> Resu
     let search_entry = gtk::SearchEntry::new();
     let toggle_translation = gtk::ToggleButton::with_label("Translation");
     let dark_toggle = gtk::ToggleButton::with_label("Dark");
+    let tafsir_toggle = gtk::ToggleButton::with_label("Tafsir");
+    let transliteration_toggle = gtk::ToggleButton::with_label("Transliteration");
+    let tajweed_toggle = gtk::ToggleButton::with_label("Tajweed");
+    let focus_toggle = gtk::ToggleButton::with_label("Focus");
+    let mushaf_toggle = gtk::ToggleButton::with_label("Mushaf");
     let lang_combo = gtk::ComboBoxText::new();
+    let arabic_font_button = gtk::FontButton::new();
+    let translation_font_button = gtk::FontButton::new();
+    let zoom_row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    zoom_row.add_css_class("linked");
+    for (icon, action) in [("zoom-out-symbolic", "app.zoom-out"), ("zoom-original-symbolic", "app.zoom-reset"), ("zoom-in-symbolic", "app.zoom-in")] {
+        let b = gtk::Button::from_icon_name(icon);
+        b.set_action_name(Some(action));
+        zoom_row.append(&b);
+    }
+    let fonts_grid = gtk::Grid::new();
+    fonts_grid.set_row_spacing(6);
+    fonts_grid.set_column_spacing(12);
+    fonts_grid.attach(&gtk::Label::new(Some("Arabic")), 0, 0, 1, 1);
+    fonts_grid.attach(&arabic_font_button, 1, 0, 1, 1);
+    fonts_grid.attach(&gtk::Label::new(Some("Translation")), 0, 1, 1, 1);
+    fonts_grid.attach(&translation_font_button, 1, 1, 1, 1);
+    fonts_grid.attach(&zoom_row, 1, 2, 1, 1);
+    let fonts_popover = gtk::Popover::new();
+    fonts_popover.set_child(Some(&fonts_grid));
+    let fonts_button = gtk::MenuButton::new();
+    fonts_button.set_label("Fonts");
+    fonts_button.set_popover(Some(&fonts_popover));
+    let theme_combo = gtk::ComboBoxText::new();
+    for t in Theme::ALL {
+        theme_combo.append(Some(t.id()), t.name());
+    }
+    let opacity_scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.5, 1.0, 0.01);
+    opacity_scale.set_width_request(160);
+    opacity_scale.set_value(theme::DEFAULT_OPACITY);
+    let theme_grid = gtk::Grid::new();
+    theme_grid.set_row_spacing(6);
+    theme_grid.set_column_spacing(12);
+    theme_grid.attach(&gtk::Label::new(Some("Theme")), 0, 0, 1, 1);
+    theme_grid.attach(&theme_combo, 1, 0, 1, 1);
+    theme_grid.attach(&gtk::Label::new(Some("Opacity")), 0, 1, 1, 1);
+    theme_grid.attach(&opacity_scale, 1, 1, 1, 1);
+    let follow_check = gtk::CheckButton::with_label("Follow system light/dark preference");
+    theme_grid.attach(&follow_check, 0, 2, 2, 1);
+    let theme_popover = gtk::Popover::new();
+    theme_popover.set_child(Some(&theme_grid));
+    let theme_button = gtk::MenuButton::new();
+    theme_button.set_label("Theme");
+    theme_button.set_popover(Some(&theme_popover));
+    let compare_button = gtk::MenuButton::new();
+    compare_button.set_label("Compare");
+    let compare_list = gtk::ListBox::new();
+    compare_list.set_selection_mode(gtk::SelectionMode::None);
+    let columns_check = gtk::CheckButton::with_label("Side by side");
+    let compare_panel = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    compare_panel.append(&compare_list);
+    compare_panel.append(&columns_check);
+    let compare_popover = gtk::Popover::new();
+    compare_popover.set_child(Some(&compare_panel));
+    compare_button.set_popover(Some(&compare_popover));
+    let mut compare_checks: Vec<(String, gtk::CheckButton)> = Vec::new();
+    if let Ok(translations) = data::get_available_translations() {
+        for (lang, name) in translations {
+            let row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+            let check = gtk::CheckButton::with_label(&format!("{} ({})", name, lang.to_uppercase()));
+            check.set_hexpand(true);
+            let raise = gtk::Button::from_icon_name("go-up-symbolic");
+            raise.set_tooltip_text(Some("Show earlier"));
+            row.append(&check);
+            row.append(&raise);
+            let item = gtk::ListBoxRow::new();
+            item.set_widget_name(&lang);
+            item.set_child(Some(&row));
+            compare_list.append(&item);
+            raise.connect_clicked(clone!(@strong state, @strong lang, @strong compare_list => move |_| {
+                {
+                    let mut st = state.borrow_mut();
+                    st.raise_compare_lang(&lang);
+                    persist(&st);
+                }
+                compare_list.invalidate_sort();
+            }));
+            compare_checks.push((lang, check));
+        }
+    }
+    // Selected translations first, in display order, then the rest by code.
+    compare_list.set_sort_func(clone!(@strong state => move |a, b| {
+        let Ok(st) = state.try_borrow() else { return gtk::Ordering::Equal };
+        let rank = |r: &gtk::ListBoxRow| {
+            let name = r.widget_name();
+            (st.compare_langs.iter().position(|l| l == name.as_str()).unwrap_or(usize::MAX), name)
+        };
+        rank(a).cmp(&rank(b)).into()
+    }));
     
     // Populate language combo with available translations
     #[cfg(feature = "sqlite")]
@@ -61,109 +188,567 @@ pub fn build_ui_with_init(app: &gtk::Application, init: Option<AyahRef>) -> Resu
     }
     
     lang_combo.set_active(Some(0));
+    let history_button = gtk::MenuButton::new();
+    history_button.set_icon_name("document-open-recent-symbolic");
+    history_button.set_tooltip_text(Some("History"));
+    let back_button = gtk::Button::from_icon_name("go-previous-symbolic");
+    back_button.set_action_name(Some("app.back"));
+    back_button.set_tooltip_text(Some("Back (Alt+Left)"));
+    let forward_button = gtk::Button::from_icon_name("go-next-symbolic");
+    forward_button.set_action_name(Some("app.forward"));
+    forward_button.set_tooltip_text(Some("Forward (Alt+Right)"));
+    let history_nav = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    history_nav.append(&back_button);
+    history_nav.append(&forward_button);
+    let recent_list = gtk::ListBox::new();
+    let recent_heading = gtk::Label::new(Some("Recently read"));
+    recent_heading.set_xalign(0.0);
+    recent_heading.add_css_class("heading");
+    let history_panel = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    history_panel.append(&history_nav);
+    history_panel.append(&recent_heading);
+    history_panel.append(&recent_list);
+    let history_popover = gtk::Popover::new();
+    history_popover.set_child(Some(&history_panel));
+    history_button.set_popover(Some(&history_popover));
+    header.pack_start(&history_button);
     header.pack_start(&surah_combo);
     header.pack_start(&ayah_spin);
     header.pack_start(&search_entry);
+    header.pack_start(&focus_toggle);
+    header.pack_start(&mushaf_toggle);
+    header.pack_end(&compare_button);
+    header.pack_end(&fonts_button);
+    header.pack_end(&theme_button);
     header.pack_end(&lang_combo);
     header.pack_end(&toggle_translation);
+    header.pack_end(&transliteration_toggle);
+    header.pack_end(&tajweed_toggle);
     header.pack_end(&dark_toggle);
+    header.pack_end(&tafsir_toggle);
 
     let vbox = gtk::Box::new(gtk::Orientation::Vertical, 8);
     let arabic_area = gtk::ScrolledWindow::builder().hexpand(true).vexpand(true).build();
+    let transliteration_area = gtk::ScrolledWindow::builder().hexpand(true).vexpand(true).visible(false).build();
     let translation_area = gtk::ScrolledWindow::builder().hexpand(true).vexpand(true).build();
+    let transliteration_label = gtk::Label::new(None);
+    transliteration_label.set_wrap(true);
+    transliteration_label.set_xalign(0.0);
+    transliteration_label.set_direction(gtk::TextDirection::Ltr);
+    transliteration_area.set_child(Some(&transliteration_label));
     let arabic_label = gtk::Label::new(None);
-    let translation_label = gtk::Label::new(None);
+    let focus_translations = gtk::Box::new(gtk::Orientation::Vertical, 12);
     arabic_label.set_wrap(true);
-    translation_label.set_wrap(true);
     arabic_label.set_xalign(0.0);
-    translation_label.set_xalign(0.0);
     arabic_label.set_direction(gtk::TextDirection::Rtl);
-    translation_label.set_direction(gtk::TextDirection::Ltr);
-    let attrs = arabic_attrs();
+    let attrs = arabic_attrs("", false, &FontSettings::default());
     arabic_label.set_attributes(Some(&attrs));
+    let tajweed_legend = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+    tajweed_legend.set_halign(gtk::Align::Center);
+    tajweed_legend.set_visible(false);
+    for rule in Rule::ALL {
+        let item = gtk::Label::new(None);
+        item.set_markup(&format!("<span foreground=\"{}\">●</span> {}", rule.colour(), rule.name()));
+        tajweed_legend.append(&item);
+    }
+    let footnote_popover = FootnotePopover::new();
     arabic_area.set_child(Some(&arabic_label));
-    translation_area.set_child(Some(&translation_label));
+    translation_area.set_child(Some(&focus_translations));
     vbox.append(&arabic_area);
+    vbox.append(&transliteration_area);
     vbox.append(&translation_area);
+    vbox.set_focusable(true);
+
+    let surah_store = gio::ListStore::new::<glib::BoxedAnyObject>();
+    let surah_notes: Rc<RefCell<Vec<Note>>> = Rc::new(RefCell::new(Vec::new()));
+    let surah_factory = gtk::SignalListItemFactory::new();
+    surah_factory.connect_setup(clone!(@strong state => move |_, item| {
+        let Some(item) = item.downcast_ref::<gtk::ListItem>() else { return };
+        let row = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        row.set_margin_top(12);
+        row.set_margin_bottom(12);
+        row.set_margin_start(16);
+        row.set_margin_end(16);
+        let arabic = gtk::Label::new(None);
+        arabic.set_wrap(true);
+        arabic.set_xalign(0.0);
+        arabic.set_direction(gtk::TextDirection::Rtl);
+        let translations = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        let ayah_notes = gtk::Label::new(None);
+        ayah_notes.set_wrap(true);
+        ayah_notes.set_xalign(0.0);
+        ayah_notes.add_css_class("ayah-notes");
+        ayah_notes.connect_activate_link(|label, uri| match uri.strip_prefix("note:").and_then(|id| id.parse::<i64>().ok()) {
+            Some(id) => {
+                let _ = label.activate_action("app.edit-note", Some(&id.to_variant()));
+                glib::Propagation::Stop
+            }
+            None => glib::Propagation::Proceed,
+        });
+        row.append(&arabic);
+        row.append(&translations);
+        row.append(&ayah_notes);
+        // Right-click menu; its actions get the row's ayah as `surah * 1000 + ayah`.
+        let menu = gtk::PopoverMenu::from_model(None::<&gio::MenuModel>);
+        menu.set_parent(&row);
+        menu.set_has_arrow(false);
+        let click = gtk::GestureClick::new();
+        click.set_button(gdk::BUTTON_SECONDARY);
+        click.connect_pressed(clone!(@strong state, @weak row, @weak menu => move |_, _, x, y| {
+            let Some(ayah) = row.widget_name().strip_prefix("ayah-").and_then(|n| n.parse::<i64>().ok()) else { return };
+            let Ok(surah) = state.try_borrow().map(|st| st.current.surah_id as i64) else { return };
+            let model = gio::Menu::new();
+            let share = gio::MenuItem::new(Some("Share as image…"), None);
+            share.set_action_and_target_value(Some("app.verse-image"), Some(&(surah * 1000 + ayah).to_variant()));
+            model.append_item(&share);
+            menu.set_menu_model(Some(&model));
+            menu.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, y as i32, 1, 1)));
+            menu.popup();
+        }));
+        row.add_controller(click);
+        item.set_child(Some(&row));
+    }));
+    surah_factory.connect_bind(clone!(@strong state, @strong footnote_popover, @strong surah_notes => move |_, item| {
+        let Some(item) = item.downcast_ref::<gtk::ListItem>() else { return };
+        let Some(obj) = item.item().and_downcast::<glib::BoxedAnyObject>() else { return };
+        let Some(row) = item.child().and_downcast::<gtk::Box>() else { return };
+        let Some(arabic) = row.first_child().and_downcast::<gtk::Label>() else { return };
+        let Some(translations) = arabic.next_sibling().and_downcast::<gtk::Box>() else { return };
+        let Some(ayah_notes) = translations.next_sibling().and_downcast::<gtk::Label>() else { return };
+        let ayah = obj.borrow::<data::AyahText>();
+        let (tajweed, layout, fonts) = state.try_borrow().map(|st| (st.show_tajweed, st.translation_layout, st.fonts.clone())).unwrap_or_default();
+        let notes: Vec<Note> = surah_notes.borrow().iter().filter(|n| n.ayah == ayah.ayah).cloned().collect();
+        arabic.set_text(&format!("{} {}", ayah.arabic, ayah_marker(ayah.ayah.ayah_index)));
+        arabic.set_attributes(Some(&highlighted_attrs(&ayah.arabic, tajweed, &fonts, &notes)));
+        fill_translations(&translations, &ayah.translations, layout, &fonts, &footnote_popover);
+        ayah_notes.set_markup(&notes_markup(&notes));
+        ayah_notes.set_visible(!notes.is_empty());
+        row.set_widget_name(&format!("ayah-{}", ayah.ayah.ayah_index));
+    }));
+    let surah_list = gtk::ListView::new(Some(gtk::NoSelection::new(Some(surah_store.clone()))), Some(surah_factory));
+    let surah_area = gtk::ScrolledWindow::builder().hexpand(true).vexpand(true).child(&surah_list).build();
+
+    // Pages are laid out right to left, so in a spread the odd page comes first.
+    let right_page = gtk::Box::new(gtk::Orientation::Vertical, 4);
+    let left_page = gtk::Box::new(gtk::Orientation::Vertical, 4);
+    let page_spread = gtk::Box::new(gtk::Orientation::Horizontal, 24);
+    page_spread.set_direction(gtk::TextDirection::Rtl);
+    page_spread.set_homogeneous(true);
+    page_spread.set_halign(gtk::Align::Center);
+    page_spread.set_margin_top(12);
+    page_spread.set_margin_bottom(12);
+    page_spread.append(&right_page);
+    page_spread.append(&left_page);
+    let page_area = gtk::ScrolledWindow::builder().hexpand(true).vexpand(true).child(&page_spread).build();
+    page_area.set_focusable(true);
+
+    let view_stack = gtk::Stack::new();
+    view_stack.set_hexpand(true);
+    view_stack.add_named(&surah_area, Some("surah"));
+    view_stack.add_named(&vbox, Some("focus"));
+    view_stack.add_named(&page_area, Some("page"));
+    let reading_box = gtk::Box::new(gtk::Orientation::Vertical, 8);
+    reading_box.set_hexpand(true);
+    reading_box.append(&view_stack);
+    reading_box.append(&tajweed_legend);
+
+    let tafsir_combo = gtk::ComboBoxText::new();
+    if let Ok(tafsirs) = data::get_available_tafsirs() {
+        for (id, name, lang) in tafsirs {
+            tafsir_combo.append(Some(&id.to_string()), &format!("{} ({})", name, lang.to_uppercase()));
+        }
+    }
+    let tafsir_label = gtk::Label::new(None);
+    tafsir_label.set_wrap(true);
+    tafsir_label.set_selectable(true);
+    tafsir_label.set_xalign(0.0);
+    tafsir_label.set_yalign(0.0);
+    let tafsir_area = gtk::ScrolledWindow::builder().vexpand(true).child(&tafsir_label).build();
+    let tafsir_panel = gtk::Box::new(gtk::Orientation::Vertical, 8);
+    tafsir_panel.set_width_request(320);
+    tafsir_panel.set_margin_start(8);
+    tafsir_panel.set_margin_end(8);
+    tafsir_panel.append(&tafsir_combo);
+    tafsir_panel.append(&tafsir_area);
+    let tafsir_revealer = gtk::Revealer::builder()
+        .transition_type(gtk::RevealerTransitionType::SlideLeft)
+        .child(&tafsir_panel)
+        .reveal_child(false)
+        .build();
+
+    let content = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+    content.append(&reading_box);
+    content.append(&gtk::Separator::new(gtk::Orientation::Vertical));
+    content.append(&tafsir_revealer);
 
     let root = gtk::Box::new(gtk::Orientation::Vertical, 0);
     root.append(&header);
-    root.append(&vbox);
+    root.append(&font_warning);
+    root.append(&content);
     window.set_child(Some(&root));
 
     let bookmarks_button = gtk::MenuButton::new();
     bookmarks_button.set_label("Bookmarks");
     let popover = gtk::Popover::new();
     let list = gtk::ListBox::new();
-    popover.set_child(Some(&list));
+    let manage_button = gtk::Button::with_label("Manage…");
+    manage_button.set_action_name(Some("app.manage-bookmarks"));
+    let bookmarks_panel = gtk::Box::new(gtk::Orientation::Vertical, 6);
+    bookmarks_panel.append(&gtk::ScrolledWindow::builder().child(&list).propagate_natural_height(true).max_content_height(400).build());
+    bookmarks_panel.append(&manage_button);
+    popover.set_child(Some(&bookmarks_panel));
     bookmarks_button.set_popover(Some(&popover));
     header.pack_end(&bookmarks_button);
+    let notes_button = gtk::Button::with_label("Notes");
+    notes_button.set_action_name(Some("app.notes"));
+    header.pack_end(&notes_button);
+    let share_menu = gio::Menu::new();
+    share_menu.append(Some("Copy ayat…"), Some("app.copy-range"));
+    share_menu.append(Some("Export to PDF or HTML…"), Some("app.export"));
+    share_menu.append(Some("Share as image…"), Some("app.verse-image(int64 0)"));
+    let share_button = gtk::MenuButton::new();
+    share_button.set_icon_name("document-send-symbolic");
+    share_button.set_tooltip_text(Some("Copy and export"));
+    share_button.set_menu_model(Some(&share_menu));
+    header.pack_end(&share_button);
+
+    let mushaf_page: Rc<Cell<u16>> = Rc::new(Cell::new(1));
+    let render_mushaf = {
+        let state = state.clone();
+        let window = window.clone();
+        let right_page = right_page.clone();
+        let left_page = left_page.clone();
+        let mushaf_page = mushaf_page.clone();
+        move || {
+            let st = state.borrow().clone();
+            if !st.mushaf_mode {
+                return;
+            }
+            // Stay on the requested page while it shows the current ayah, so
+            // flipping to a page that opens mid-ayah does not jump back.
+            let shown = data::load_mushaf_page(mushaf_page.get()).ok();
+            if !shown.is_some_and(|p| p.contains(&st.current)) {
+                if let Some(n) = data::page_for_ayah(&st.current) {
+                    mushaf_page.set(n);
+                }
+            }
+            let (right, left) = match window.default_width() >= SPREAD_MIN_WIDTH {
+                true => mushaf::spread(mushaf_page.get()),
+                false => (mushaf_page.get(), None),
+            };
+            fill_page(&right_page, data::load_mushaf_page(right).ok().as_ref(), &st);
+            left_page.set_visible(left.is_some());
+            if let Some(left) = left {
+                fill_page(&left_page, data::load_mushaf_page(left).ok().as_ref(), &st);
+            }
+        }
+    };
 
+    let scroll_sync = ScrollSync::default();
     let refresh = {
+        let render_mushaf = render_mushaf.clone();
         let state = state.clone();
         let arabic_label = arabic_label.clone();
-        let translation_label = translation_label.clone();
+        let focus_translations = focus_translations.clone();
+        let footnote_popover = footnote_popover.clone();
+        let tafsir_label = tafsir_label.clone();
+        let transliteration_label = transliteration_label.clone();
+        let surah_store = surah_store.clone();
+        let surah_notes = surah_notes.clone();
+        let surah_list = surah_list.clone();
+        let surah_key: Rc<RefCell<Option<SurahKey>>> = Rc::new(RefCell::new(None));
+        let scroll_sync = scroll_sync.clone();
         move || {
             let st = state.borrow().clone();
+            let langs = st.translation_langs();
+            let notes = data::load_notes(Some(st.current.surah_id)).unwrap_or_default();
+            let key = (st.current.surah_id, langs.clone(), st.show_tajweed, st.translation_layout, st.fonts.clone(), notes.clone());
+            if surah_key.borrow().as_ref() != Some(&key) {
+                *surah_notes.borrow_mut() = notes.clone();
+                let rows = data::load_surah_with_translations(st.current.surah_id, &langs).unwrap_or_default();
+                let objs: Vec<glib::BoxedAnyObject> = rows.into_iter().map(glib::BoxedAnyObject::new).collect();
+                surah_store.splice(0, surah_store.n_items(), &objs);
+                *surah_key.borrow_mut() = Some(key);
+            }
+            if !scroll_sync.is_syncing() {
+                scroll_sync.hold();
+                let pos = st.current.ayah_index.saturating_sub(1) as u32;
+                let _ = surah_list.activate_action("list.scroll-to-item", Some(&pos.to_variant()));
+            }
             if !st.current_ayat.is_empty() {
                 let idx = (st.current.ayah_index.max(1) as usize).min(st.current_ayat.len());
                 let arabic = st.current_ayat[idx - 1].clone();
+                let ayah_notes: Vec<Note> = notes.iter().filter(|n| n.ayah == st.current).cloned().collect();
                 arabic_label.set_text(&arabic);
+                arabic_label.set_attributes(Some(&highlighted_attrs(&arabic, st.show_tajweed, &st.fonts, &ayah_notes)));
             }
-            if let Some(lang) = st.translation_lang.clone() {
-                if let Ok(tf) = data::load_translation(&lang, st.current.surah_id) {
-                    let idx = st.current.ayah_index;
-                    if let Some(e) = tf.entries.iter().find(|e| e.ayah == idx) {
-                        translation_label.set_text(&e.text);
-                    } else {
-                        translation_label.set_text("");
-                    }
-                } else {
-                    translation_label.set_text("");
-                }
-            } else {
-                translation_label.set_text("");
+            let current = surah_store
+                .item(st.current.ayah_index.saturating_sub(1) as u32)
+                .and_downcast::<glib::BoxedAnyObject>();
+            match current {
+                Some(obj) => fill_translations(&focus_translations, &obj.borrow::<data::AyahText>().translations, st.translation_layout, &st.fonts, &footnote_popover),
+                None => fill_translations(&focus_translations, &[], st.translation_layout, &st.fonts, &footnote_popover),
             }
+            let transliteration = st
+                .transliteration_lang
+                .as_ref()
+                .filter(|_| st.show_transliteration)
+                .and_then(|lang| data::load_transliteration(lang, st.current.surah_id).ok())
+                .and_then(|tf| tf.entries.into_iter().find(|e| e.ayah == st.current.ayah_index))
+                .map(|e| e.text);
+            // Fall back to the rule-based generator when no edition covers this ayah
+            let transliteration = match transliteration {
+                Some(t) => t,
+                None if st.show_transliteration => transliterate(arabic_label.text().as_str(), st.transliteration_scheme),
+                None => String::new(),
+            };
+            transliteration_label.set_text(&transliteration);
+            transliteration_label.set_attributes(Some(&translation_attrs(&st.fonts)));
+            tafsir_label.set_attributes(Some(&translation_attrs(&st.fonts)));
+            match st.tafsir_id.map(|id| data::load_tafsir(id, &st.current)) {
+                Some(Ok(entries)) if !entries.is_empty() => tafsir_label.set_text(&tafsir_text(&entries)),
+                Some(_) => tafsir_label.set_text("No tafsir for this ayah."),
+                None => tafsir_label.set_text(""),
+            }
+            render_mushaf();
         }
     };
 
-    surah_combo.connect_changed(clone!(@strong state, @strong ayah_spin, @strong refresh => move |c| {
-        if let Some(idx) = c.active() {
-            let mut st = state.borrow_mut();
-            let surah = st.surahs.get(idx as usize).cloned();
-            if let Some(s) = surah {
-                st.current = AyahRef { surah_id: s.id, ayah_index: 1 };
-                ayah_spin.set_range(1.0, s.ayah_count as f64);
-                ayah_spin.set_value(1.0);
-                if let Ok(sf) = crate::data::load_surah_text(s.id) {
-                    st.set_ayat(sf.ayat);
-                } else {
-                    st.set_ayat(Vec::new());
+    let flip = {
+        let state = state.clone();
+        let window = window.clone();
+        let surah_combo = surah_combo.clone();
+        let ayah_spin = ayah_spin.clone();
+        let mushaf_page = mushaf_page.clone();
+        let refresh = refresh.clone();
+        move |forward: bool| {
+            let step = if window.default_width() >= SPREAD_MIN_WIDTH { 2 } else { 1 };
+            let current = mushaf_page.get();
+            let page = if forward { (current + step).min(mushaf::PAGE_COUNT) } else { current.saturating_sub(step).max(1) };
+            let Ok(p) = data::load_mushaf_page(page) else { return };
+            mushaf_page.set(page);
+            match p.first_ayah() {
+                Some(first) if first != state.borrow().current => goto(&surah_combo, &ayah_spin, &first),
+                _ => refresh(),
+            }
+        }
+    };
+    let page_keys = gtk::EventControllerKey::new();
+    page_keys.connect_key_pressed(clone!(@strong flip => move |_, key, _, _| {
+        match key {
+            gdk::Key::Left | gdk::Key::Page_Down | gdk::Key::space => { flip(true); glib::Propagation::Stop }
+            gdk::Key::Right | gdk::Key::Page_Up | gdk::Key::BackSpace => { flip(false); glib::Propagation::Stop }
+            _ => glib::Propagation::Proceed,
+        }
+    }));
+    page_area.add_controller(page_keys);
+    // Swiping right turns the page forward, as in a right-to-left book.
+    let page_swipe = gtk::GestureSwipe::new();
+    page_swipe.connect_swipe(clone!(@strong flip => move |_, vx, vy| {
+        if vx.abs() > vy.abs() && vx.abs() > 300.0 {
+            flip(vx > 0.0);
+        }
+    }));
+    page_area.add_controller(page_swipe);
+    window.connect_default_width_notify(clone!(@strong render_mushaf => move |_| render_mushaf()));
+
+    // Set while replaying history so the surah change is not recorded again.
+    let replaying = Rc::new(Cell::new(false));
+    surah_combo.connect_changed(clone!(@strong state, @strong ayah_spin, @strong refresh, @strong replaying => move |c| {
+        let Some(idx) = c.active() else { return };
+        let surah = state.borrow().surahs.get(idx as usize).cloned();
+        if let Some(s) = surah {
+            let ayat = crate::data::load_surah_text(s.id).map(|sf| sf.ayat).unwrap_or_default();
+            {
+                let mut st = state.borrow_mut();
+                if !replaying.get() && st.current.surah_id != s.id {
+                    let from = st.current.clone();
+                    st.history.push(from);
                 }
-                refresh();
+                st.current = AyahRef { surah_id: s.id, ayah_index: 1 };
+                st.set_ayat(ayat);
             }
+            ayah_spin.set_range(1.0, s.ayah_count as f64);
+            ayah_spin.set_value(1.0);
+            refresh();
         }
     }));
 
     ayah_spin.connect_value_changed(clone!(@strong state, @strong refresh => move |sp| {
-        let mut st = state.borrow_mut();
-        st.current.ayah_index = sp.value() as u16;
+        {
+            let mut st = state.borrow_mut();
+            st.current.ayah_index = sp.value() as u16;
+            let current = st.current.clone();
+            st.history.read(&current);
+            persist(&st);
+        }
+        refresh();
+    }));
+
+    surah_area.vadjustment().connect_value_changed(clone!(@strong state, @strong surah_list, @strong ayah_spin, @strong scroll_sync => move |_| {
+        if scroll_sync.is_syncing() {
+            return;
+        }
+        let Some(top) = top_ayah(&surah_list) else { return };
+        if top == state.borrow().current.ayah_index {
+            return;
+        }
+        scroll_sync.syncing.set(true);
+        ayah_spin.set_value(top as f64);
+        scroll_sync.syncing.set(false);
+    }));
+
+    focus_toggle.connect_toggled(clone!(@strong state, @strong view_stack, @strong vbox, @strong refresh => move |t| {
+        {
+            let mut st = state.borrow_mut();
+            st.focus_mode = t.is_active();
+            view_stack.set_visible_child_name(view_name(&st));
+            persist(&st);
+        }
+        refresh();
+        if t.is_active() {
+            vbox.grab_focus();
+        }
+    }));
+
+    mushaf_toggle.connect_toggled(clone!(@strong state, @strong view_stack, @strong page_area, @strong refresh => move |t| {
+        {
+            let mut st = state.borrow_mut();
+            st.mushaf_mode = t.is_active();
+            view_stack.set_visible_child_name(view_name(&st));
+            persist(&st);
+        }
+        refresh();
+        if t.is_active() {
+            page_area.grab_focus();
+        }
+    }));
+
+    arabic_font_button.connect_font_set(clone!(@strong state, @strong refresh => move |b| {
+        let Some((family, size)) = chosen_font(b) else { return };
+        {
+            let mut st = state.borrow_mut();
+            st.fonts.arabic_family = Some(family);
+            if let Some(size) = size {
+                st.fonts.arabic_size = size;
+            }
+            persist(&st);
+        }
+        refresh();
+    }));
+
+    translation_font_button.connect_font_set(clone!(@strong state, @strong refresh => move |b| {
+        let Some((family, size)) = chosen_font(b) else { return };
+        {
+            let mut st = state.borrow_mut();
+            st.fonts.translation_family = Some(family);
+            if let Some(size) = size {
+                st.fonts.translation_size = size;
+            }
+            persist(&st);
+        }
+        refresh();
+    }));
+
+    for (name, steps, accels) in [
+        ("zoom-in", 1, &["<Control>plus", "<Control>equal", "<Control>KP_Add"][..]),
+        ("zoom-out", -1, &["<Control>minus", "<Control>KP_Subtract"][..]),
+        ("zoom-reset", 0, &["<Control>0"][..]),
+    ] {
+        let action = gio::SimpleAction::new(name, None);
+        action.connect_activate(clone!(@strong state, @strong refresh, @strong arabic_font_button, @strong translation_font_button => move |_, _| {
+            let fonts = {
+                let mut st = state.borrow_mut();
+                if steps == 0 { st.fonts.reset_sizes() } else { st.fonts.zoom(steps) }
+                persist(&st);
+                st.fonts.clone()
+            };
+            sync_font_buttons(&arabic_font_button, &translation_font_button, &fonts);
+            refresh();
+        }));
+        app.add_action(&action);
+        app.set_accels_for_action(&format!("app.{}", name), accels);
+    }
+
+    for (lang, check) in &compare_checks {
+        check.connect_toggled(clone!(@strong state, @strong lang, @strong compare_list, @strong refresh => move |c| {
+            {
+                let mut st = state.borrow_mut();
+                let present = st.compare_langs.contains(&lang);
+                if c.is_active() && !present {
+                    st.compare_langs.push(lang.clone());
+                } else if !c.is_active() && present {
+                    st.compare_langs.retain(|l| l != &lang);
+                }
+                persist(&st);
+            }
+            compare_list.invalidate_sort();
+            refresh();
+        }));
+    }
+
+    columns_check.connect_toggled(clone!(@strong state, @strong refresh => move |c| {
+        {
+            let mut st = state.borrow_mut();
+            st.translation_layout = if c.is_active() { TranslationLayout::Columns } else { TranslationLayout::Stacked };
+            persist(&st);
+        }
         refresh();
     }));
 
     toggle_translation.connect_toggled(clone!(@strong state, @strong refresh => move |t| {
         let active = t.is_active();
-        let mut st = state.borrow_mut();
-        st.translation_lang = if active { Some("en".to_string()) } else { None };
-        persist(&st);
+        {
+            let mut st = state.borrow_mut();
+            st.translation_lang = if active { Some("en".to_string()) } else { None };
+            persist(&st);
+        }
         refresh();
     }));
 
     lang_combo.connect_changed(clone!(@strong state, @strong toggle_translation, @strong refresh => move |c| {
+        {
+            let mut st = state.borrow_mut();
+            let lang = c.active_id().map(|id| id.to_string());
+            st.translation_lang = if toggle_translation.is_active() { lang } else { None };
+            persist(&st);
+        }
+        refresh();
+    }));
+
+    transliteration_toggle.connect_toggled(clone!(@strong state, @strong transliteration_area, @strong refresh => move |t| {
+        {
+            let mut st = state.borrow_mut();
+            st.show_transliteration = t.is_active();
+            transliteration_area.set_visible(st.show_transliteration);
+            persist(&st);
+        }
+        refresh();
+    }));
+
+    tajweed_toggle.connect_toggled(clone!(@strong state, @strong tajweed_legend, @strong refresh => move |t| {
+        {
+            let mut st = state.borrow_mut();
+            st.show_tajweed = t.is_active();
+            tajweed_legend.set_visible(st.show_tajweed);
+            persist(&st);
+        }
+        refresh();
+    }));
+
+    tafsir_toggle.connect_toggled(clone!(@strong state, @strong tafsir_revealer => move |t| {
         let mut st = state.borrow_mut();
-        let lang = c.active_id().map(|id| id.to_string());
-        st.translation_lang = if toggle_translation.is_active() { lang } else { None };
+        st.show_tafsir = t.is_active();
+        tafsir_revealer.set_reveal_child(st.show_tafsir);
         persist(&st);
+    }));
+
+    tafsir_combo.connect_changed(clone!(@strong state, @strong refresh => move |c| {
+        {
+            let mut st = state.borrow_mut();
+            st.tafsir_id = c.active_id().and_then(|id| id.parse::<i64>().ok());
+            persist(&st);
+        }
         refresh();
     }));
 
@@ -173,166 +758,1995 @@ pub fn build_ui_with_init(app: &gtk::Application, init: Option<AyahRef>) -> Resu
         st.run_search(&q);
     }));
 
-    dark_toggle.connect_toggled(clone!(@strong state => move |t| {
+    theme_combo.connect_changed(clone!(@strong state, @strong themer => move |c| {
+        let Some(theme) = c.active_id().and_then(|id| Theme::from_id(&id)) else { return };
+        themer.set_theme(theme);
         let mut st = state.borrow_mut();
-        st.prefer_dark = t.is_active();
-        if let Some(settings) = gtk::Settings::default() {
-            settings.set_gtk_application_prefer_dark_theme(st.prefer_dark);
-        }
+        st.theme = theme;
         persist(&st);
     }));
 
-    add_shortcuts(app, state.clone(), search_entry.clone(), refresh.clone(), list.as_ref());
-
-    if let Ok(s) = data::load_surah_text(1) {
+    opacity_scale.connect_value_changed(clone!(@strong state, @strong window => move |s| {
+        window.set_opacity(s.value());
         let mut st = state.borrow_mut();
-        st.set_ayat(s.ayat);
+        st.opacity = s.value();
+        persist(&st);
+    }));
+
+    let system_scheme: Rc<Cell<Option<ColorScheme>>> = Rc::new(Cell::new(None));
+    let portal_conn = gio::bus_get_sync(gio::BusType::Session, gio::Cancellable::NONE).ok();
+    match &portal_conn {
+        Some(conn) => {
+            system_scheme.set(portal::read_color_scheme(conn).ok());
+            portal::watch_color_scheme(conn, clone!(@strong state, @strong system_scheme => move |scheme| {
+                system_scheme.set(Some(scheme));
+                if let Ok(st) = state.try_borrow() {
+                    apply_dark(&st, Some(scheme));
+                }
+            }));
+        }
+        None => {
+            follow_check.set_sensitive(false);
+            follow_check.set_tooltip_text(Some("No session bus; the settings portal is unavailable."));
+        }
     }
-    if let Some(p) = storage::load() {
+
+    dark_toggle.connect_toggled(clone!(@strong state, @strong system_scheme => move |t| {
         let mut st = state.borrow_mut();
-        st.current = p.last;
-        st.translation_lang = p.translation_lang;
-        st.prefer_dark = p.prefer_dark;
-        if let Some(settings) = gtk::Settings::default() { settings.set_gtk_application_prefer_dark_theme(st.prefer_dark); }
-        dark_toggle.set_active(st.prefer_dark);
-        if let Some(lang) = &st.translation_lang { 
-            toggle_translation.set_active(true); 
-            // Set the active ID in the combo box
-            lang_combo.set_active_id(Some(lang));
-        };
-        ayah_spin.set_value(st.current.ayah_index as f64);
-    }
-    if let Some(i) = init {
+        st.prefer_dark = t.is_active();
+        apply_dark(&st, system_scheme.get());
+        persist(&st);
+    }));
+
+    follow_check.connect_toggled(clone!(@strong state, @strong system_scheme, @strong dark_toggle, @strong portal_conn => move |c| {
+        // The portal may have started after us, so ask again when switching on.
+        if c.is_active() {
+            if let Some(scheme) = portal_conn.as_ref().and_then(|conn| portal::read_color_scheme(conn).ok()) {
+                system_scheme.set(Some(scheme));
+            }
+        }
+        dark_toggle.set_sensitive(!c.is_active());
         let mut st = state.borrow_mut();
-        st.current = i;
-        ayah_spin.set_value(st.current.ayah_index as f64);
+        st.follow_system_scheme = c.is_active();
+        apply_dark(&st, system_scheme.get());
+        persist(&st);
+    }));
+
+    add_shortcuts(app, state.clone(), search_entry.clone(), surah_combo.clone(), ayah_spin.clone(), refresh.clone(), list.as_ref());
+
+    // Up and Down step through ayat only while the reading view has focus, so
+    // they still move the cursor in entries and lists elsewhere.
+    let reading_keys = gtk::ShortcutController::new();
+    reading_keys.set_scope(gtk::ShortcutScope::Local);
+    reading_keys.set_propagation_phase(gtk::PropagationPhase::Capture);
+    for (key, action) in [(gdk::Key::Down, "app.next-ayah"), (gdk::Key::Up, "app.prev-ayah")] {
+        let trigger = gtk::KeyvalTrigger::new(key, gdk::ModifierType::empty());
+        reading_keys.add_shortcut(gtk::Shortcut::new(Some(trigger), Some(gtk::NamedAction::new(action))));
     }
-    refresh();
-    window.present();
-    Ok(())
-}
+    view_stack.add_controller(reading_keys);
 
-fn arabic_attrs() -> AttrList {
-    let attrs = AttrList::new();
-    let family = runtime_font_family();
-    attrs.insert(AttrString::new_family(&family));
-    attrs.insert(AttrSize::new_size_absolute(20 * pango::SCALE));
-    attrs
-}
+    // The open manager, if any; it lives until its window is closed.
+    let bookmark_manager: Rc<RefCell<Option<Rc<BookmarkManager>>>> = Rc::new(RefCell::new(None));
+    let manage_bookmarks = gio::SimpleAction::new("manage-bookmarks", None);
+    manage_bookmarks.connect_activate(clone!(@strong state, @strong window, @strong surah_combo, @strong ayah_spin, @strong list, @strong bookmark_manager => move |_, _| {
+        if let Some(manager) = bookmark_manager.borrow().as_ref() {
+            manager.reload();
+            manager.window.present();
+            return;
+        }
+        let surahs = state.borrow().surahs.clone();
+        let on_goto = clone!(@strong state, @strong surah_combo, @strong ayah_spin => move |r: &AyahRef| jump(&state, &surah_combo, &ayah_spin, r));
+        let on_change = clone!(@strong list, @strong surahs => move || fill_bookmark_list(&list, &surahs));
+        let manager = BookmarkManager::open(&window, surahs, on_goto, on_change);
+        manager.window.connect_close_request(clone!(@strong bookmark_manager => move |_| {
+            bookmark_manager.replace(None);
+            glib::Propagation::Proceed
+        }));
+        bookmark_manager.replace(Some(manager));
+    }));
+    app.add_action(&manage_bookmarks);
+    app.set_accels_for_action("app.manage-bookmarks", &["<Control>B"]);
 
-fn runtime_font_family() -> String {
-    let bundled = crate::data::assets_dir().join("fonts").join("AmiriQuran.ttf");
-    if bundled.exists() { return "Amiri Quran".to_string(); }
-    "Amiri Quran".to_string()
-}
+    // Id 0 starts a new note on the current ayah.
+    let edit_note = gio::SimpleAction::new("edit-note", Some(glib::VariantTy::INT64));
+    edit_note.connect_activate(clone!(@strong state, @strong window, @strong refresh => move |_, param| {
+        let id = param.and_then(|p| p.get::<i64>()).unwrap_or(0);
+        let current = state.borrow().current.clone();
+        let note = match id {
+            0 => Some(Note::new(current)),
+            id => data::load_notes(None).unwrap_or_default().into_iter().find(|n| n.id == id),
+        };
+        let Some(note) = note else { return };
+        let text = data::load_surah_text(note.ayah.surah_id)
+            .ok()
+            .and_then(|sf| sf.ayat.get(note.ayah.ayah_index.saturating_sub(1) as usize).cloned())
+            .unwrap_or_default();
+        let heading = format!("{} {}:{}", surah_name(&state.borrow().surahs, note.ayah.surah_id), note.ayah.surah_id, note.ayah.ayah_index);
+        show_note_editor(&window, note, &heading, &text, refresh.clone());
+    }));
+    app.add_action(&edit_note);
+    app.set_accels_for_action("app.edit-note(int64 0)", &["<Control>N"]);
 
-fn add_shortcuts(app: &gtk::Application, state: Rc<RefCell<AppState>>, search_entry: gtk::SearchEntry, refresh: impl Fn() + 'static + Clone, list: &gtk::ListBox) {
-    let next_ayah = gio::SimpleAction::new("next-ayah", None);
-    next_ayah.connect_activate(clone!(@strong state, @strong refresh => move |_, _| {
-        let mut st = state.borrow_mut();
-        st.current.ayah_index = (st.current.ayah_index + 1).min(7);
-        persist(&st);
-        refresh();
+    // The open browser, if any; it lives until its window is closed.
+    let notes_browser: Rc<RefCell<Option<Rc<NotesBrowser>>>> = Rc::new(RefCell::new(None));
+    let open_notes = gio::SimpleAction::new("notes", None);
+    open_notes.connect_activate(clone!(@strong state, @strong window, @strong surah_combo, @strong ayah_spin, @strong notes_browser => move |_, _| {
+        if let Some(browser) = notes_browser.borrow().as_ref() {
+            browser.window.present();
+            return;
+        }
+        let surahs = state.borrow().surahs.clone();
+        let on_goto = clone!(@strong state, @strong surah_combo, @strong ayah_spin => move |r: &AyahRef| jump(&state, &surah_combo, &ayah_spin, r));
+        let browser = NotesBrowser::open(&window, surahs, on_goto);
+        browser.window.connect_close_request(clone!(@strong notes_browser => move |_| {
+            notes_browser.replace(None);
+            glib::Propagation::Proceed
+        }));
+        notes_browser.replace(Some(browser));
     }));
-    app.add_action(&next_ayah);
-    app.set_accels_for_action("app.next-ayah", &["<Alt>Right"]);
+    app.add_action(&open_notes);
+    app.set_accels_for_action("app.notes", &["<Control>J"]);
 
-    let prev_ayah = gio::SimpleAction::new("prev-ayah", None);
-    prev_ayah.connect_activate(clone!(@strong state, @strong refresh => move |_, _| {
-        let mut st = state.borrow_mut();
-        st.current.ayah_index = st.current.ayah_index.saturating_sub(1).max(1);
-        persist(&st);
-        refresh();
+    let go_to = gio::SimpleAction::new("go-to", None);
+    go_to.connect_activate(clone!(@strong state, @strong window, @strong surah_combo, @strong ayah_spin => move |_, _| {
+        let surahs = state.borrow().surahs.clone();
+        let on_go = clone!(@strong state, @strong surah_combo, @strong ayah_spin => move |r: &AyahRef| jump(&state, &surah_combo, &ayah_spin, r));
+        show_goto_dialog(&window, surahs, on_go);
     }));
-    app.add_action(&prev_ayah);
-    app.set_accels_for_action("app.prev-ayah", &["<Alt>Left"]);
+    app.add_action(&go_to);
+    app.set_accels_for_action("app.go-to", &["<Control>G"]);
 
-    let toggle_t = gio::SimpleAction::new("toggle-translation", None);
-    toggle_t.connect_activate(clone!(@strong state, @strong refresh => move |_, _| {
-        let mut st = state.borrow_mut();
-        st.translation_lang = if st.translation_lang.is_some() { None } else { Some("en".to_string()) };
-        persist(&st);
+    // For launches of a second instance and opened links; the id is
+    // `surah * 1000 + ayah`.
+    let show_ayah = gio::SimpleAction::new("show-ayah", Some(glib::VariantTy::INT64));
+    show_ayah.connect_activate(clone!(@strong state, @strong surah_combo, @strong ayah_spin => move |_, param| {
+        let Some(id) = param.and_then(|p| p.get::<i64>()) else { return };
+        jump(&state, &surah_combo, &ayah_spin, &AyahRef { surah_id: (id / 1000) as u16, ayah_index: (id % 1000) as u16 });
+    }));
+    app.add_action(&show_ayah);
+
+    let show_translation = gio::SimpleAction::new("show-translation", Some(glib::VariantTy::STRING));
+    show_translation.connect_activate(clone!(@strong state, @strong toggle_translation, @strong lang_combo, @strong refresh => move |_, param| {
+        let Some(lang) = param.and_then(|p| p.get::<String>()) else { return };
+        toggle_translation.set_active(true);
+        lang_combo.set_active_id(Some(&lang));
+        // The toggle falls back to English and the combo box only reports changes.
+        {
+            let mut st = state.borrow_mut();
+            st.translation_lang = Some(lang);
+            persist(&st);
+        }
         refresh();
     }));
-    app.add_action(&toggle_t);
-    app.set_accels_for_action("app.toggle-translation", &["T"]);
+    app.add_action(&show_translation);
 
-    let focus_search = gio::SimpleAction::new("focus-search", None);
-    focus_search.connect_activate(move |_, _| {
-        search_entry.grab_focus();
-    });
-    app.add_action(&focus_search);
-    app.set_accels_for_action("app.focus-search", &["<Control>F"]);
+    let show_tafsir = gio::SimpleAction::new("show-tafsir", Some(glib::VariantTy::INT64));
+    show_tafsir.connect_activate(clone!(@strong tafsir_combo, @strong tafsir_toggle => move |_, param| {
+        let Some(id) = param.and_then(|p| p.get::<i64>()) else { return };
+        if tafsir_combo.set_active_id(Some(&id.to_string())) {
+            tafsir_toggle.set_active(true);
+        } else {
+            tracing::warn!("no tafsir with id {}", id);
+        }
+    }));
+    app.add_action(&show_tafsir);
 
-    let next_result = gtk4::gio::SimpleAction::new("next-result", None);
-    next_result.connect_activate(clone!(@strong state, @strong refresh => move |_, _| {
-        let mut st = state.borrow_mut();
-        if let Some(&i) = st.search_results.iter().find(|&&i| i > st.current.ayah_index) {
-            st.current.ayah_index = i;
-            persist(&st);
-            refresh();
+    let copy_ayah = gio::SimpleAction::new("copy-ayah", None);
+    copy_ayah.connect_activate(clone!(@strong state, @strong window => move |_, _| {
+        let st = state.borrow().clone();
+        let range = AyahRange::single(st.current.clone());
+        match passage_text(&st, &st.copy, &range) {
+            Ok(text) => copy_to_clipboard(&window, &text, st.copy.format),
+            Err(e) => tracing::warn!("copying {}: {:#}", range, e),
         }
     }));
-    app.add_action(&next_result);
-    app.set_accels_for_action("app.next-result", &["Return"]);
+    app.add_action(&copy_ayah);
+    app.set_accels_for_action("app.copy-ayah", &["<Control><Shift>C"]);
 
-    let prev_result = gio::SimpleAction::new("prev-result", None);
-    prev_result.connect_activate(clone!(@strong state, @strong refresh => move |_, _| {
-        let mut st = state.borrow_mut();
-        if let Some(&i) = st.search_results.iter().rev().find(|&&i| i < st.current.ayah_index) {
-            st.current.ayah_index = i;
-            persist(&st);
-            refresh();
+    let copy_range = gio::SimpleAction::new("copy-range", None);
+    copy_range.connect_activate(clone!(@strong state, @strong window => move |_, _| show_copy_dialog(&window, state.clone())));
+    app.add_action(&copy_range);
+
+    let export = gio::SimpleAction::new("export", None);
+    export.connect_activate(clone!(@strong state, @strong window => move |_, _| show_export_dialog(&window, state.clone())));
+    app.add_action(&export);
+    app.set_accels_for_action("app.export", &["<Control>E"]);
+
+    // Id 0 is the current ayah.
+    let verse_image = gio::SimpleAction::new("verse-image", Some(glib::VariantTy::INT64));
+    verse_image.connect_activate(clone!(@strong state, @strong window => move |_, param| {
+        let id = param.and_then(|p| p.get::<i64>()).unwrap_or(0);
+        let at = match id {
+            0 => state.borrow().current.clone(),
+            id => AyahRef { surah_id: (id / 1000) as u16, ayah_index: (id % 1000) as u16 },
+        };
+        show_card_dialog(&window, state.clone(), at);
+    }));
+    app.add_action(&verse_image);
+
+    let go_back = gio::SimpleAction::new("back", None);
+    go_back.connect_activate(clone!(@strong state, @strong surah_combo, @strong ayah_spin, @strong replaying => move |_, _| {
+        let to = {
+            let mut st = state.borrow_mut();
+            let current = st.current.clone();
+            st.history.back(current)
+        };
+        if let Some(to) = to {
+            replaying.set(true);
+            goto(&surah_combo, &ayah_spin, &to);
+            replaying.set(false);
         }
     }));
-    app.add_action(&prev_result);
-    app.set_accels_for_action("app.prev-result", &["<Shift>Return"]);
+    app.add_action(&go_back);
+    app.set_accels_for_action("app.back", &["<Alt>Left", "Back"]);
 
-    let bookmark = gio::SimpleAction::new("bookmark", None);
-    bookmark.connect_activate(clone!(@strong state, @strong list => move |_, _| {
-        let st = state.borrow();
-        let _ = storage::add_bookmark(st.current.clone());
-        // Append to UI list
-        let row = gtk::ListBoxRow::new();
-        let label = gtk::Label::new(Some(&format!("{}:{}", st.current.surah_id, st.current.ayah_index)));
-        row.set_child(Some(&label));
-        list.append(&row);
+    let go_forward = gio::SimpleAction::new("forward", None);
+    go_forward.connect_activate(clone!(@strong state, @strong surah_combo, @strong ayah_spin, @strong replaying => move |_, _| {
+        let to = {
+            let mut st = state.borrow_mut();
+            let current = st.current.clone();
+            st.history.forward(current)
+        };
+        if let Some(to) = to {
+            replaying.set(true);
+            goto(&surah_combo, &ayah_spin, &to);
+            replaying.set(false);
+        }
     }));
-    app.add_action(&bookmark);
-    app.set_accels_for_action("app.bookmark", &["B"]);
+    app.add_action(&go_forward);
+    app.set_accels_for_action("app.forward", &["<Alt>Right", "Forward"]);
 
-    // Load bookmarks into list
-    if let Some(p) = storage::load() {
-        for b in p.bookmarks {
+    // Mouse back and forward buttons.
+    let history_click = gtk::GestureClick::new();
+    history_click.set_button(0);
+    history_click.connect_pressed(clone!(@strong app => move |g, _, _, _| {
+        match g.current_button() {
+            8 => app.activate_action("back", None),
+            9 => app.activate_action("forward", None),
+            _ => {}
+        }
+    }));
+    window.add_controller(history_click);
+
+    history_popover.connect_show(clone!(@strong state, @strong recent_list, @strong back_button, @strong forward_button => move |_| {
+        let st = state.borrow();
+        back_button.set_sensitive(!st.history.back.is_empty());
+        forward_button.set_sensitive(!st.history.forward.is_empty());
+        while let Some(child) = recent_list.first_child() {
+            recent_list.remove(&child);
+        }
+        for r in &st.history.recent {
+            let label = gtk::Label::new(Some(&format!("{} {}:{}", surah_name(&st.surahs, r.surah_id), r.surah_id, r.ayah_index)));
+            label.set_xalign(0.0);
             let row = gtk::ListBoxRow::new();
-            let label = gtk::Label::new(Some(&format!("{}:{}", b.surah_id, b.ayah_index)));
+            row.set_widget_name(&format!("{}:{}", r.surah_id, r.ayah_index));
             row.set_child(Some(&label));
-            list.append(&row);
+            recent_list.append(&row);
         }
-    }
-
-    list.connect_row_activated(clone!(@strong state, @strong refresh => move |_, row| {
-        if let Some(child) = row.child() {
-            if let Ok(lbl) = child.downcast::<gtk::Label>() {
-                let text = lbl.text();
-                let parts: Vec<_> = text.split(':').collect();
-                if parts.len() == 2 { if let (Ok(s), Ok(a)) = (parts[0].parse::<u16>(), parts[1].parse::<u16>()) {
-                    let mut st = state.borrow_mut();
-                    st.current = AyahRef { surah_id: s, ayah_index: a };
-                    persist(&st);
-                    refresh();
-                }}
-            }
+    }));
+    recent_list.connect_row_activated(clone!(@strong state, @strong surah_combo, @strong ayah_spin, @strong history_popover => move |_, row| {
+        if let Some(r) = parse_row_ref(&row.widget_name()) {
+            history_popover.popdown();
+            jump(&state, &surah_combo, &ayah_spin, &r);
         }
-
     }));
 
+    if let Ok(s) = data::load_surah_text(1) {
+        let mut st = state.borrow_mut();
+        st.set_ayat(s.ayat);
+    }
+    if let Ok(list) = data::get_available_transliterations() {
+        let mut st = state.borrow_mut();
+        st.transliteration_lang = list.into_iter().next().map(|(lang, _)| lang);
+    }
+    let restored = storage::load();
+    if let Some(p) = &restored {
+        let mut st = state.borrow_mut();
+        st.translation_lang = p.translation_lang.clone();
+        st.compare_langs = p.compare_langs.clone();
+        st.translation_layout = p.translation_layout;
+        st.prefer_dark = p.prefer_dark;
+        st.follow_system_scheme = p.follow_system_scheme;
+        st.tafsir_id = p.tafsir_id;
+        st.show_tafsir = p.show_tafsir;
+        st.show_transliteration = p.show_transliteration;
+        st.transliteration_scheme = p.transliteration_scheme;
+        st.show_tajweed = p.show_tajweed;
+        st.focus_mode = p.focus_mode;
+        st.mushaf_mode = p.mushaf_mode;
+        st.fonts = p.fonts.clone();
+        st.theme = p.theme;
+        st.opacity = p.opacity.unwrap_or(theme::DEFAULT_OPACITY);
+        st.history = p.history.clone();
+        st.copy = p.copy.clone();
+        st.card = p.card.clone();
+    }
+    let (translation_lang, compare_langs, translation_layout, prefer_dark, follow_system_scheme, tafsir_id, show_tafsir, show_transliteration, show_tajweed, focus_mode, mushaf_mode, view) = {
+        let st = state.borrow();
+        apply_dark(&st, system_scheme.get());
+        (st.translation_lang.clone(), st.compare_langs.clone(), st.translation_layout, st.prefer_dark, st.follow_system_scheme, st.tafsir_id, st.show_tafsir, st.show_transliteration, st.show_tajweed, st.focus_mode, st.mushaf_mode, view_name(&st))
+    };
+    dark_toggle.set_active(prefer_dark);
+    follow_check.set_active(follow_system_scheme);
+    if let Some(lang) = &translation_lang {
+        toggle_translation.set_active(true);
+        // Set the active ID in the combo box
+        lang_combo.set_active_id(Some(lang));
+    }
+    for (lang, check) in &compare_checks {
+        check.set_active(compare_langs.contains(lang));
+    }
+    compare_list.invalidate_sort();
+    columns_check.set_active(translation_layout == TranslationLayout::Columns);
+    let (fonts, current_theme, opacity) = {
+        let st = state.borrow();
+        (st.fonts.clone(), st.theme, st.opacity)
+    };
+    theme_combo.set_active_id(Some(current_theme.id()));
+    opacity_scale.set_value(opacity);
+    sync_font_buttons(&arabic_font_button, &translation_font_button, &fonts);
+    focus_toggle.set_active(focus_mode);
+    mushaf_toggle.set_active(mushaf_mode);
+    view_stack.set_visible_child_name(view);
+    tajweed_toggle.set_active(show_tajweed);
+    tajweed_legend.set_visible(show_tajweed);
+    tafsir_toggle.set_active(show_tafsir);
+    tafsir_revealer.set_reveal_child(show_tafsir);
+    transliteration_toggle.set_active(show_transliteration);
+    transliteration_area.set_visible(show_transliteration);
+    match tafsir_id {
+        Some(id) => { tafsir_combo.set_active_id(Some(&id.to_string())); }
+        None => tafsir_combo.set_active(Some(0)),
+    }
+    let start = init.or(restored.map(|p| p.last)).unwrap_or_default();
+    replaying.set(true);
+    goto(&surah_combo, &ayah_spin, &start);
+    replaying.set(false);
+    refresh();
+    window.present();
+    Ok(())
+}
+
+const SPREAD_MIN_WIDTH: i32 = 1400;
+
+fn view_name(st: &AppState) -> &'static str {
+    if st.mushaf_mode {
+        "page"
+    } else if st.focus_mode {
+        "focus"
+    } else {
+        "surah"
+    }
+}
+
+fn goto(surah_combo: &gtk::ComboBoxText, ayah_spin: &gtk::SpinButton, r: &AyahRef) {
+    let idx = r.surah_id.saturating_sub(1) as u32;
+    if surah_combo.active() != Some(idx) {
+        surah_combo.set_active(Some(idx));
+    }
+    ayah_spin.set_value(r.ayah_index as f64);
+}
+
+// Like `goto`, but remembers the position being left for Back.
+fn jump(state: &Rc<RefCell<AppState>>, surah_combo: &gtk::ComboBoxText, ayah_spin: &gtk::SpinButton, r: &AyahRef) {
+    {
+        let mut st = state.borrow_mut();
+        if &st.current != r {
+            let from = st.current.clone();
+            st.history.push(from);
+        }
+    }
+    goto(surah_combo, ayah_spin, r);
+}
+
+// Guards the surah view against feedback between programmatic scrolling
+// and the scroll-position tracking of `AppState::current`.
+#[derive(Clone, Default)]
+struct ScrollSync {
+    syncing: Rc<Cell<bool>>,
+    held: Rc<Cell<u32>>,
+}
+
+impl ScrollSync {
+    fn is_syncing(&self) -> bool {
+        self.syncing.get() || self.held.get() > 0
+    }
+
+    // Ignore scroll events while a programmatic scroll settles.
+    fn hold(&self) {
+        self.held.set(self.held.get() + 1);
+        let held = self.held.clone();
+        glib::timeout_add_local_once(std::time::Duration::from_millis(300), move || held.set(held.get() - 1));
+    }
+}
+
+fn top_ayah(list: &gtk::ListView) -> Option<u16> {
+    let mut w = list.pick(list.width() as f64 / 2.0, 4.0, gtk::PickFlags::DEFAULT);
+    while let Some(widget) = w {
+        if let Some(n) = widget.widget_name().strip_prefix("ayah-").and_then(|n| n.parse().ok()) {
+            return Some(n);
+        }
+        w = widget.parent();
+    }
+    None
+}
+
+fn fill_page(container: &gtk::Box, page: Option<&MushafPage>, st: &AppState) {
+    while let Some(child) = container.first_child() {
+        container.remove(&child);
+    }
+    let Some(page) = page else {
+        let empty = gtk::Label::new(Some("No mushaf layout imported. Run tanzil_import --mushaf <file>."));
+        empty.set_wrap(true);
+        container.append(&empty);
+        return;
+    };
+    let surah_name = |id: u16| st.surahs.iter().find(|s| s.id == id).map(|s| s.name_ar.clone()).unwrap_or_default();
+    let header = gtk::CenterBox::new();
+    header.set_direction(gtk::TextDirection::Rtl);
+    header.set_start_widget(Some(&gtk::Label::new(Some(&format!("الجزء {}", arabic_digits(page.juz as u16))))));
+    let names: Vec<String> = page.surahs().into_iter().map(surah_name).collect();
+    header.set_end_widget(Some(&gtk::Label::new(Some(&names.join(" · ")))));
+    header.add_css_class("dim-label");
+    container.append(&header);
+    // Tajweed rules run across words, so each ayah's words on the page are
+    // annotated together, in page order.
+    let words: Vec<&mushaf::Word> = page.lines.iter().filter(|l| matches!(l.kind, LineKind::Ayah)).flat_map(|l| &l.words).collect();
+    let mut spans: Vec<Vec<tajweed::Span>> = Vec::with_capacity(words.len());
+    for ayah in words.chunk_by(|a, b| a.ayah == b.ayah) {
+        let texts: Vec<&str> = ayah.iter().map(|w| w.text.as_str()).collect();
+        match st.show_tajweed {
+            true => spans.extend(tajweed::annotate_words(&texts)),
+            false => spans.extend(texts.iter().map(|_| Vec::new())),
+        }
+    }
+    let mut spans = spans.into_iter();
+    for line in &page.lines {
+        let row = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        row.set_direction(gtk::TextDirection::Rtl);
+        row.set_height_request(40);
+        match line.kind {
+            LineKind::SurahName => {
+                let title = gtk::Label::new(Some(&format!("سورة {}", surah_name(line.surah_id))));
+                title.set_attributes(Some(&arabic_attrs("", false, &st.fonts)));
+                title.set_hexpand(true);
+                title.add_css_class("title-3");
+                row.append(&title);
+            }
+            LineKind::Basmala => {
+                let text = "بِسْمِ ٱللَّهِ ٱلرَّحْمَٰنِ ٱلرَّحِيمِ";
+                let basmala = gtk::Label::new(Some(text));
+                basmala.set_attributes(Some(&arabic_attrs(text, st.show_tajweed, &st.fonts)));
+                basmala.set_hexpand(true);
+                row.append(&basmala);
+            }
+            // Each word takes an equal share of the slack, which justifies the
+            // line across the page width.
+            LineKind::Ayah => {
+                for w in &line.words {
+                    let text = match w.ends_ayah {
+                        true => format!("{} {}", w.text, ayah_marker(w.ayah.ayah_index)),
+                        false => w.text.clone(),
+                    };
+                    let word = gtk::Label::new(Some(&text));
+                    let attrs = arabic_attrs("", false, &st.fonts);
+                    insert_tajweed(&attrs, spans.next().unwrap_or_default());
+                    word.set_attributes(Some(&attrs));
+                    word.set_hexpand(true);
+                    if w.ayah == st.current {
+                        word.add_css_class("accent");
+                    }
+                    row.append(&word);
+                }
+            }
+        }
+        container.append(&row);
+    }
+    let number = gtk::Label::new(Some(&arabic_digits(page.number)));
+    number.add_css_class("dim-label");
+    container.append(&number);
+}
+
+fn translation_markup(text: &str) -> String {
+    footnotes::segments(text)
+        .into_iter()
+        .map(|s| match s {
+            Segment::Text(t) => glib::markup_escape_text(t).to_string(),
+            Segment::Insertion(t) => format!("<span fgalpha=\"70%\">{}</span>", glib::markup_escape_text(t)),
+            Segment::Marker(m) => {
+                let m = glib::markup_escape_text(m);
+                format!("<a href=\"footnote:{}\"><sup>{}</sup></a>", m, m)
+            }
+        })
+        .collect()
+}
+
+// Application-wide CSS: the selected bundled theme plus the user's
+// style.css, which is reloaded whenever it changes on disk.
+struct Themer {
+    theme: gtk::CssProvider,
+    _monitor: Option<gio::FileMonitor>,
+}
+
+impl Themer {
+    fn new(display: &gdk::Display) -> Rc<Self> {
+        let theme = gtk::CssProvider::new();
+        let user = gtk::CssProvider::new();
+        gtk::style_context_add_provider_for_display(display, &theme, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+        gtk::style_context_add_provider_for_display(display, &user, gtk::STYLE_PROVIDER_PRIORITY_USER);
+        user.connect_parsing_error(|_, section, error| {
+            tracing::warn!("style.css:{}: {}", section.start_location().lines() + 1, error);
+        });
+        let path = theme::user_css_path();
+        let monitor = path.as_ref().and_then(|p| gio::File::for_path(p).monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE).ok());
+        if let Some(path) = path {
+            load_user_css(&user, &path);
+            if let Some(monitor) = &monitor {
+                monitor.connect_changed(move |_, _, _, event| {
+                    if matches!(event, gio::FileMonitorEvent::ChangesDoneHint | gio::FileMonitorEvent::Created | gio::FileMonitorEvent::Deleted) {
+                        load_user_css(&user, &path);
+                    }
+                });
+            }
+        }
+        Rc::new(Self { theme, _monitor: monitor })
+    }
+
+    fn set_theme(&self, theme: Theme) {
+        match theme.css() {
+            Ok(css) => self.theme.load_from_data(&css),
+            Err(e) => tracing::warn!("{:#}", e),
+        }
+    }
+}
+
+fn apply_dark(st: &AppState, system: Option<ColorScheme>) {
+    if let Some(settings) = gtk::Settings::default() {
+        settings.set_gtk_application_prefer_dark_theme(theme::use_dark(st.follow_system_scheme, system, st.prefer_dark));
+    }
+}
+
+fn load_user_css(provider: &gtk::CssProvider, path: &std::path::Path) {
+    if path.is_file() {
+        provider.load_from_path(path);
+    } else {
+        provider.load_from_data("");
+    }
+}
+
+// Shared popover showing the body of a clicked footnote marker. It is
+// re-parented onto whichever translation label was clicked.
+#[derive(Clone)]
+struct FootnotePopover {
+    popover: gtk::Popover,
+    label: gtk::Label,
+}
+
+impl FootnotePopover {
+    fn new() -> Self {
+        let label = gtk::Label::new(None);
+        label.set_wrap(true);
+        label.set_max_width_chars(50);
+        label.set_selectable(true);
+        let popover = gtk::Popover::new();
+        popover.set_child(Some(&label));
+        Self { popover, label }
+    }
+
+    fn attach(&self, target: &gtk::Label, notes: Vec<Footnote>) {
+        let this = self.clone();
+        target.connect_activate_link(move |l, uri| match uri.strip_prefix("footnote:") {
+            Some(marker) => {
+                if let Some(n) = notes.iter().find(|n| n.marker == marker) {
+                    this.label.set_text(&n.text);
+                    this.popover.unparent();
+                    this.popover.set_parent(l);
+                    this.popover.popup();
+                }
+                glib::Propagation::Stop
+            }
+            None => glib::Propagation::Proceed,
+        });
+    }
+
+    // Must run before `container`'s labels are dropped, or the popover is
+    // finalized along with its parent.
+    fn release(&self, container: &gtk::Box) {
+        if self.popover.parent().is_some_and(|p| p.is_ancestor(container)) {
+            self.popover.popdown();
+            self.popover.unparent();
+        }
+    }
+}
+
+fn fill_translations(container: &gtk::Box, translations: &[(String, data::TranslationEntry)], layout: TranslationLayout, fonts: &FontSettings, footnote_popover: &FootnotePopover) {
+    footnote_popover.release(container);
+    while let Some(child) = container.first_child() {
+        container.remove(&child);
+    }
+    let columns = layout == TranslationLayout::Columns && translations.len() > 1;
+    container.set_orientation(if columns { gtk::Orientation::Horizontal } else { gtk::Orientation::Vertical });
+    container.set_homogeneous(columns);
+    container.set_visible(!translations.is_empty());
+    for (lang, entry) in translations {
+        let label = gtk::Label::new(None);
+        label.set_wrap(true);
+        label.set_xalign(0.0);
+        label.set_yalign(0.0);
+        label.set_hexpand(true);
+        label.set_direction(if data::is_rtl_language(lang) { gtk::TextDirection::Rtl } else { gtk::TextDirection::Ltr });
+        label.set_tooltip_text(Some(&lang.to_uppercase()));
+        label.set_markup(&translation_markup(&entry.text));
+        label.set_attributes(Some(&translation_attrs(fonts)));
+        footnote_popover.attach(&label, entry.footnotes.clone());
+        container.append(&label);
+    }
+}
+
+fn tafsir_text(entries: &[data::TafsirEntry]) -> String {
+    entries
+        .iter()
+        .map(|e| match e.range.len() {
+            1 => e.text.clone(),
+            _ => format!("{}\n{}", e.range, e.text),
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn arabic_attrs(text: &str, tajweed: bool, fonts: &FontSettings) -> AttrList {
+    highlighted_attrs(text, tajweed, fonts, &[])
+}
+
+// Highlights go in before the tajweed colours so that the rule colours still
+// show on a highlighted word.
+fn highlighted_attrs(text: &str, tajweed: bool, fonts: &FontSettings, notes: &[Note]) -> AttrList {
+    let attrs = AttrList::new();
+    if let Some(family) = fonts.arabic_family.as_deref().or(crate::fonts::default_family()) {
+        attrs.insert(AttrString::new_family(family));
+    }
+    attrs.insert(AttrSize::new((fonts.arabic_size * pango::SCALE as f64) as i32));
+    let ink = pango::Color::parse(notes::HIGHLIGHT_TEXT).ok();
+    for note in notes {
+        let (Some(h), Some((start, end)), Some(ink)) = (note.highlight, note.span(text), ink.as_ref()) else { continue };
+        let Ok(c) = pango::Color::parse(h.colour()) else { continue };
+        for mut attr in [AttrColor::new_background(c.red(), c.green(), c.blue()), AttrColor::new_foreground(ink.red(), ink.green(), ink.blue())] {
+            attr.set_start_index(start as u32);
+            attr.set_end_index(end as u32);
+            attrs.insert(attr);
+        }
+    }
+    if tajweed {
+        insert_tajweed(&attrs, tajweed::annotate(text));
+    }
+    attrs
+}
+
+fn insert_tajweed(attrs: &AttrList, spans: Vec<tajweed::Span>) {
+    for span in spans {
+        if let Ok(c) = pango::Color::parse(span.rule.colour()) {
+            let mut attr = AttrColor::new_foreground(c.red(), c.green(), c.blue());
+            attr.set_start_index(span.start as u32);
+            attr.set_end_index(span.end as u32);
+            attrs.insert(attr);
+        }
+    }
+}
+
+fn translation_attrs(fonts: &FontSettings) -> AttrList {
+    let attrs = AttrList::new();
+    if let Some(family) = &fonts.translation_family {
+        attrs.insert(AttrString::new_family(family));
+    }
+    attrs.insert(AttrSize::new((fonts.translation_size * pango::SCALE as f64) as i32));
+    attrs
+}
+
+fn font_description(family: &str, size: f64) -> pango::FontDescription {
+    let mut desc = pango::FontDescription::new();
+    desc.set_family(family);
+    desc.set_size((size * pango::SCALE as f64) as i32);
+    desc
+}
+
+fn sync_font_buttons(arabic: &gtk::FontButton, translation: &gtk::FontButton, fonts: &FontSettings) {
+    let arabic_family = fonts.arabic_family.as_deref().or(crate::fonts::default_family()).unwrap_or("Serif");
+    arabic.set_font_desc(&font_description(arabic_family, fonts.arabic_size));
+    translation.set_font_desc(&font_description(fonts.translation_family.as_deref().unwrap_or("Sans"), fonts.translation_size));
+}
+
+// Family and point size chosen in a font button; sizes given in device
+// units are left unchanged.
+fn chosen_font(button: &gtk::FontButton) -> Option<(String, Option<f64>)> {
+    let desc = button.font_desc()?;
+    let family = desc.family()?.to_string();
+    let size = (!desc.is_size_absolute() && desc.size() > 0).then(|| desc.size() as f64 / pango::SCALE as f64);
+    Some((family, size))
+}
+
+fn add_shortcuts(app: &gtk::Application, state: Rc<RefCell<AppState>>, search_entry: gtk::SearchEntry, surah_combo: gtk::ComboBoxText, ayah_spin: gtk::SpinButton, refresh: impl Fn() + 'static + Clone, list: &gtk::ListBox) {
+    let next_ayah = gio::SimpleAction::new("next-ayah", None);
+    next_ayah.connect_activate(clone!(@strong state, @strong ayah_spin => move |_, _| {
+        let (current, count) = {
+            let st = state.borrow();
+            let count = st.surahs.iter().find(|s| s.id == st.current.surah_id).map(|s| s.ayah_count).unwrap_or(1);
+            (st.current.ayah_index, count)
+        };
+        ayah_spin.set_value((current + 1).min(count) as f64);
+    }));
+    app.add_action(&next_ayah);
+
+    let prev_ayah = gio::SimpleAction::new("prev-ayah", None);
+    prev_ayah.connect_activate(clone!(@strong state, @strong ayah_spin => move |_, _| {
+        let current = state.borrow().current.ayah_index;
+        ayah_spin.set_value(current.saturating_sub(1).max(1) as f64);
+    }));
+    app.add_action(&prev_ayah);
+
+    let toggle_t = gio::SimpleAction::new("toggle-translation", None);
+    toggle_t.connect_activate(clone!(@strong state, @strong refresh => move |_, _| {
+        {
+            let mut st = state.borrow_mut();
+            st.translation_lang = if st.translation_lang.is_some() { None } else { Some("en".to_string()) };
+            persist(&st);
+        }
+        refresh();
+    }));
+    app.add_action(&toggle_t);
+    app.set_accels_for_action("app.toggle-translation", &["T"]);
+
+    let focus_search = gio::SimpleAction::new("focus-search", None);
+    focus_search.connect_activate(move |_, _| {
+        search_entry.grab_focus();
+    });
+    app.add_action(&focus_search);
+    app.set_accels_for_action("app.focus-search", &["<Control>F"]);
+
+    let next_result = gtk4::gio::SimpleAction::new("next-result", None);
+    next_result.connect_activate(clone!(@strong state, @strong surah_combo, @strong ayah_spin => move |_, _| {
+        let next = {
+            let st = state.borrow();
+            st.search_results.iter().find(|&&i| i > st.current.ayah_index).map(|&i| AyahRef { surah_id: st.current.surah_id, ayah_index: i })
+        };
+        if let Some(r) = next {
+            jump(&state, &surah_combo, &ayah_spin, &r);
+        }
+    }));
+    app.add_action(&next_result);
+    app.set_accels_for_action("app.next-result", &["Return"]);
+
+    let prev_result = gio::SimpleAction::new("prev-result", None);
+    prev_result.connect_activate(clone!(@strong state, @strong surah_combo, @strong ayah_spin => move |_, _| {
+        let prev = {
+            let st = state.borrow();
+            st.search_results.iter().rev().find(|&&i| i < st.current.ayah_index).map(|&i| AyahRef { surah_id: st.current.surah_id, ayah_index: i })
+        };
+        if let Some(r) = prev {
+            jump(&state, &surah_combo, &ayah_spin, &r);
+        }
+    }));
+    app.add_action(&prev_result);
+    app.set_accels_for_action("app.prev-result", &["<Shift>Return"]);
+
+    let bookmark = gio::SimpleAction::new("bookmark", None);
+    bookmark.connect_activate(clone!(@strong state, @strong list => move |_, _| {
+        let st = state.borrow();
+        let _ = storage::add_bookmark(st.current.clone());
+        fill_bookmark_list(&list, &st.surahs);
+    }));
+    app.add_action(&bookmark);
+    app.set_accels_for_action("app.bookmark", &["B"]);
+
+    fill_bookmark_list(list, &state.borrow().surahs);
+
+    list.connect_row_activated(clone!(@strong state, @strong surah_combo, @strong ayah_spin => move |_, row| {
+        if let Some(r) = parse_row_ref(&row.widget_name()) {
+            jump(&state, &surah_combo, &ayah_spin, &r);
+        }
+    }));
+
+}
+
+// Rows are named "s:a" so activation does not depend on the label text.
+fn parse_row_ref(name: &str) -> Option<AyahRef> {
+    let (s, a) = name.split_once(':')?;
+    Some(AyahRef { surah_id: s.parse().ok()?, ayah_index: a.parse().ok()? })
+}
+
+fn surah_name(surahs: &[Surah], id: u16) -> String {
+    surahs.iter().find(|s| s.id == id).map(|s| s.name_en.clone()).unwrap_or_else(|| format!("Surah {}", id))
+}
+
+fn fill_bookmark_list(list: &gtk::ListBox, surahs: &[Surah]) {
+    while let Some(child) = list.first_child() {
+        list.remove(&child);
+    }
+    for b in storage::load().map(|p| p.bookmarks).unwrap_or_default() {
+        let mut text = format!("{} {}:{}", surah_name(surahs, b.ayah.surah_id), b.ayah.surah_id, b.ayah.ayah_index);
+        if let Some(line) = b.note.lines().next().filter(|l| !l.is_empty()) {
+            text.push_str(" — ");
+            text.push_str(line);
+        }
+        let label = gtk::Label::new(Some(&text));
+        label.set_xalign(0.0);
+        label.set_ellipsize(pango::EllipsizeMode::End);
+        label.set_max_width_chars(40);
+        let row = gtk::ListBoxRow::new();
+        row.set_widget_name(&format!("{}:{}", b.ayah.surah_id, b.ayah.ayah_index));
+        row.set_child(Some(&label));
+        list.append(&row);
+    }
+}
+
+// Window for browsing, filtering, editing, deleting and reordering
+// bookmarks. The main window adds bookmarks too, so every change is applied
+// to what is stored and written back straight away.
+struct BookmarkManager {
+    surahs: Vec<Surah>,
+    bookmarks: RefCell<Vec<Bookmark>>,
+    collections: RefCell<Vec<String>>,
+    ayat: RefCell<std::collections::HashMap<u16, Vec<String>>>,
+    selected: RefCell<Option<AyahRef>>,
+    updating: Cell<bool>,
+    list: gtk::ListBox,
+    search: gtk::SearchEntry,
+    tag_filter: gtk::ComboBoxText,
+    collection_filter: gtk::ComboBoxText,
+    editor: gtk::Box,
+    heading: gtk::Label,
+    note: gtk::TextView,
+    tags: gtk::Entry,
+    collection: gtk::ComboBoxText,
+    window: gtk::Window,
+    status: gtk::Label,
+    import_dialog: RefCell<Option<gtk::FileChooserNative>>,
+    on_goto: Box<dyn Fn(&AyahRef)>,
+    on_change: Box<dyn Fn()>,
+}
+
+impl BookmarkManager {
+    fn open(parent: &gtk::ApplicationWindow, surahs: Vec<Surah>, on_goto: impl Fn(&AyahRef) + 'static, on_change: impl Fn() + 'static) -> Rc<Self> {
+        let stored = storage::load().unwrap_or_default();
+        let window = gtk::Window::builder()
+            .title("Bookmarks")
+            .transient_for(parent)
+            .default_width(860)
+            .default_height(560)
+            .build();
+
+        let search = gtk::SearchEntry::new();
+        search.set_hexpand(true);
+        let tag_filter = gtk::ComboBoxText::new();
+        let collection_filter = gtk::ComboBoxText::new();
+        let filters = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        filters.append(&search);
+        filters.append(&tag_filter);
+        filters.append(&collection_filter);
+        let import = gtk::Button::with_label("Import…");
+        import.set_tooltip_text(Some("Add bookmarks from a text file with one reference per line"));
+        filters.append(&import);
+        let status = gtk::Label::new(None);
+        status.set_xalign(0.0);
+        status.set_wrap(true);
+        status.add_css_class("dim-label");
+        status.set_visible(false);
+
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::Single);
+        let list_area = gtk::ScrolledWindow::builder().hexpand(true).vexpand(true).child(&list).build();
+
+        let heading = gtk::Label::new(None);
+        heading.set_xalign(0.0);
+        heading.add_css_class("title-4");
+        let note = gtk::TextView::new();
+        note.set_wrap_mode(gtk::WrapMode::WordChar);
+        let note_area = gtk::ScrolledWindow::builder().vexpand(true).min_content_height(160).child(&note).build();
+        let tags = gtk::Entry::new();
+        tags.set_placeholder_text(Some("Tags, separated by commas"));
+        let collection = gtk::ComboBoxText::with_entry();
+        if let Some(entry) = collection.child().and_downcast::<gtk::Entry>() {
+            entry.set_placeholder_text(Some("Collection"));
+        }
+        let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        let save = gtk::Button::with_label("Save");
+        save.add_css_class("suggested-action");
+        let up = gtk::Button::from_icon_name("go-up-symbolic");
+        let down = gtk::Button::from_icon_name("go-down-symbolic");
+        let delete = gtk::Button::from_icon_name("user-trash-symbolic");
+        delete.add_css_class("destructive-action");
+        let open_ayah = gtk::Button::with_label("Go to");
+        for b in [&save, &open_ayah, &up, &down, &delete] {
+            buttons.append(b);
+        }
+        let editor = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        editor.set_width_request(320);
+        editor.append(&heading);
+        editor.append(&gtk::Label::new(Some("Note")));
+        editor.append(&note_area);
+        editor.append(&tags);
+        editor.append(&collection);
+        editor.append(&buttons);
+        editor.set_sensitive(false);
+
+        let body = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        body.append(&list_area);
+        body.append(&editor);
+        let root = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        root.set_margin_top(12);
+        root.set_margin_bottom(12);
+        root.set_margin_start(12);
+        root.set_margin_end(12);
+        root.append(&filters);
+        root.append(&body);
+        root.append(&status);
+        window.set_child(Some(&root));
+
+        let this = Rc::new(Self {
+            surahs,
+            bookmarks: RefCell::new(stored.bookmarks),
+            collections: RefCell::new(stored.collections),
+            ayat: RefCell::new(std::collections::HashMap::new()),
+            selected: RefCell::new(None),
+            updating: Cell::new(false),
+            list,
+            search,
+            tag_filter,
+            collection_filter,
+            editor,
+            heading,
+            note,
+            tags,
+            collection,
+            window: window.clone(),
+            status,
+            import_dialog: RefCell::new(None),
+            on_goto: Box::new(on_goto),
+            on_change: Box::new(on_change),
+        });
+        this.fill_filters();
+        this.fill_list();
+
+        this.search.connect_search_changed(clone!(@weak this => move |_| this.fill_list()));
+        this.tag_filter.connect_changed(clone!(@weak this => move |_| this.fill_list()));
+        this.collection_filter.connect_changed(clone!(@weak this => move |_| this.fill_list()));
+        this.list.connect_row_selected(clone!(@weak this => move |_, row| {
+            if !this.updating.get() {
+                let index = row.and_then(|r| r.widget_name().parse::<usize>().ok());
+                let ayah = index.and_then(|i| this.bookmarks.borrow().get(i).map(|b| b.ayah.clone()));
+                this.select(ayah);
+            }
+        }));
+        this.list.connect_row_activated(clone!(@weak this => move |_, _| this.goto_selected()));
+        save.connect_clicked(clone!(@weak this => move |_| this.save_selected()));
+        open_ayah.connect_clicked(clone!(@weak this => move |_| this.goto_selected()));
+        up.connect_clicked(clone!(@weak this => move |_| this.move_selected(-1)));
+        down.connect_clicked(clone!(@weak this => move |_| this.move_selected(1)));
+        delete.connect_clicked(clone!(@weak this => move |_| this.delete_selected()));
+        import.connect_clicked(clone!(@weak this => move |_| this.choose_import()));
+        window.connect_is_active_notify(clone!(@weak this => move |w| {
+            if w.is_active() {
+                this.reload();
+            }
+        }));
+        window.present();
+        this
+    }
+
+    fn choose_import(self: &Rc<Self>) {
+        let dialog = gtk::FileChooserNative::new(Some("Import bookmarks"), Some(&self.window), gtk::FileChooserAction::Open, Some("Import"), Some("Cancel"));
+        dialog.connect_response(clone!(@weak self as this => move |d, response| {
+            if response == gtk::ResponseType::Accept {
+                if let Some(path) = d.file().and_then(|f| f.path()) {
+                    this.import(&path);
+                }
+            }
+            this.import_dialog.replace(None);
+        }));
+        dialog.show();
+        // Native dialogs are not kept alive by their parent.
+        self.import_dialog.replace(Some(dialog));
+    }
+
+    fn import(&self, path: &std::path::Path) {
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) => {
+                self.status.set_text(&format!("Could not read {}: {}", path.display(), e));
+                self.status.set_visible(true);
+                return;
+            }
+        };
+        let (found, errors) = storage::parse_bookmark_lines(&text);
+        let mut added = 0;
+        if !found.is_empty() {
+            self.edit(|bookmarks, _| {
+                for b in found {
+                    if !bookmarks.iter().any(|x| x.ayah == b.ayah) {
+                        bookmarks.push(b);
+                        added += 1;
+                    }
+                }
+            });
+        }
+        let mut message = format!("Imported {} bookmark{}.", added, if added == 1 { "" } else { "s" });
+        for (line, error) in errors.iter().take(5) {
+            message.push_str(&format!("\nLine {}: {}", line, error));
+        }
+        if errors.len() > 5 {
+            message.push_str(&format!("\n…and {} more lines that could not be read.", errors.len() - 5));
+        }
+        self.status.set_text(&message);
+        self.status.set_visible(true);
+    }
+
+    fn filter(&self) -> BookmarkFilter {
+        let id = |c: &gtk::ComboBoxText| c.active_id().map(|s| s.to_string()).filter(|s| !s.is_empty());
+        BookmarkFilter { text: self.search.text().to_string(), tag: id(&self.tag_filter), collection: id(&self.collection_filter) }
+    }
+
+    fn preview(&self, r: &AyahRef) -> String {
+        let mut ayat = self.ayat.borrow_mut();
+        let text = ayat
+            .entry(r.surah_id)
+            .or_insert_with(|| data::load_surah_text(r.surah_id).map(|sf| sf.ayat).unwrap_or_default());
+        text.get(r.ayah_index.saturating_sub(1) as usize).cloned().unwrap_or_default()
+    }
+
+    // Combo changes re-enter `fill_list`, so repopulating is guarded.
+    fn fill_filters(&self) {
+        self.updating.set(true);
+        let bookmarks = self.bookmarks.borrow();
+        let tag = self.tag_filter.active_id();
+        self.tag_filter.remove_all();
+        self.tag_filter.append(Some(""), "All tags");
+        for t in storage::all_tags(&bookmarks) {
+            self.tag_filter.append(Some(&t), &format!("#{}", t));
+        }
+        if tag.is_none() || !self.tag_filter.set_active_id(tag.as_deref()) {
+            self.tag_filter.set_active_id(Some(""));
+        }
+        let collection = self.collection_filter.active_id();
+        self.collection_filter.remove_all();
+        self.collection_filter.append(Some(""), "All collections");
+        self.collection.remove_all();
+        for c in self.collections.borrow().iter() {
+            self.collection_filter.append(Some(c), c);
+            self.collection.append(Some(c), c);
+        }
+        if collection.is_none() || !self.collection_filter.set_active_id(collection.as_deref()) {
+            self.collection_filter.set_active_id(Some(""));
+        }
+        self.updating.set(false);
+    }
+
+    fn fill_list(&self) {
+        if self.updating.get() {
+            return;
+        }
+        self.updating.set(true);
+        while let Some(child) = self.list.first_child() {
+            self.list.remove(&child);
+        }
+        let filter = self.filter();
+        let bookmarks = self.bookmarks.borrow().clone();
+        for (i, b) in bookmarks.iter().enumerate() {
+            let name = surah_name(&self.surahs, b.ayah.surah_id);
+            if !filter.matches(b, &name) {
+                continue;
+            }
+            let row = gtk::ListBoxRow::new();
+            row.set_widget_name(&i.to_string());
+            row.set_child(Some(&self.row_content(b, &name)));
+            self.list.append(&row);
+            if self.selected.borrow().as_ref() == Some(&b.ayah) {
+                self.list.select_row(Some(&row));
+            }
+        }
+        self.updating.set(false);
+    }
+
+    fn row_content(&self, b: &Bookmark, name: &str) -> gtk::Box {
+        let mut title = format!("<b>{}</b> {}:{}", glib::markup_escape_text(name), b.ayah.surah_id, b.ayah.ayah_index);
+        if let Some(c) = &b.collection {
+            title.push_str(&format!(" · {}", glib::markup_escape_text(c)));
+        }
+        let heading = gtk::Label::new(None);
+        heading.set_markup(&title);
+        heading.set_xalign(0.0);
+        let preview = gtk::Label::new(Some(&self.preview(&b.ayah)));
+        preview.set_direction(gtk::TextDirection::Rtl);
+        preview.set_xalign(0.0);
+        preview.set_ellipsize(pango::EllipsizeMode::End);
+        let mut meta: Vec<String> = Vec::new();
+        if let Some(date) = glib::DateTime::from_unix_local(b.created as i64).ok().and_then(|d| d.format("%Y-%m-%d").ok()) {
+            meta.push(date.to_string());
+        }
+        meta.extend(b.tags.iter().map(|t| format!("#{}", t)));
+        if let Some(line) = b.note.lines().next().filter(|l| !l.is_empty()) {
+            meta.push(line.to_string());
+        }
+        let details = gtk::Label::new(Some(&meta.join("  ")));
+        details.set_xalign(0.0);
+        details.set_ellipsize(pango::EllipsizeMode::End);
+        details.add_css_class("dim-label");
+        let content = gtk::Box::new(gtk::Orientation::Vertical, 2);
+        content.set_margin_top(6);
+        content.set_margin_bottom(6);
+        content.set_margin_start(6);
+        content.set_margin_end(6);
+        content.append(&heading);
+        content.append(&preview);
+        content.append(&details);
+        content
+    }
+
+    fn select(&self, ayah: Option<AyahRef>) {
+        let b = ayah.as_ref().and_then(|a| self.bookmarks.borrow().iter().find(|b| &b.ayah == a).cloned());
+        self.selected.replace(ayah);
+        self.editor.set_sensitive(b.is_some());
+        let Some(b) = b else {
+            self.heading.set_text("");
+            self.note.buffer().set_text("");
+            self.tags.set_text("");
+            return;
+        };
+        self.heading.set_text(&format!("{} {}:{}", surah_name(&self.surahs, b.ayah.surah_id), b.ayah.surah_id, b.ayah.ayah_index));
+        self.note.buffer().set_text(&b.note);
+        self.tags.set_text(&b.tags.join(", "));
+        match &b.collection {
+            Some(c) => {
+                if let Some(entry) = self.collection.child().and_downcast::<gtk::Entry>() {
+                    entry.set_text(c);
+                }
+            }
+            None => {
+                if let Some(entry) = self.collection.child().and_downcast::<gtk::Entry>() {
+                    entry.set_text("");
+                }
+            }
+        }
+    }
+
+    fn save_selected(&self) {
+        let Some(ayah) = self.selected.borrow().clone() else { return };
+        let buffer = self.note.buffer();
+        let note = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).to_string();
+        let tags = storage::parse_tags(&self.tags.text());
+        let collection = self.collection.active_text().map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+        self.edit(|bookmarks, collections| {
+            if let Some(b) = bookmarks.iter_mut().find(|b| b.ayah == ayah) {
+                b.note = note;
+                b.tags = tags;
+                b.collection = collection.clone();
+            }
+            if let Some(c) = collection {
+                if !collections.contains(&c) {
+                    collections.push(c);
+                }
+            }
+        });
+    }
+
+    fn move_selected(&self, delta: isize) {
+        let Some(ayah) = self.selected.borrow().clone() else { return };
+        self.edit(|bookmarks, _| {
+            let Some(i) = bookmarks.iter().position(|b| b.ayah == ayah) else { return };
+            let j = i as isize + delta;
+            if j >= 0 && (j as usize) < bookmarks.len() {
+                bookmarks.swap(i, j as usize);
+            }
+        });
+    }
+
+    fn delete_selected(&self) {
+        let Some(ayah) = self.selected.borrow().clone() else { return };
+        self.select(None);
+        self.edit(|bookmarks, _| bookmarks.retain(|b| b.ayah != ayah));
+    }
+
+    fn goto_selected(&self) {
+        let ayah = self.selected.borrow().clone();
+        if let Some(ayah) = ayah {
+            (self.on_goto)(&ayah);
+        }
+    }
+
+    fn reload(&self) {
+        let stored = storage::load().unwrap_or_default();
+        self.bookmarks.replace(stored.bookmarks);
+        self.collections.replace(stored.collections);
+        self.fill_filters();
+        self.fill_list();
+    }
+
+    fn edit(&self, f: impl FnOnce(&mut Vec<Bookmark>, &mut Vec<String>)) {
+        let mut stored = storage::load().unwrap_or_default();
+        f(&mut stored.bookmarks, &mut stored.collections);
+        if let Err(e) = storage::save_bookmarks(&stored.bookmarks, &stored.collections) {
+            tracing::warn!("saving bookmarks: {:#}", e);
+        }
+        self.bookmarks.replace(stored.bookmarks);
+        self.collections.replace(stored.collections);
+        self.fill_filters();
+        self.fill_list();
+        (self.on_change)();
+    }
+}
+
+fn describe_reference(r: &Reference, surahs: &[Surah]) -> String {
+    match r {
+        Reference::Ayah(a) => format!("{} {}", surah_name(surahs, a.surah_id), r),
+        Reference::Range(range) => format!("{} {} ({} ayat)", surah_name(surahs, range.start.surah_id), r, range.len()),
+        Reference::Surah(s) => format!("{} (surah {})", surah_name(surahs, *s), s),
+        Reference::Juz(_) | Reference::Page(_) => r.to_string(),
+    }
+}
+
+fn show_goto_dialog(parent: &gtk::ApplicationWindow, surahs: Vec<Surah>, on_go: impl Fn(&AyahRef) + 'static) {
+    let window = gtk::Window::builder()
+        .title("Go to")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(420)
+        .build();
+    let entry = gtk::Entry::new();
+    entry.set_placeholder_text(Some("2:255, Al-Baqarah 255, البقرة ٢٥٥, juz 30, page 50"));
+    let feedback = gtk::Label::new(Some("Type a surah and ayah, a range, a juz or a page."));
+    feedback.set_xalign(0.0);
+    feedback.set_wrap(true);
+    feedback.add_css_class("dim-label");
+    let go = gtk::Button::with_label("Go");
+    go.add_css_class("suggested-action");
+    go.set_sensitive(false);
+    go.set_halign(gtk::Align::End);
+    let root = gtk::Box::new(gtk::Orientation::Vertical, 8);
+    root.set_margin_top(12);
+    root.set_margin_bottom(12);
+    root.set_margin_start(12);
+    root.set_margin_end(12);
+    root.append(&entry);
+    root.append(&feedback);
+    root.append(&go);
+    window.set_child(Some(&root));
+
+    entry.connect_changed(clone!(@strong feedback, @strong go => move |e| {
+        let text = e.text();
+        match reference::parse(&text) {
+            Ok(r) => {
+                let found = r.first_ayah().is_some();
+                feedback.set_text(&match found {
+                    true => describe_reference(&r, &surahs),
+                    false => format!("{} needs an imported mushaf layout", r),
+                });
+                feedback.remove_css_class("error");
+                go.set_sensitive(found);
+            }
+            Err(err) => {
+                feedback.set_text(&if text.trim().is_empty() { String::new() } else { format!("{:#}", err) });
+                feedback.add_css_class("error");
+                go.set_sensitive(false);
+            }
+        }
+    }));
+    let submit = Rc::new(clone!(@strong entry, @strong window => move || {
+        if let Some(r) = reference::parse(&entry.text()).ok().and_then(|r| r.first_ayah()) {
+            on_go(&r);
+            window.close();
+        }
+    }));
+    entry.connect_activate(clone!(@strong submit => move |_| submit()));
+    go.connect_clicked(clone!(@strong submit => move |_| submit()));
+    let keys = gtk::EventControllerKey::new();
+    keys.connect_key_pressed(clone!(@strong window => move |_, key, _, _| {
+        if key == gdk::Key::Escape {
+            window.close();
+            return glib::Propagation::Stop;
+        }
+        glib::Propagation::Proceed
+    }));
+    window.add_controller(keys);
+    window.present();
+}
+
+fn passage_text(st: &AppState, settings: &CopySettings, range: &AyahRange) -> Result<String> {
+    let langs = st.translation_langs();
+    let ayat = data::load_ayat_in_range(range, &langs)?;
+    let names = data::get_available_translations().unwrap_or_default();
+    let editions: Vec<(String, String)> = langs
+        .into_iter()
+        .map(|lang| {
+            let name = names.iter().find(|(l, _)| *l == lang).map(|(_, n)| n.clone()).unwrap_or_else(|| lang.clone());
+            (lang, name)
+        })
+        .collect();
+    Ok(citation::format_passage(settings, range, &ayat, &surah_name(&st.surahs, range.start.surah_id), &editions))
+}
+
+fn reference_text(st: &AppState, settings: &CopySettings, input: &str) -> Result<String> {
+    let r = reference::parse(input)?;
+    let range = r.range().ok_or_else(|| anyhow::anyhow!("{} needs an imported mushaf layout", r))?;
+    passage_text(st, settings, &range)
+}
+
+// HTML goes on the clipboard as text/html with the markup as the plain-text
+// fallback, so editors that only take text still get something useful.
+fn copy_to_clipboard(widget: &impl IsA<gtk::Widget>, text: &str, format: CopyFormat) {
+    let clipboard = widget.clipboard();
+    match format {
+        CopyFormat::Html => {
+            let provider = gdk::ContentProvider::new_union(&[
+                gdk::ContentProvider::for_bytes("text/html", &glib::Bytes::from_owned(text.as_bytes().to_vec())),
+                gdk::ContentProvider::for_value(&text.to_value()),
+            ]);
+            let _ = clipboard.set_content(Some(&provider));
+        }
+        CopyFormat::Plain | CopyFormat::Markdown => clipboard.set_text(text),
+    }
+}
+
+fn show_copy_dialog(parent: &gtk::ApplicationWindow, state: Rc<RefCell<AppState>>) {
+    let (current, settings) = {
+        let st = state.borrow();
+        (st.current.clone(), st.copy.clone())
+    };
+    let window = gtk::Window::builder()
+        .title("Copy ayat")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(520)
+        .default_height(480)
+        .build();
+    let entry = gtk::Entry::new();
+    entry.set_text(&format!("{}:{}", current.surah_id, current.ayah_index));
+    entry.set_placeholder_text(Some("2:255-257, Al-Kahf 1-10, juz 30"));
+    let content = gtk::ComboBoxText::new();
+    for c in CopyContent::ALL {
+        content.append(Some(c.id()), c.name());
+    }
+    content.set_active_id(Some(settings.content.id()));
+    let format = gtk::ComboBoxText::new();
+    for f in CopyFormat::ALL {
+        format.append(Some(f.id()), f.name());
+    }
+    format.set_active_id(Some(settings.format.id()));
+    let cite = gtk::CheckButton::with_label("Add citation");
+    cite.set_active(settings.cite);
+    let template = gtk::Entry::new();
+    template.set_text(&settings.template);
+    template.set_tooltip_text(Some("{ref} is the reference, {surah} the surah name and {edition} the translations"));
+    template.set_hexpand(true);
+    template.set_sensitive(settings.cite);
+    let options = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    options.append(&content);
+    options.append(&format);
+    let cite_row = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    cite_row.append(&cite);
+    cite_row.append(&template);
+    let preview = gtk::TextView::new();
+    preview.set_editable(false);
+    preview.set_wrap_mode(gtk::WrapMode::WordChar);
+    let status = gtk::Label::new(None);
+    status.set_xalign(0.0);
+    status.add_css_class("dim-label");
+    let copy = gtk::Button::with_label("Copy");
+    copy.add_css_class("suggested-action");
+    copy.set_halign(gtk::Align::End);
+    let root = gtk::Box::new(gtk::Orientation::Vertical, 8);
+    root.set_margin_top(12);
+    root.set_margin_bottom(12);
+    root.set_margin_start(12);
+    root.set_margin_end(12);
+    root.append(&entry);
+    root.append(&options);
+    root.append(&cite_row);
+    root.append(&gtk::ScrolledWindow::builder().child(&preview).vexpand(true).build());
+    root.append(&status);
+    root.append(&copy);
+    window.set_child(Some(&root));
+
+    let read = clone!(@strong content, @strong format, @strong cite, @strong template => move || CopySettings {
+        content: content.active_id().and_then(|id| CopyContent::from_id(&id)).unwrap_or_default(),
+        format: format.active_id().and_then(|id| CopyFormat::from_id(&id)).unwrap_or_default(),
+        cite: cite.is_active(),
+        template: template.text().to_string(),
+    });
+    // The formatted text, or why there is none.
+    let render = Rc::new(clone!(@strong state, @strong entry => move || {
+        let settings = read();
+        reference_text(&state.borrow(), &settings, &entry.text()).map(|text| (text, settings))
+    }));
+    let update = Rc::new(clone!(@strong render, @strong preview, @strong status, @strong copy, @strong cite, @strong template => move || {
+        template.set_sensitive(cite.is_active());
+        match render() {
+            Ok((text, _)) => {
+                preview.buffer().set_text(&text);
+                status.set_text("");
+                copy.set_sensitive(true);
+            }
+            Err(e) => {
+                preview.buffer().set_text("");
+                status.set_text(&format!("{:#}", e));
+                copy.set_sensitive(false);
+            }
+        }
+    }));
+    entry.connect_changed(clone!(@strong update => move |_| update()));
+    content.connect_changed(clone!(@strong update => move |_| update()));
+    format.connect_changed(clone!(@strong update => move |_| update()));
+    cite.connect_toggled(clone!(@strong update => move |_| update()));
+    template.connect_changed(clone!(@strong update => move |_| update()));
+    update();
+
+    let submit = Rc::new(clone!(@strong window, @strong state => move || {
+        let Ok((text, settings)) = render() else { return };
+        copy_to_clipboard(&window, &text, settings.format);
+        state.borrow_mut().copy = settings;
+        persist(&state.borrow());
+        window.close();
+    }));
+    entry.connect_activate(clone!(@strong submit => move |_| submit()));
+    copy.connect_clicked(clone!(@strong submit => move |_| submit()));
+    let keys = gtk::EventControllerKey::new();
+    keys.connect_key_pressed(clone!(@strong window => move |_, key, _, _| {
+        if key == gdk::Key::Escape {
+            window.close();
+            return glib::Propagation::Stop;
+        }
+        glib::Propagation::Proceed
+    }));
+    window.add_controller(keys);
+    window.present();
+}
+
+fn show_export_dialog(parent: &gtk::ApplicationWindow, state: Rc<RefCell<AppState>>) {
+    let st = state.borrow().clone();
+    let window = gtk::Window::builder()
+        .title("Export")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(440)
+        .build();
+    let entry = gtk::Entry::new();
+    entry.set_text(&surah_name(&st.surahs, st.current.surah_id));
+    entry.set_placeholder_text(Some("Al-Mulk, 2:255-257, juz 30"));
+    let format = gtk::ComboBoxText::new();
+    for f in ExportFormat::ALL {
+        format.append(Some(f.id()), f.name());
+    }
+    format.set_active_id(Some(ExportFormat::Pdf.id()));
+    let tafsir_name = st.tafsir_id.and_then(|id| data::get_available_tafsirs().ok()?.into_iter().find(|t| t.0 == id).map(|t| t.1));
+    let tafsir = gtk::CheckButton::with_label(&match &tafsir_name {
+        Some(name) => format!("Include tafsir ({})", name),
+        None => "Include tafsir (choose one in the tafsir panel first)".to_string(),
+    });
+    tafsir.set_sensitive(tafsir_name.is_some());
+    tafsir.set_active(tafsir_name.is_some() && st.show_tafsir);
+    let langs = st.translation_langs();
+    let editions = gtk::Label::new(Some(&match langs.is_empty() {
+        true => "No translation is shown, so only the Arabic is exported.".to_string(),
+        false => format!("Translations: {}", langs.join(", ")),
+    }));
+    editions.set_xalign(0.0);
+    editions.set_wrap(true);
+    editions.add_css_class("dim-label");
+    let status = gtk::Label::new(None);
+    status.set_xalign(0.0);
+    status.set_wrap(true);
+    let save = gtk::Button::with_label("Export…");
+    save.add_css_class("suggested-action");
+    save.set_halign(gtk::Align::End);
+    let root = gtk::Box::new(gtk::Orientation::Vertical, 8);
+    root.set_margin_top(12);
+    root.set_margin_bottom(12);
+    root.set_margin_start(12);
+    root.set_margin_end(12);
+    root.append(&entry);
+    root.append(&format);
+    root.append(&tafsir);
+    root.append(&editions);
+    root.append(&status);
+    root.append(&save);
+    window.set_child(Some(&root));
+
+    // Native dialogs are not kept alive by their parent.
+    let chooser: Rc<RefCell<Option<gtk::FileChooserNative>>> = Rc::new(RefCell::new(None));
+    let submit = Rc::new(clone!(@strong window, @strong entry, @strong format, @strong tafsir, @strong status, @strong chooser => move || {
+        let range = match reference::parse(&entry.text()).map(|r| r.range()) {
+            Ok(Some(range)) => range,
+            Ok(None) => return status.set_text("That page needs an imported mushaf layout."),
+            Err(e) => return status.set_text(&format!("{:#}", e)),
+        };
+        let format = format.active_id().and_then(|id| ExportFormat::from_id(&id)).unwrap_or(ExportFormat::Pdf);
+        let tafsir_id = st.tafsir_id.filter(|_| tafsir.is_active());
+        let dialog = gtk::FileChooserNative::new(Some("Export"), Some(&window), gtk::FileChooserAction::Save, Some("Export"), Some("Cancel"));
+        dialog.set_current_name(&format!("{}.{}", export::title(&range, &st.surahs), format.id()));
+        let (langs, surahs) = (st.translation_langs(), st.surahs.clone());
+        dialog.connect_response(clone!(@strong window, @strong status, @strong chooser => move |d, response| {
+            if response == gtk::ResponseType::Accept {
+                if let Some(path) = d.file().and_then(|f| f.path()) {
+                    let written = Document::load(&range, &langs, tafsir_id, &surahs).and_then(|doc| export::export(&doc, &path, format));
+                    match written {
+                        Ok(()) => window.close(),
+                        Err(e) => status.set_text(&format!("{:#}", e)),
+                    }
+                }
+            }
+            chooser.replace(None);
+        }));
+        dialog.show();
+        chooser.replace(Some(dialog));
+    }));
+    entry.connect_activate(clone!(@strong submit => move |_| submit()));
+    save.connect_clicked(clone!(@strong submit => move |_| submit()));
+    let keys = gtk::EventControllerKey::new();
+    keys.connect_key_pressed(clone!(@strong window => move |_, key, _, _| {
+        if key == gdk::Key::Escape {
+            window.close();
+            return glib::Propagation::Stop;
+        }
+        glib::Propagation::Proceed
+    }));
+    window.add_controller(keys);
+    window.present();
+}
+
+fn show_card_dialog(parent: &gtk::ApplicationWindow, state: Rc<RefCell<AppState>>, at: AyahRef) {
+    let (options, surahs) = {
+        let st = state.borrow();
+        let mut options = st.card.clone();
+        if options.lang.is_none() {
+            options.lang = st.translation_lang.clone();
+        }
+        (options, st.surahs.clone())
+    };
+    let window = gtk::Window::builder()
+        .title("Share as image")
+        .transient_for(parent)
+        .modal(true)
+        .default_width(560)
+        .default_height(640)
+        .build();
+    let entry = gtk::Entry::new();
+    entry.set_text(&format!("{}:{}", at.surah_id, at.ayah_index));
+    entry.set_placeholder_text(Some("2:255 or a short range such as 112:1-4"));
+    let template = gtk::ComboBoxText::new();
+    for t in CardTemplate::ALL {
+        template.append(Some(t.id()), t.name());
+    }
+    template.set_active_id(Some(options.template.id()));
+    let ratio = gtk::ComboBoxText::new();
+    for r in AspectRatio::ALL {
+        ratio.append(Some(r.id()), r.name());
+    }
+    ratio.set_active_id(Some(options.ratio.id()));
+    let lang = gtk::ComboBoxText::new();
+    lang.append(Some(""), "Arabic only");
+    for (code, name) in data::get_available_translations().unwrap_or_default() {
+        lang.append(Some(&code), &name);
+    }
+    lang.set_active_id(Some(options.lang.as_deref().unwrap_or("")));
+    let controls = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    controls.append(&template);
+    controls.append(&ratio);
+    controls.append(&lang);
+    let preview = gtk::DrawingArea::new();
+    preview.set_vexpand(true);
+    preview.set_hexpand(true);
+    let status = gtk::Label::new(None);
+    status.set_xalign(0.0);
+    status.set_wrap(true);
+    let save = gtk::Button::with_label("Save…");
+    save.add_css_class("suggested-action");
+    save.set_halign(gtk::Align::End);
+    let root = gtk::Box::new(gtk::Orientation::Vertical, 8);
+    root.set_margin_top(12);
+    root.set_margin_bottom(12);
+    root.set_margin_start(12);
+    root.set_margin_end(12);
+    root.append(&entry);
+    root.append(&controls);
+    root.append(&preview);
+    root.append(&status);
+    root.append(&save);
+    window.set_child(Some(&root));
+
+    let read = Rc::new(clone!(@strong template, @strong ratio, @strong lang => move || CardOptions {
+        template: template.active_id().and_then(|id| CardTemplate::from_id(&id)).unwrap_or_default(),
+        ratio: ratio.active_id().and_then(|id| AspectRatio::from_id(&id)).unwrap_or_default(),
+        lang: lang.active_id().map(|id| id.to_string()).filter(|id| !id.is_empty()),
+    }));
+    let current: Rc<RefCell<Option<(AyahRange, Card)>>> = Rc::new(RefCell::new(None));
+    // Scaled down to fit the preview area, centred.
+    preview.set_draw_func(clone!(@strong read, @strong current => move |_, cr, width, height| {
+        let Some((_, card)) = &*current.borrow() else { return };
+        let options = read();
+        let (w, h) = options.ratio.size();
+        let scale = (width as f64 / w as f64).min(height as f64 / h as f64);
+        cr.translate((width as f64 - w as f64 * scale) / 2.0, (height as f64 - h as f64 * scale) / 2.0);
+        cr.scale(scale, scale);
+        if let Err(e) = card::draw(cr, card, &options) {
+            tracing::warn!("drawing card: {:#}", e);
+        }
+    }));
+    let update = Rc::new(clone!(@strong read, @strong current, @strong entry, @strong preview, @strong status, @strong save => move || {
+        let options = read();
+        let loaded = reference::parse(&entry.text()).and_then(|r| {
+            let range = r.range().ok_or_else(|| anyhow::anyhow!("{} needs an imported mushaf layout", r))?;
+            let card = Card::load(&range, options.lang.as_deref(), &surahs)?;
+            Ok((range, card))
+        });
+        match loaded {
+            Ok(loaded) => {
+                current.replace(Some(loaded));
+                status.set_text("");
+            }
+            Err(e) => {
+                current.replace(None);
+                status.set_text(&format!("{:#}", e));
+            }
+        }
+        save.set_sensitive(current.borrow().is_some());
+        preview.queue_draw();
+    }));
+    entry.connect_changed(clone!(@strong update => move |_| update()));
+    lang.connect_changed(clone!(@strong update => move |_| update()));
+    template.connect_changed(clone!(@strong preview => move |_| preview.queue_draw()));
+    ratio.connect_changed(clone!(@strong preview => move |_| preview.queue_draw()));
+    update();
+
+    // Native dialogs are not kept alive by their parent.
+    let chooser: Rc<RefCell<Option<gtk::FileChooserNative>>> = Rc::new(RefCell::new(None));
+    save.connect_clicked(clone!(@strong window, @strong status, @strong chooser, @strong current, @strong read => move |_| {
+        let Some((range, card)) = current.borrow().clone() else { return };
+        let options = read();
+        let dialog = gtk::FileChooserNative::new(Some("Save image"), Some(&window), gtk::FileChooserAction::Save, Some("Save"), Some("Cancel"));
+        dialog.set_current_name(&format!("{}.png", range).replace(':', "-"));
+        dialog.connect_response(clone!(@strong window, @strong status, @strong chooser, @strong state => move |d, response| {
+            if response == gtk::ResponseType::Accept {
+                if let Some(path) = d.file().and_then(|f| f.path()) {
+                    let format = CardFormat::from_path(&path).unwrap_or(CardFormat::Png);
+                    match card::save(&card, &options, &path, format) {
+                        Ok(()) => {
+                            state.borrow_mut().card = options.clone();
+                            persist(&state.borrow());
+                            window.close();
+                        }
+                        Err(e) => status.set_text(&format!("{:#}", e)),
+                    }
+                }
+            }
+            chooser.replace(None);
+        }));
+        dialog.show();
+        chooser.replace(Some(dialog));
+    }));
+    let keys = gtk::EventControllerKey::new();
+    keys.connect_key_pressed(clone!(@strong window => move |_, key, _, _| {
+        if key == gdk::Key::Escape {
+            window.close();
+            return glib::Propagation::Stop;
+        }
+        glib::Propagation::Proceed
+    }));
+    window.add_controller(keys);
+    window.present();
+}
+
+fn format_time(secs: u64, format: &str) -> String {
+    glib::DateTime::from_unix_local(secs as i64)
+        .ok()
+        .and_then(|d| d.format(format).ok())
+        .map(|s| s.to_string())
+        .unwrap_or_default()
+}
+
+fn note_swatch(note: &Note) -> String {
+    note.highlight.map(|h| format!("<span foreground=\"{}\">●</span> ", h.colour())).unwrap_or_default()
+}
+
+// Notes shown under an ayah in the surah view, each with an edit link.
+fn notes_markup(notes: &[Note]) -> String {
+    notes
+        .iter()
+        .map(|n| {
+            let words = n.words.map(|(a, b)| format!("<small>words {}–{}</small> ", a, b)).unwrap_or_default();
+            let body = match n.body.trim() {
+                "" => String::new(),
+                body => format!("{}\n", notes::markdown_markup(body)),
+            };
+            format!("{}{}{}<a href=\"note:{}\">Edit note</a>", note_swatch(n), words, body, n.id)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn show_note_editor(parent: &gtk::ApplicationWindow, note: Note, heading: &str, text: &str, on_saved: impl Fn() + 'static) {
+    let window = gtk::Window::builder()
+        .title(if note.id == 0 { "New note" } else { "Edit note" })
+        .transient_for(parent)
+        .modal(true)
+        .default_width(560)
+        .default_height(560)
+        .build();
+    let title = gtk::Label::new(None);
+    title.set_markup(&format!("<b>{}</b>", glib::markup_escape_text(heading)));
+    title.set_xalign(0.0);
+    let arabic = gtk::Label::new(Some(text));
+    arabic.set_wrap(true);
+    arabic.set_xalign(0.0);
+    arabic.set_direction(gtk::TextDirection::Rtl);
+
+    let highlight = gtk::ComboBoxText::new();
+    highlight.append(Some(""), "No highlight");
+    for h in Highlight::ALL {
+        highlight.append(Some(h.id()), h.name());
+    }
+    highlight.set_active_id(Some(note.highlight.map(|h| h.id()).unwrap_or("")));
+    let word_count = text.split_whitespace().count().max(1) as f64;
+    let whole = gtk::CheckButton::with_label("Whole ayah");
+    let from = gtk::SpinButton::with_range(1.0, word_count, 1.0);
+    let to = gtk::SpinButton::with_range(1.0, word_count, 1.0);
+    let (start, end) = note.words.unwrap_or((1, word_count as u16));
+    from.set_value(start as f64);
+    to.set_value(end as f64);
+    whole.set_active(note.words.is_none());
+    from.set_sensitive(note.words.is_some());
+    to.set_sensitive(note.words.is_some());
+    let range = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    range.append(&highlight);
+    range.append(&whole);
+    range.append(&gtk::Label::new(Some("Words")));
+    range.append(&from);
+    range.append(&gtk::Label::new(Some("to")));
+    range.append(&to);
+
+    let body = gtk::TextView::new();
+    body.set_wrap_mode(gtk::WrapMode::WordChar);
+    body.set_monospace(true);
+    body.buffer().set_text(&note.body);
+    let body_area = gtk::ScrolledWindow::builder().vexpand(true).min_content_height(140).child(&body).build();
+    let preview = gtk::Label::new(None);
+    preview.set_wrap(true);
+    preview.set_xalign(0.0);
+    preview.set_yalign(0.0);
+    let preview_area = gtk::ScrolledWindow::builder().vexpand(true).min_content_height(100).child(&preview).build();
+    let error = gtk::Label::new(None);
+    error.add_css_class("error");
+    error.set_visible(false);
+
+    let buttons = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+    buttons.set_halign(gtk::Align::End);
+    let delete = gtk::Button::with_label("Delete");
+    delete.add_css_class("destructive-action");
+    delete.set_visible(note.id != 0);
+    let cancel = gtk::Button::with_label("Cancel");
+    let save = gtk::Button::with_label("Save");
+    save.add_css_class("suggested-action");
+    buttons.append(&delete);
+    buttons.append(&cancel);
+    buttons.append(&save);
+
+    let root = gtk::Box::new(gtk::Orientation::Vertical, 8);
+    root.set_margin_top(12);
+    root.set_margin_bottom(12);
+    root.set_margin_start(12);
+    root.set_margin_end(12);
+    for w in [title.upcast_ref::<gtk::Widget>(), arabic.upcast_ref(), range.upcast_ref(), body_area.upcast_ref(), preview_area.upcast_ref(), error.upcast_ref(), buttons.upcast_ref()] {
+        root.append(w);
+    }
+    window.set_child(Some(&root));
+
+    let id = note.id;
+    // The note as currently entered in the form.
+    let read = {
+        let (highlight, whole, from, to, body) = (highlight.clone(), whole.clone(), from.clone(), to.clone(), body.clone());
+        move || {
+            let mut n = note.clone();
+            n.highlight = highlight.active_id().and_then(|id| Highlight::from_id(&id));
+            let (a, b) = (from.value() as u16, to.value() as u16);
+            n.words = (!whole.is_active()).then_some((a.min(b), a.max(b)));
+            let buffer = body.buffer();
+            n.body = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false).to_string();
+            n
+        }
+    };
+    let update = {
+        let (read, arabic, preview, text) = (read.clone(), arabic.clone(), preview.clone(), text.to_string());
+        move || {
+            let n = read();
+            arabic.set_attributes(Some(&highlighted_attrs(&text, false, &FontSettings::default(), std::slice::from_ref(&n))));
+            preview.set_markup(&notes::markdown_markup(&n.body));
+        }
+    };
+    update();
+    highlight.connect_changed(clone!(@strong update => move |_| update()));
+    whole.connect_toggled(clone!(@strong update, @strong from, @strong to => move |w| {
+        from.set_sensitive(!w.is_active());
+        to.set_sensitive(!w.is_active());
+        update();
+    }));
+    from.connect_value_changed(clone!(@strong update => move |_| update()));
+    to.connect_value_changed(clone!(@strong update => move |_| update()));
+    body.buffer().connect_changed(clone!(@strong update => move |_| update()));
+
+    let on_saved = Rc::new(on_saved);
+    save.connect_clicked(clone!(@strong window, @strong error, @strong on_saved => move |_| {
+        let mut n = read();
+        match data::save_note(&mut n) {
+            Ok(()) => {
+                on_saved();
+                window.close();
+            }
+            Err(e) => {
+                error.set_text(&format!("Could not save the note: {:#}", e));
+                error.set_visible(true);
+            }
+        }
+    }));
+    delete.connect_clicked(clone!(@strong window, @strong error, @strong on_saved => move |_| {
+        match data::delete_note(id) {
+            Ok(()) => {
+                on_saved();
+                window.close();
+            }
+            Err(e) => {
+                error.set_text(&format!("Could not delete the note: {:#}", e));
+                error.set_visible(true);
+            }
+        }
+    }));
+    cancel.connect_clicked(clone!(@strong window => move |_| window.close()));
+    window.present();
+}
+
+// Searchable list of every note, newest edits last in reading order.
+struct NotesBrowser {
+    surahs: Vec<Surah>,
+    notes: RefCell<Vec<Note>>,
+    list: gtk::ListBox,
+    search: gtk::SearchEntry,
+    colour_filter: gtk::ComboBoxText,
+    window: gtk::Window,
+    on_goto: Box<dyn Fn(&AyahRef)>,
+}
+
+impl NotesBrowser {
+    fn open(parent: &gtk::ApplicationWindow, surahs: Vec<Surah>, on_goto: impl Fn(&AyahRef) + 'static) -> Rc<Self> {
+        let window = gtk::Window::builder()
+            .title("Notes")
+            .transient_for(parent)
+            .default_width(720)
+            .default_height(600)
+            .build();
+        let search = gtk::SearchEntry::new();
+        search.set_hexpand(true);
+        search.set_placeholder_text(Some("Search notes"));
+        let colour_filter = gtk::ComboBoxText::new();
+        colour_filter.append(Some(""), "All highlights");
+        for h in Highlight::ALL {
+            colour_filter.append(Some(h.id()), h.name());
+        }
+        colour_filter.set_active_id(Some(""));
+        let filters = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        filters.append(&search);
+        filters.append(&colour_filter);
+        let list = gtk::ListBox::new();
+        list.set_selection_mode(gtk::SelectionMode::None);
+        list.set_placeholder(Some(&gtk::Label::new(Some("No notes yet. Press Ctrl+N while reading to add one."))));
+        let list_area = gtk::ScrolledWindow::builder().vexpand(true).child(&list).build();
+        let root = gtk::Box::new(gtk::Orientation::Vertical, 8);
+        root.set_margin_top(12);
+        root.set_margin_bottom(12);
+        root.set_margin_start(12);
+        root.set_margin_end(12);
+        root.append(&filters);
+        root.append(&list_area);
+        window.set_child(Some(&root));
+
+        let this = Rc::new(Self {
+            surahs,
+            notes: RefCell::new(Vec::new()),
+            list,
+            search,
+            colour_filter,
+            window: window.clone(),
+            on_goto: Box::new(on_goto),
+        });
+        this.reload();
+        this.search.connect_search_changed(clone!(@weak this => move |_| this.fill_list()));
+        this.colour_filter.connect_changed(clone!(@weak this => move |_| this.fill_list()));
+        this.list.connect_row_activated(clone!(@weak this => move |_, row| {
+            let note = row.widget_name().parse::<usize>().ok().and_then(|i| this.notes.borrow().get(i).cloned());
+            if let Some(n) = note {
+                (this.on_goto)(&n.ayah);
+            }
+        }));
+        // Notes are edited in their own dialog; pick up changes on return.
+        window.connect_is_active_notify(clone!(@weak this => move |w| {
+            if w.is_active() {
+                this.reload();
+            }
+        }));
+        window.present();
+        this
+    }
+
+    fn reload(&self) {
+        match data::load_notes(None) {
+            Ok(notes) => *self.notes.borrow_mut() = notes,
+            Err(e) => tracing::warn!("loading notes: {:#}", e),
+        }
+        self.fill_list();
+    }
+
+    fn fill_list(&self) {
+        while let Some(child) = self.list.first_child() {
+            self.list.remove(&child);
+        }
+        let query = self.search.text();
+        let colour = self.colour_filter.active_id().and_then(|id| Highlight::from_id(&id));
+        for (i, n) in self.notes.borrow().iter().enumerate() {
+            let name = surah_name(&self.surahs, n.ayah.surah_id);
+            if colour.is_some_and(|c| n.highlight != Some(c)) || !n.matches(&query, &name) {
+                continue;
+            }
+            let mut title = format!("{}<b>{}</b> {}:{}", note_swatch(n), glib::markup_escape_text(&name), n.ayah.surah_id, n.ayah.ayah_index);
+            if let Some((a, b)) = n.words {
+                title.push_str(&format!(" · words {}–{}", a, b));
+            }
+            title.push_str(&format!(" · <small>{}</small>", format_time(n.updated, "%Y-%m-%d %H:%M")));
+            let heading = gtk::Label::new(None);
+            heading.set_markup(&title);
+            heading.set_xalign(0.0);
+            heading.set_hexpand(true);
+            let edit = gtk::Button::with_label("Edit");
+            edit.set_action_name(Some("app.edit-note"));
+            edit.set_action_target_value(Some(&n.id.to_variant()));
+            let top = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+            top.append(&heading);
+            top.append(&edit);
+            let body = gtk::Label::new(None);
+            body.set_markup(&notes::markdown_markup(&n.body));
+            body.set_xalign(0.0);
+            body.set_wrap(true);
+            body.set_lines(4);
+            body.set_ellipsize(pango::EllipsizeMode::End);
+            body.set_visible(!n.body.trim().is_empty());
+            let content = gtk::Box::new(gtk::Orientation::Vertical, 4);
+            content.set_margin_top(6);
+            content.set_margin_bottom(6);
+            content.set_margin_start(6);
+            content.set_margin_end(6);
+            content.append(&top);
+            content.append(&body);
+            let row = gtk::ListBoxRow::new();
+            row.set_widget_name(&i.to_string());
+            row.set_child(Some(&content));
+            self.list.append(&row);
+        }
+    }
 }
 
 fn persist(st: &crate::state::AppState) {
     let mut p = storage::load().unwrap_or_default();
     p.last = st.current.clone();
     p.translation_lang = st.translation_lang.clone();
+    p.compare_langs = st.compare_langs.clone();
+    p.translation_layout = st.translation_layout;
     p.prefer_dark = st.prefer_dark;
+    p.follow_system_scheme = st.follow_system_scheme;
+    p.tafsir_id = st.tafsir_id;
+    p.show_tafsir = st.show_tafsir;
+    p.show_transliteration = st.show_transliteration;
+    p.transliteration_scheme = st.transliteration_scheme;
+    p.show_tajweed = st.show_tajweed;
+    p.focus_mode = st.focus_mode;
+    p.mushaf_mode = st.mushaf_mode;
+    p.fonts = st.fonts.clone();
+    p.theme = st.theme;
+    p.opacity = Some(st.opacity);
+    p.history = st.history.clone();
+    p.copy = st.copy.clone();
+    p.card = st.card.clone();
     let _ = storage::save(&p);
 }
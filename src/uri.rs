@@ -0,0 +1,102 @@
+use crate::reference::{self, Reference};
+use anyhow::{bail, Result};
+
+// `quran:` links. The short form takes any reference the parser reads
+// (`quran:2:255`, `quran:2:255-257`, `quran:Yasin%2012`); the long form
+// names its parts (`quran://surah/2/ayah/255`, `quran://juz/30`). Either may
+// end in `?translation=` with a language code or part of an edition name.
+
+pub const SCHEME: &str = "quran";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuranUri {
+    pub reference: Reference,
+    pub translation: Option<String>,
+}
+
+pub fn is_quran_uri(s: &str) -> bool {
+    s.split_once(':').is_some_and(|(scheme, _)| scheme.eq_ignore_ascii_case(SCHEME))
+}
+
+fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b'+', _) => {
+                out.push(b' ');
+                i += 1;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+pub fn parse(uri: &str) -> Result<QuranUri> {
+    let Some((_, rest)) = uri.trim().split_once(':').filter(|_| is_quran_uri(uri.trim())) else {
+        bail!("{} is not a {}: link", uri, SCHEME);
+    };
+    let rest = rest.split('#').next().unwrap_or("");
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let translation = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| matches!(*key, "translation" | "lang"))
+        .map(|(_, value)| decode(value))
+        .filter(|t| !t.is_empty());
+    let text = match path.strip_prefix("//") {
+        Some(path) => {
+            let parts: Vec<String> = path.split('/').filter(|p| !p.is_empty()).map(|p| decode(p).to_lowercase()).collect();
+            let parts: Vec<&str> = parts.iter().map(String::as_str).collect();
+            match parts.as_slice() {
+                ["surah", surah] => surah.to_string(),
+                ["surah", surah, "ayah", ayah] => format!("{}:{}", surah, ayah),
+                ["juz", n] => format!("juz {}", n),
+                ["page", n] => format!("page {}", n),
+                _ => bail!("{} is not a {}: link; try {}://surah/2/ayah/255", uri, SCHEME, SCHEME),
+            }
+        }
+        None => decode(path),
+    };
+    Ok(QuranUri { reference: reference::parse(&text)?, translation })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::range::AyahRange;
+    use crate::state::AyahRef;
+
+    #[test]
+    fn parses_short_links() {
+        let at = |surah_id, ayah_index| AyahRef { surah_id, ayah_index };
+        assert_eq!(parse("quran:2:255").unwrap(), QuranUri { reference: Reference::Ayah(at(2, 255)), translation: None });
+        assert_eq!(parse("QURAN:Yasin%2012").unwrap().reference, Reference::Ayah(at(36, 12)));
+        assert_eq!(parse("quran:2:255-257?translation=sahih").unwrap(), QuranUri {
+            reference: Reference::Range(AyahRange::new(at(2, 255), at(2, 257)).unwrap()),
+            translation: Some("sahih".into()),
+        });
+        assert!(parse("https://example.com/2:255").is_err());
+        assert!(parse("quran:2:999").is_err());
+    }
+
+    #[test]
+    fn parses_long_links() {
+        let uri = parse("quran://surah/2/ayah/255?translation=en&x=1#top").unwrap();
+        assert_eq!(uri.reference, Reference::Ayah(AyahRef { surah_id: 2, ayah_index: 255 }));
+        assert_eq!(uri.translation.as_deref(), Some("en"));
+        assert_eq!(parse("quran://Surah/67/").unwrap().reference, Reference::Surah(67));
+        assert_eq!(parse("quran://juz/30").unwrap().reference, Reference::Juz(30));
+        assert!(parse("quran://ayah/255").is_err());
+    }
+}